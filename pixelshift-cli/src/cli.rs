@@ -0,0 +1,621 @@
+//! Argument parsing and output of the headless commands; the work is done by
+//! the same backend and scheduler as the GUI.
+
+use glib::ControlFlow;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use pixelshift_core::autostart::{self, AutostartKind};
+use pixelshift_core::brightness::{self, IdleDimmer};
+use pixelshift_core::capabilities::{self, CapabilityStore};
+use pixelshift_core::ddc::{self, Ddc};
+use pixelshift_core::profiles::{ProfileStore, METHOD_KEYS};
+use pixelshift_core::schedule::Schedule;
+use pixelshift_core::scheduler::{AutoShiftSession, Scheduler};
+use pixelshift_core::state::{self, ShiftState};
+use pixelshift_core::usage::{self, UsageStore, UsageTracker};
+use pixelshift_core::wear::{WearMap, WearRecorder};
+use pixelshift_core::{apply_pixel_shift, dbus, dryrun, get_connected_displays, logind, quirks, reset_display_safe, select_display, DisplayInfo, SetTextSafe};
+
+const USAGE: &str = "\
+Usage: pixelshift COMMAND [OPTIONS]
+       pixelshift-gtk [COMMAND] [OPTIONS]
+
+pixelshift-gtk without a command opens the GTK window; with one it runs pixelshift.
+
+Commands:
+  list [--json]                       List connected displays
+  shift --display X --dx N --dy N [--method M]
+                                      Apply a single offset and leave it in place
+  reset [--display X | --all]         Restore displays to their unshifted state
+  probe [--display X | --all] [--json]
+                                      Try each shift method and remember which ones work
+  run [--display X] [--amount N] [--method M] [--interval S] [--schedule RULES]
+      [--no-pattern] [--adaptive | --fixed]
+                                      Run the auto-shift scheduler in the foreground
+  status [--json]                     Show the shift currently applied, if any
+  recover [--force]                   Undo shifts and dims a crashed or killed session left behind
+  autostart [status | install [--display X] [--systemd | --desktop] | uninstall]
+                                      Run the scheduler on login for a monitor
+  wear [--display X] [--enable | --disable] [--png FILE] [--csv FILE]
+                                      Show, export or toggle a monitor's wear heatmap
+  usage [--csv FILE]                  Show powered-on, shifting and brightness hours per monitor
+  condition [--display X] [--minutes N] [--pattern STEPS]
+                                      Run pixel conditioning fullscreen until done or interrupted
+                                      (pixelshift-gtk only, as it needs GTK)
+  ddc [get CODE | set CODE VALUE | orbit [on | off] | refresh] [--display X]
+                                      Read or write VCP features, or the monitor's own orbit/refresh
+  help                                Show this message
+
+Any command takes --dry-run (or PIXELSHIFT_DRY_RUN=1 in the environment) to print and
+log the xrandr commands and DDC/CI writes it would make instead of making them.
+Displays can be given by connector name (HDMI-1) or monitor id (DEL-A0B1-12345).
+Methods: transform, panning-smooth, position, panning.
+Options not given to `run` come from the monitor's saved profile; `run` falls back to
+the next method that works when the chosen one fails or `probe` found it unsupported.
+Schedules look like \"sat-sun off; mon-fri 09:00-18:00 60s; * 10m\"; the first matching
+rule wins and --interval applies when none does. --adaptive samples the screen and
+shortens the interval while much of it is static (Wayland uses the screenshot
+portal). `run` also dims monitors whose profile sets idle-dim.";
+
+/// Status sink that prints to the terminal instead of a label
+#[derive(Clone)]
+struct ConsoleStatus;
+
+impl SetTextSafe for ConsoleStatus {
+    fn set_text_safe(&self, text: &str) {
+        println!("{}", text);
+    }
+
+    fn append_text_safe(&self, text: &str) {
+        println!("{}", text);
+    }
+}
+
+/// Run a subcommand and return the process exit code
+pub fn run(args: &[String]) -> i32 {
+    // Accepted by every command
+    let (dry_run, args): (Vec<String>, Vec<String>) = args.iter().cloned().partition(|a| a == "--dry-run");
+    if !dry_run.is_empty() {
+        dryrun::set_enabled(true);
+    }
+    let args = args.as_slice();
+
+    let Some(command) = args.first() else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let result = match command.as_str() {
+        "list" => parse_options(&args[1..], &["json"]).and_then(|o| cmd_list(&o)),
+        "shift" => parse_options(&args[1..], &[]).and_then(|o| cmd_shift(&o)),
+        "reset" => parse_options(&args[1..], &["all"]).and_then(|o| cmd_reset(&o)),
+        "probe" => parse_options(&args[1..], &["all", "json"]).and_then(|o| cmd_probe(&o)),
+        "run" => parse_options(&args[1..], &["no-pattern", "adaptive", "fixed"]).and_then(|o| cmd_run(&o)),
+        "status" => parse_options(&args[1..], &["json"]).and_then(|o| cmd_status(&o)),
+        "recover" => parse_options(&args[1..], &["force"]).and_then(|o| cmd_recover(&o)),
+        "autostart" => cmd_autostart(&args[1..]),
+        "wear" => parse_options(&args[1..], &["enable", "disable"]).and_then(|o| cmd_wear(&o)),
+        "usage" => parse_options(&args[1..], &[]).and_then(|o| cmd_usage(&o)),
+        "condition" => Err("needs GTK to draw; run `pixelshift-gtk condition` instead".to_string()),
+        "ddc" => cmd_ddc(&args[1..]),
+        "help" | "--help" | "-h" => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        _ => {
+            // A typo must not look like success to a script
+            eprintln!("pixelshift: unknown command '{}'\n{}", command, USAGE);
+            return 2;
+        }
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("pixelshift {}: {}", command, e);
+            2
+        }
+    }
+}
+
+struct Options {
+    values: HashMap<String, String>,
+    flags: Vec<String>,
+}
+
+impl Options {
+    fn value(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|f| f == name)
+    }
+
+    fn int(&self, name: &str) -> Result<Option<i32>, String> {
+        self.value(name)
+            .map(|v| v.parse::<i32>().map_err(|_| format!("--{} expects an integer, got '{}'", name, v)))
+            .transpose()
+    }
+
+    fn method(&self) -> Result<Option<u32>, String> {
+        self.value("method")
+            .map(|m| {
+                METHOD_KEYS
+                    .iter()
+                    .position(|k| *k == m)
+                    .map(|i| i as u32)
+                    .ok_or_else(|| format!("unknown method '{}' (expected one of {})", m, METHOD_KEYS.join(", ")))
+            })
+            .transpose()
+    }
+}
+
+fn parse_options(args: &[String], flags: &[&str]) -> Result<Options, String> {
+    let mut options = Options {
+        values: HashMap::new(),
+        flags: Vec::new(),
+    };
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let Some(name) = arg.strip_prefix("--") else {
+            return Err(format!("unexpected argument '{}'", arg));
+        };
+
+        // Accept both `--dx 2` and `--dx=2`
+        if let Some((name, value)) = name.split_once('=') {
+            options.values.insert(name.to_string(), value.to_string());
+        } else if flags.contains(&name) {
+            options.flags.push(name.to_string());
+        } else {
+            let value = iter.next().ok_or_else(|| format!("--{} needs a value", name))?;
+            options.values.insert(name.to_string(), value.clone());
+        }
+    }
+
+    Ok(options)
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn cmd_list(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+
+    if options.flag("json") {
+        let entries: Vec<String> = displays
+            .iter()
+            .map(|d| {
+                format!(
+                    "{{\"name\":{},\"monitor_id\":{},\"model\":{},\"width\":{},\"height\":{},\"refresh_rate\":{:.2},\"primary\":{},\"oled\":{}}}",
+                    json_string(&d.name),
+                    json_string(&d.monitor_id()),
+                    d.edid.as_ref().map(|e| json_string(&e.display_name())).unwrap_or_else(|| "null".to_string()),
+                    d.width,
+                    d.height,
+                    d.refresh_rate,
+                    d.is_primary,
+                    d.is_likely_oled(),
+                )
+            })
+            .collect();
+        println!("[{}]", entries.join(","));
+        return Ok(0);
+    }
+
+    println!("{:<12} {:<12} {:>8}  {:<7} {:<4}  MONITOR", "NAME", "RESOLUTION", "REFRESH", "PRIMARY", "OLED");
+    for d in &displays {
+        let model = d.edid.as_ref().map(|e| format!(" ({})", e.display_name())).unwrap_or_default();
+        println!(
+            "{:<12} {:<12} {:>8.2}  {:<7} {:<4}  {}{}",
+            d.name,
+            format!("{}x{}", d.width, d.height),
+            d.refresh_rate,
+            if d.is_primary { "yes" } else { "no" },
+            if d.is_likely_oled() { "yes" } else { "no" },
+            d.monitor_id(),
+            model,
+        );
+    }
+    Ok(0)
+}
+
+fn cmd_shift(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+    let display = select_display(&displays, options.value("display"))?;
+    let dx = options.int("dx")?.unwrap_or(0);
+    let dy = options.int("dy")?.unwrap_or(0);
+    let method_idx = match options.method()? {
+        Some(m) => m,
+        None => ProfileStore::load().get(&display.monitor_id()).map(|p| p.method_idx).unwrap_or(0),
+    };
+
+    if apply_pixel_shift(method_idx, &display, dx, dy, &ConsoleStatus) {
+        state::save(&ShiftState::new(None, &display.name, method_idx, (dx, dy)));
+        Ok(0)
+    } else {
+        Ok(1)
+    }
+}
+
+fn cmd_reset(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+    let targets = if options.flag("all") {
+        displays
+    } else {
+        vec![select_display(&displays, options.value("display"))?]
+    };
+
+    let mut ok = true;
+    for display in &targets {
+        ok &= reset_display_safe(display, &ConsoleStatus);
+    }
+
+    if state::load().is_some_and(|s| targets.iter().any(|d| d.name == s.display)) {
+        state::clear();
+    }
+    Ok(if ok { 0 } else { 1 })
+}
+
+fn cmd_probe(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+    let targets = if options.flag("all") {
+        displays
+    } else {
+        vec![select_display(&displays, options.value("display"))?]
+    };
+
+    let mut store = CapabilityStore::load();
+    let mut entries = Vec::new();
+    let mut ok = true;
+    for display in &targets {
+        // With --all one refusal should not cost the others their results
+        let support = match capabilities::probe(display) {
+            Ok(support) => support,
+            Err(e) if options.flag("all") => {
+                eprintln!("✗ Cannot probe {}: {}", display.name, e);
+                ok = false;
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        if options.flag("json") {
+            let methods: Vec<String> = METHOD_KEYS
+                .iter()
+                .enumerate()
+                .map(|(i, key)| {
+                    format!(
+                        "{{\"method\":{},\"supported\":{},\"reason\":{}}}",
+                        json_string(key),
+                        support.supports(i as u32),
+                        support.reason(i as u32).map(json_string).unwrap_or_else(|| "null".to_string()),
+                    )
+                })
+                .collect();
+            entries.push(format!(
+                "{{\"name\":{},\"monitor_id\":{},\"methods\":[{}]}}",
+                json_string(&display.name),
+                json_string(&display.monitor_id()),
+                methods.join(",")
+            ));
+        } else {
+            println!("{} ({})", display.name, display.monitor_id());
+            for line in support.summary() {
+                println!("  {}", line);
+            }
+        }
+        store.insert(&display.monitor_id(), support);
+    }
+    if options.flag("json") {
+        println!("[{}]", entries.join(","));
+    }
+
+    store.save().map_err(|e| format!("cannot save {}: {}", CapabilityStore::default_path().display(), e))?;
+    Ok(if ok { 0 } else { 1 })
+}
+
+fn cmd_run(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+    let display = select_display(&displays, options.value("display"))?;
+    let profile = ProfileStore::load().get(&display.monitor_id()).cloned().unwrap_or_default();
+
+    let shift_amount = options.int("amount")?.unwrap_or(profile.shift_amount).clamp(1, 10);
+    let method_idx = options.method()?.unwrap_or(profile.method_idx);
+    let interval_secs = options.int("interval")?.map(|i| i as u32).unwrap_or(profile.interval_secs).clamp(5, 300);
+    let use_pattern = !options.flag("no-pattern") && profile.use_pattern;
+    let adaptive = options.flag("adaptive") || (profile.adaptive && !options.flag("fixed"));
+    let schedule = Schedule::parse(options.value("schedule").unwrap_or(&profile.schedule)).map_err(|e| format!("invalid schedule: {}", e))?;
+
+    let main_loop = glib::MainLoop::new(None, false);
+    let scheduler = Scheduler::new(ConsoleStatus);
+    scheduler.start(AutoShiftSession::new(display, shift_amount, method_idx, use_pattern, interval_secs as u64).with_schedule(schedule).with_adaptive(adaptive).with_hardware_orbit(profile.hardware_orbit));
+
+    let sleep_watcher = logind::SleepWatcher::connect({
+        let scheduler = scheduler.clone();
+        move |going_to_sleep| {
+            if going_to_sleep {
+                scheduler.suspend();
+            } else {
+                scheduler.wake();
+            }
+        }
+    });
+    if let Err(e) = &sleep_watcher {
+        eprintln!("Suspend handling unavailable: {}", e);
+    }
+
+    // Restore the display on Ctrl-C or `systemctl stop`
+    let quit = Rc::new({
+        let scheduler = scheduler.clone();
+        let main_loop = main_loop.clone();
+        move || {
+            scheduler.stop();
+            main_loop.quit();
+            ControlFlow::Break
+        }
+    });
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        let quit = quit.clone();
+        glib::unix_signal_add_local(signal, move || quit());
+    }
+
+    let dbus_owner = dbus::own_name(&scheduler);
+    let wear_recorder = WearRecorder::start(&scheduler);
+    let usage_tracker = UsageTracker::start(&scheduler);
+    let idle_dimmer = IdleDimmer::start(&scheduler, ConsoleStatus);
+
+    main_loop.run();
+    gio::bus_unown_name(dbus_owner);
+    drop(idle_dimmer);
+    drop(usage_tracker);
+    drop(wear_recorder);
+    drop(sleep_watcher);
+    Ok(0)
+}
+
+fn cmd_status(options: &Options) -> Result<i32, String> {
+    let current = state::load();
+
+    if options.flag("json") {
+        match &current {
+            Some(s) => println!(
+                "{{\"active\":true,\"display\":{},\"method\":{},\"x\":{},\"y\":{},\"pid\":{},\"owner_running\":{},\"updated\":{}}}",
+                json_string(&s.display),
+                json_string(&s.method),
+                s.offset.0,
+                s.offset.1,
+                s.pid.map(|p| p.to_string()).unwrap_or_else(|| "null".to_string()),
+                s.owner_running(),
+                s.updated,
+            ),
+            None => println!("{{\"active\":false}}"),
+        }
+        return Ok(0);
+    }
+
+    match current {
+        Some(s) => {
+            println!("Display: {}", s.display);
+            println!("Method:  {}", s.method);
+            println!("Offset:  {:+}{:+}", s.offset.0, s.offset.1);
+            match s.pid {
+                Some(pid) if s.owner_running() => println!("Owner:   pid {} (running)", pid),
+                Some(pid) => println!("Owner:   pid {} (not running; use `recover` to reset)", pid),
+                None => println!("Owner:   none (one-off shift)"),
+            }
+        }
+        None => println!("No shift applied."),
+    }
+    Ok(0)
+}
+
+fn cmd_recover(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+    let current = state::load();
+
+    if let Some(ref s) = current {
+        if s.owner_running() && !options.flag("force") {
+            return Err(format!(
+                "pid {} is still shifting {}; stop it first or pass --force",
+                s.pid.unwrap_or(0),
+                s.display
+            ));
+        }
+    }
+
+    // Without a record we cannot know what was touched, so reset everything
+    let targets: Vec<&DisplayInfo> = match &current {
+        Some(s) => displays.iter().filter(|d| d.name == s.display).collect(),
+        None => displays.iter().collect(),
+    };
+    let targets = if targets.is_empty() { displays.iter().collect() } else { targets };
+
+    let mut ok = true;
+    for display in targets {
+        ok &= reset_display_safe(display, &ConsoleStatus);
+    }
+    state::clear();
+
+    // Idle dims are recorded the same way; a running dimmer puts its own back
+    for record in brightness::dim_records() {
+        if record.owner_running() && record.pid != std::process::id() && !options.flag("force") {
+            println!("pid {} dimmed {}; it restores the brightness itself", record.pid, record.display);
+            continue;
+        }
+        let Some(display) = displays.iter().find(|d| d.name == record.display) else {
+            println!("{} is not connected; keeping its brightness record for later", record.display);
+            continue;
+        };
+        match record.method.write(display, record.previous) {
+            Ok(()) => {
+                brightness::clear_dim_record(&record.display);
+                println!("✓ Brightness restored on {}", record.display);
+            }
+            Err(e) => {
+                println!("✗ Could not restore brightness on {}: {}", record.display, e);
+                ok = false;
+            }
+        }
+    }
+    Ok(if ok { 0 } else { 1 })
+}
+
+fn cmd_autostart(args: &[String]) -> Result<i32, String> {
+    let action = args.first().map(String::as_str).unwrap_or("status");
+    let rest = args.get(1..).unwrap_or_default();
+
+    match action {
+        "status" => {
+            parse_options(rest, &[])?;
+            match autostart::status() {
+                Some(status) => println!("{} ({})", status.summary(), status.kind.path().display()),
+                None => println!("Autostart is not installed."),
+            }
+            Ok(0)
+        }
+        "install" => {
+            let options = parse_options(rest, &["systemd", "desktop"])?;
+            let kind = match (options.flag("systemd"), options.flag("desktop")) {
+                (true, true) => return Err("--systemd and --desktop are mutually exclusive".to_string()),
+                (true, false) => AutostartKind::Systemd,
+                (false, true) => AutostartKind::Desktop,
+                (false, false) => AutostartKind::preferred(),
+            };
+            let display = select_display(&get_connected_displays(), options.value("display"))?;
+            let path = autostart::install(kind, &display)?;
+            println!("Installed {} for {} at {}", kind.describe(), display.monitor_id(), path.display());
+            Ok(0)
+        }
+        "uninstall" => {
+            parse_options(rest, &[])?;
+            autostart::uninstall()?;
+            println!("Autostart removed.");
+            Ok(0)
+        }
+        _ => Err(format!("unknown action '{}' (expected status, install or uninstall)", action)),
+    }
+}
+
+fn cmd_wear(options: &Options) -> Result<i32, String> {
+    let displays = get_connected_displays();
+    let display = select_display(&displays, options.value("display"))?;
+    let id = display.monitor_id();
+
+    if options.flag("enable") || options.flag("disable") {
+        let mut profiles = ProfileStore::load();
+        let mut profile = profiles.get(&id).cloned().unwrap_or_default();
+        profile.wear = options.flag("enable");
+        profiles.insert(&id, profile);
+        profiles.save().map_err(|e| format!("cannot save profile: {}", e))?;
+        println!(
+            "Wear recording {} for {} (samples are taken while the app or `run` is active).",
+            if options.flag("enable") { "enabled" } else { "disabled" },
+            id
+        );
+    }
+
+    let map = WearMap::load(&id);
+    println!("Monitor: {}", id);
+    println!("Sampled: {:.1} h", map.sampled_secs / 3600.0);
+    match map.hottest() {
+        Some((col, row, risk)) => println!(
+            "Hottest: {}x{} region at +{}+{} ({:.0}% static full-brightness time)",
+            display.width / map.cols as u32,
+            display.height / map.rows as u32,
+            display.x + (col as u32 * display.width / map.cols as u32) as i32,
+            display.y + (row as u32 * display.height / map.rows as u32) as i32,
+            risk * 100.0
+        ),
+        None => println!("Hottest: no static content recorded yet"),
+    }
+
+    if let Some(path) = options.value("png") {
+        map.export_png(&display, std::path::Path::new(path)).map_err(|e| format!("cannot write {}: {}", path, e))?;
+        println!("Wrote {}", path);
+    }
+    if let Some(path) = options.value("csv") {
+        std::fs::write(path, map.to_csv(&display)).map_err(|e| format!("cannot write {}: {}", path, e))?;
+        println!("Wrote {}", path);
+    }
+    Ok(0)
+}
+
+fn cmd_usage(options: &Options) -> Result<i32, String> {
+    let store = UsageStore::load();
+
+    if let Some(path) = options.value("csv") {
+        std::fs::write(path, store.to_csv()).map_err(|e| format!("cannot write {}: {}", path, e))?;
+        println!("Wrote {}", path);
+        return Ok(0);
+    }
+
+    if store.records.is_empty() {
+        println!("No usage recorded yet.");
+        return Ok(0);
+    }
+
+    println!("{:<24} {:>10} {:>10} {:>8} {:>10}  NAME", "MONITOR", "ON", "SHIFTING", "COVERED", "BRIGHTNESS");
+    for (id, record) in store.sorted() {
+        println!(
+            "{:<24} {:>10} {:>10} {:>7.0}% {:>10}  {}",
+            id,
+            usage::format_hours(record.on_secs),
+            usage::format_hours(record.shifting_secs),
+            record.coverage() * 100.0,
+            record.mean_brightness().map(|b| format!("{:.0}%", b * 100.0)).unwrap_or_else(|| "-".to_string()),
+            record.name,
+        );
+    }
+    Ok(0)
+}
+
+fn cmd_ddc(args: &[String]) -> Result<i32, String> {
+    // Positional arguments come first, then options
+    let split = args.iter().position(|a| a.starts_with("--")).unwrap_or(args.len());
+    let (positional, rest) = args.split_at(split);
+    let options = parse_options(rest, &[])?;
+    let positional: Vec<&str> = positional.iter().map(String::as_str).collect();
+    let number = |text: &str| ddc::parse_number(text).ok_or_else(|| format!("'{}' is not a number", text));
+    let code = |text: &str| number(text).and_then(|c| u8::try_from(c).map_err(|_| format!("'{}' is not a VCP code", text)));
+
+    let display = select_display(&get_connected_displays(), options.value("display"))?;
+    match positional[..] {
+        ["get", vcp] => {
+            let value = Ddc::for_display(&display)?.get_vcp(code(vcp)?)?;
+            println!("VCP 0x{:02x}: {} (max {})", code(vcp)?, value.current, value.maximum);
+        }
+        ["set", vcp, value] => {
+            Ddc::for_display(&display)?.set_vcp(code(vcp)?, number(value)?)?;
+            println!("Set VCP 0x{:02x} to {} on {}", code(vcp)?, number(value)?, display.name);
+        }
+        ["orbit"] => match quirks::orbit_state(&display, &ConsoleStatus) {
+            Some(state) => println!("Built-in pixel orbit on {} is {}", display.name, if state? { "on" } else { "off" }),
+            None => return Err(format!("no orbit control known for {} (see ddc-quirks.ini)", display.name)),
+        },
+        ["orbit", state @ ("on" | "off")] => {
+            quirks::set_orbit(&display, state == "on", &ConsoleStatus)?;
+            println!("Built-in pixel orbit on {} switched {}", display.name, state);
+        }
+        ["refresh"] => {
+            quirks::start_refresh(&display, &ConsoleStatus)?;
+            println!("Started the panel refresh on {}; the screen may go dark for a while.", display.name);
+        }
+        _ => return Err("expected get CODE, set CODE VALUE, orbit [on|off] or refresh".to_string()),
+    }
+    Ok(0)
+}
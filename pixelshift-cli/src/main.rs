@@ -0,0 +1,11 @@
+//! `pixelshift`: the headless commands on their own, without GTK.
+
+mod cli;
+
+use pixelshift_core::dryrun;
+
+fn main() {
+    dryrun::init_from_env();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    std::process::exit(cli::run(&args));
+}
@@ -0,0 +1,527 @@
+//! Shared test harness: runs the `pixelshift` binary with a scripted fake
+//! `xrandr` first on `PATH`, or against a private Xvfb.
+//!
+//! The fake appends each invocation's arguments to a log and answers from
+//! rules added with `respond`/`fail`: shell `case` patterns matched against
+//! the joined arguments, first match wins, anything unmatched succeeds
+//! silently. Config, cache, data and runtime directories point into the
+//! test's own scratch directory so nothing leaks between tests or into the
+//! real session.
+//!
+//! `spawn` keeps a long-running command such as `run` going in the background
+//! and collects what it prints, so a test can wait for a message and then
+//! stop it the way `systemctl stop` would. `ddc_emulator` serves a software
+//! monitor on a socket for `PIXELSHIFT_DDC_DEVICE`.
+//!
+//! `DbusDaemon::start` gives a test its own session bus, and `MockLogind`
+//! plays logind on it for `PIXELSHIFT_LOGIND_BUS=session`. Without
+//! `dbus-daemon` the test skips itself unless `PIXELSHIFT_REQUIRE_DBUS` is set.
+//!
+//! `Xvfb::start` gives each test its own server with RandR. Without `Xvfb`
+//! and `xrandr` installed it returns `None` and the test skips itself, unless
+//! `PIXELSHIFT_REQUIRE_XVFB` is set (as CI should), in which case it fails.
+
+#![allow(dead_code)]
+
+use gio::prelude::*;
+use gio::{DBusCallFlags, DBusConnection, DBusConnectionFlags, DBusNodeInfo, UnixFDList};
+use pixelshift_core::ddc::{Ddc, Emulator, VcpValue};
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Output, Stdio};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SCRIPT: &str = r#"#!/bin/sh
+dir=$(dirname "$0")
+printf '%s\n' "$*" >> "$dir/invocations"
+n=0
+while [ -e "$dir/rules/$n.pattern" ]; do
+    case "$*" in
+        $(cat "$dir/rules/$n.pattern"))
+            cat "$dir/rules/$n.stdout"
+            cat "$dir/rules/$n.stderr" >&2
+            exit "$(cat "$dir/rules/$n.code")"
+            ;;
+    esac
+    n=$((n + 1))
+done
+exit 0
+"#;
+
+/// `xrandr --query` for an OLED on HDMI-1 and an LCD to its right
+pub const QUERY: &str = "\
+Screen 0: minimum 8 x 8, current 4480 x 1440, maximum 32767 x 32767
+HDMI-1 connected primary 2560x1440+0+0 (normal left inverted right x axis y axis) 600mm x 340mm
+   2560x1440     59.95*+  120.00
+   1920x1080     60.00
+DP-1 disconnected (normal left inverted right x axis y axis)
+DP-2 connected 1920x1080+2560+0 (normal left inverted right x axis y axis) 530mm x 300mm
+   1920x1080     60.00*+  74.97
+";
+
+pub struct FakeXrandr {
+    dir: PathBuf,
+    rules: usize,
+}
+
+impl FakeXrandr {
+    /// Fresh scratch directory named after the test, with `QUERY` and EDIDs canned
+    pub fn new(test: &str) -> Self {
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(test);
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("rules")).unwrap();
+        let script = dir.join("xrandr");
+        fs::write(&script, SCRIPT).unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let mut fake = Self { dir, rules: 0 };
+        fake.respond("--query --props", &props(&[("HDMI-1", &edid("LGD", 0x0617, "OLED 27GS95", "1A2B3C")), ("DP-2", &edid("DEL", 0xA0B1, "DELL U2720Q", "XYZ"))]));
+        fake.respond("--query", QUERY);
+        fake
+    }
+
+    /// Print `stdout` and succeed when the arguments match `pattern`
+    pub fn respond(&mut self, pattern: &str, stdout: &str) -> &mut Self {
+        self.rule(pattern, 0, stdout, "")
+    }
+
+    /// Print `stderr` and exit 1 when the arguments match `pattern`
+    pub fn fail(&mut self, pattern: &str, stderr: &str) -> &mut Self {
+        self.rule(pattern, 1, "", stderr)
+    }
+
+    /// Rules added later only apply where earlier ones don't match, so the
+    /// canned queries from `new` can be overridden by clearing them first
+    pub fn clear(&mut self) -> &mut Self {
+        let _ = fs::remove_dir_all(self.dir.join("rules"));
+        fs::create_dir_all(self.dir.join("rules")).unwrap();
+        self.rules = 0;
+        self
+    }
+
+    fn rule(&mut self, pattern: &str, code: i32, stdout: &str, stderr: &str) -> &mut Self {
+        let base = self.dir.join("rules").join(self.rules.to_string());
+        fs::write(base.with_extension("stdout"), stdout).unwrap();
+        fs::write(base.with_extension("stderr"), stderr).unwrap();
+        fs::write(base.with_extension("code"), code.to_string()).unwrap();
+        // Written last: the script stops at the first rule without a pattern
+        fs::write(base.with_extension("pattern"), pattern).unwrap();
+        self.rules += 1;
+        self
+    }
+
+    /// Arguments of every call so far, oldest first
+    pub fn invocations(&self) -> Vec<String> {
+        fs::read_to_string(self.dir.join("invocations"))
+            .map(|log| log.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+
+    /// Calls other than the display queries every command starts with
+    pub fn changes(&self) -> Vec<String> {
+        self.invocations().into_iter().filter(|i| !i.starts_with("--query")).collect()
+    }
+
+    pub fn forget_invocations(&self) {
+        let _ = fs::remove_file(self.dir.join("invocations"));
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The binary with `args` against this fake, for adding variables
+    pub fn command(&self, args: &[&str]) -> Command {
+        let path = format!("{}:{}", self.dir.display(), std::env::var("PATH").unwrap_or_default());
+        let mut command = pixelshift(&self.home(), args);
+        command.env("PATH", path);
+        command
+    }
+
+    /// Run the binary with `args` against this fake
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().expect("cannot run pixelshift")
+    }
+
+    /// Where the binary's config, cache, data and runtime directories live
+    pub fn home(&self) -> PathBuf {
+        self.dir.join("home")
+    }
+
+    /// Write `profiles.ini` with one group per `(monitor id, settings)`
+    pub fn profiles(&self, profiles: &[(&str, &str)]) {
+        let dir = self.home().join("config/pixelshift-gtk");
+        fs::create_dir_all(&dir).unwrap();
+        let text: String = profiles.iter().map(|(id, settings)| format!("[profile {}]\n{}\n", id, settings)).collect();
+        fs::write(dir.join("profiles.ini"), format!("[meta]\nversion=1\n\n{}", text)).unwrap();
+    }
+}
+
+/// A command running in the background; killed if the test ends early
+pub struct Running {
+    child: Child,
+    stdout: Arc<Mutex<String>>,
+}
+
+/// Start `command` with its output collected
+pub fn spawn(mut command: Command) -> Running {
+    let mut child = command.stdout(Stdio::piped()).stderr(Stdio::null()).spawn().expect("cannot start pixelshift");
+    let stdout = Arc::new(Mutex::new(String::new()));
+    let mut pipe = child.stdout.take().unwrap();
+    let collected = stdout.clone();
+    std::thread::spawn(move || {
+        let mut buf = [0u8; 4096];
+        while let Ok(n) = pipe.read(&mut buf) {
+            if n == 0 {
+                break;
+            }
+            collected.lock().unwrap().push_str(&String::from_utf8_lossy(&buf[..n]));
+        }
+    });
+    Running { child, stdout }
+}
+
+impl Running {
+    pub fn pid(&self) -> u32 {
+        self.child.id()
+    }
+
+    /// Everything printed so far
+    pub fn stdout(&self) -> String {
+        self.stdout.lock().unwrap().clone()
+    }
+
+    /// Wait until the output contains `text`; panics with the output on timeout
+    pub fn wait_for(&self, text: &str, timeout: Duration) {
+        let deadline = Instant::now() + timeout;
+        while !self.stdout().contains(text) {
+            assert!(Instant::now() < deadline, "timed out waiting for {:?}; output so far:\n{}", text, self.stdout());
+            std::thread::sleep(Duration::from_millis(50));
+        }
+    }
+
+    /// SIGTERM, as `systemctl stop` sends, then everything it printed
+    pub fn stop(mut self) -> String {
+        unsafe { libc::kill(self.child.id() as libc::pid_t, libc::SIGTERM) };
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while self.child.try_wait().unwrap().is_none() {
+            assert!(Instant::now() < deadline, "did not exit on SIGTERM; output so far:\n{}", self.stdout());
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        // Let the reader thread drain the pipe
+        std::thread::sleep(Duration::from_millis(100));
+        self.stdout()
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Serve a software monitor with `features` (`0x10=80/100; ...`) on a socket
+/// in `dir`, for the rest of the test process
+pub fn ddc_emulator(dir: &Path, features: &str) -> PathBuf {
+    let socket = dir.join("ddc.sock");
+    let emulator = Emulator::new(Emulator::parse_features(features).unwrap());
+    let path = socket.clone();
+    std::thread::spawn(move || emulator.serve(&path));
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !socket.exists() {
+        assert!(Instant::now() < deadline, "DDC emulator did not start");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    socket
+}
+
+/// What the monitor behind `socket` reports for `code`
+pub fn vcp(socket: &Path, code: u8) -> VcpValue {
+    Ddc::open(socket).unwrap().get_vcp(code).unwrap()
+}
+
+/// The binary with a clean environment living under `home`
+fn pixelshift(home: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new(env!("CARGO_BIN_EXE_pixelshift"));
+    command
+        .args(args)
+        .env_clear()
+        .env("PATH", std::env::var("PATH").unwrap_or_default())
+        .env("HOME", home)
+        .env("XDG_CONFIG_HOME", home.join("config"))
+        .env("XDG_CACHE_HOME", home.join("cache"))
+        .env("XDG_DATA_HOME", home.join("data"))
+        .env("XDG_RUNTIME_DIR", home.join("run"));
+    command
+}
+
+fn on_path(program: &str) -> bool {
+    std::env::var_os("PATH").is_some_and(|path| std::env::split_paths(&path).any(|dir| dir.join(program).is_file()))
+}
+
+/// A private session bus for one test
+pub struct DbusDaemon {
+    daemon: Child,
+    address: String,
+}
+
+impl DbusDaemon {
+    pub fn start(test: &str) -> Option<Self> {
+        if !on_path("dbus-daemon") {
+            assert!(std::env::var_os("PIXELSHIFT_REQUIRE_DBUS").is_none(), "PIXELSHIFT_REQUIRE_DBUS is set but dbus-daemon is missing");
+            eprintln!("skipping {}: dbus-daemon is not installed", test);
+            return None;
+        }
+        let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{}-bus", test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .arg(format!("--address=unix:path={}", dir.join("socket").display()))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("cannot start dbus-daemon");
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap()).read_line(&mut address).unwrap();
+        assert!(!address.trim().is_empty(), "dbus-daemon printed no address");
+        Some(Self { daemon, address: address.trim().to_string() })
+    }
+
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// A new connection from the test itself
+    pub fn connect(&self) -> DBusConnection {
+        DBusConnection::for_address_sync(
+            &self.address,
+            DBusConnectionFlags::AUTHENTICATION_CLIENT | DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+            None,
+            gio::Cancellable::NONE,
+        )
+        .expect("cannot connect to the test bus")
+    }
+}
+
+impl Drop for DbusDaemon {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+const LOGIND_XML: &str = r#"<node>
+  <interface name="org.freedesktop.login1.Manager">
+    <method name="Inhibit">
+      <arg type="s" direction="in"/><arg type="s" direction="in"/><arg type="s" direction="in"/><arg type="s" direction="in"/>
+      <arg type="h" direction="out"/>
+    </method>
+    <signal name="PrepareForSleep"><arg type="b"/></signal>
+  </interface>
+</node>"#;
+
+/// Enough of logind on a test bus: hands out delay inhibitors and sends
+/// PrepareForSleep
+pub struct MockLogind {
+    connection: DBusConnection,
+    inhibitors: Arc<AtomicUsize>,
+}
+
+impl MockLogind {
+    /// Owns `org.freedesktop.login1` on `bus`, answering calls on a thread of its own
+    pub fn start(bus: &DbusDaemon) -> Self {
+        let address = bus.address().to_string();
+        let inhibitors = Arc::new(AtomicUsize::new(0));
+        let counter = inhibitors.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+
+        std::thread::spawn(move || {
+            let context = glib::MainContext::new();
+            let _guard = context.acquire().unwrap();
+            context
+                .with_thread_default(|| {
+                    let connection = DBusConnection::for_address_sync(
+                        &address,
+                        DBusConnectionFlags::AUTHENTICATION_CLIENT | DBusConnectionFlags::MESSAGE_BUS_CONNECTION,
+                        None,
+                        gio::Cancellable::NONE,
+                    )
+                    .unwrap();
+                    let info = DBusNodeInfo::for_xml(LOGIND_XML).unwrap();
+                    let interface = info.lookup_interface("org.freedesktop.login1.Manager").unwrap();
+                    let _registration = connection
+                        .register_object("/org/freedesktop/login1", &interface)
+                        .method_call(move |_, _, _, _, _, _, invocation| {
+                            counter.fetch_add(1, Ordering::SeqCst);
+                            // Any fd will do; logind's is closed to release the delay
+                            let fds = UnixFDList::new();
+                            let file = fs::File::open("/dev/null").unwrap();
+                            fds.append(file).unwrap();
+                            invocation.return_value_with_unix_fd_list(Some(&(glib::variant::Handle(0),).to_variant()), Some(&fds));
+                        })
+                        .build()
+                        .unwrap();
+                    connection
+                        .call_sync(
+                            Some("org.freedesktop.DBus"),
+                            "/org/freedesktop/DBus",
+                            "org.freedesktop.DBus",
+                            "RequestName",
+                            Some(&("org.freedesktop.login1", 0u32).to_variant()),
+                            None,
+                            DBusCallFlags::NONE,
+                            -1,
+                            gio::Cancellable::NONE,
+                        )
+                        .unwrap();
+                    sender.send(connection).unwrap();
+                    glib::MainLoop::new(Some(&context), false).run();
+                })
+                .unwrap();
+        });
+
+        let connection = receiver.recv_timeout(Duration::from_secs(10)).expect("mock logind did not start");
+        Self { connection, inhibitors }
+    }
+
+    /// Delay inhibitors handed out so far
+    pub fn inhibitors(&self) -> usize {
+        self.inhibitors.load(Ordering::SeqCst)
+    }
+
+    pub fn prepare_for_sleep(&self, going_to_sleep: bool) {
+        self.connection
+            .emit_signal(None, "/org/freedesktop/login1", "org.freedesktop.login1.Manager", "PrepareForSleep", Some(&(going_to_sleep,).to_variant()))
+            .unwrap();
+        self.connection.flush_sync(gio::Cancellable::NONE).unwrap();
+    }
+}
+
+/// Display numbers handed out to servers of this test process
+static NEXT_DISPLAY: AtomicU32 = AtomicU32::new(0);
+
+pub struct Xvfb {
+    server: Child,
+    display: String,
+    home: PathBuf,
+}
+
+impl Xvfb {
+    pub fn start(test: &str) -> Option<Self> {
+        if !on_path("Xvfb") || !on_path("xrandr") {
+            assert!(std::env::var_os("PIXELSHIFT_REQUIRE_XVFB").is_none(), "PIXELSHIFT_REQUIRE_XVFB is set but Xvfb or xrandr is missing");
+            eprintln!("skipping {}: Xvfb or xrandr is not installed", test);
+            return None;
+        }
+        let home = Path::new(env!("CARGO_TARGET_TMPDIR")).join(test);
+        let _ = fs::remove_dir_all(&home);
+        fs::create_dir_all(&home).unwrap();
+
+        // Spread parallel test processes apart, then skip numbers in use
+        let base = 100 + (std::process::id() % 200) * 20;
+        for _ in 0..20 {
+            let number = base + NEXT_DISPLAY.fetch_add(1, Ordering::Relaxed);
+            if Path::new(&format!("/tmp/.X{}-lock", number)).exists() {
+                continue;
+            }
+            let mut server = Command::new("Xvfb")
+                .arg(format!(":{}", number))
+                .args(["-screen", "0", "1920x1080x24", "+extension", "RANDR", "-nolisten", "tcp"])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn()
+                .expect("cannot start Xvfb");
+
+            let socket = PathBuf::from(format!("/tmp/.X11-unix/X{}", number));
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while server.try_wait().ok().flatten().is_none() && Instant::now() < deadline {
+                if socket.exists() {
+                    let xvfb = Self { server, display: format!(":{}", number), home };
+                    // The socket appears slightly before the server answers
+                    while !xvfb.xrandr(&["--query"]).status.success() {
+                        assert!(Instant::now() < deadline, "Xvfb on :{} does not answer", number);
+                        std::thread::sleep(Duration::from_millis(50));
+                    }
+                    return Some(xvfb);
+                }
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            let _ = server.kill();
+            let _ = server.wait();
+        }
+        panic!("could not start Xvfb");
+    }
+
+    /// The binary with `args` on this server, for adding variables
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = pixelshift(&self.home, args);
+        command.env("DISPLAY", &self.display);
+        command
+    }
+
+    /// Run the binary with `args` on this server
+    pub fn run(&self, args: &[&str]) -> Output {
+        self.command(args).output().expect("cannot run pixelshift")
+    }
+
+    pub fn xrandr(&self, args: &[&str]) -> Output {
+        Command::new("xrandr").args(args).env("DISPLAY", &self.display).output().expect("cannot run xrandr")
+    }
+}
+
+impl Drop for Xvfb {
+    fn drop(&mut self) {
+        let _ = self.server.kill();
+        let _ = self.server.wait();
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).into_owned()
+}
+
+/// Minimal EDID base block with a model name and serial string
+pub fn edid(manufacturer: &str, product_code: u16, model: &str, serial: &str) -> Vec<u8> {
+    let mut data = vec![0u8; 128];
+    data[..8].copy_from_slice(&[0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00]);
+    let letters: Vec<u16> = manufacturer.bytes().map(|b| (b - b'A' + 1) as u16).collect();
+    data[8..10].copy_from_slice(&(letters[0] << 10 | letters[1] << 5 | letters[2]).to_be_bytes());
+    data[10..12].copy_from_slice(&product_code.to_le_bytes());
+    data[18] = 1;
+    data[19] = 4;
+
+    for (slot, (tag, text)) in [(0xfc, model), (0xff, serial)].into_iter().enumerate() {
+        let start = 54 + 18 * slot;
+        data[start + 3] = tag;
+        let mut field = [b' '; 13];
+        let len = text.len().min(12);
+        field[..len].copy_from_slice(&text.as_bytes()[..len]);
+        field[len] = b'\n';
+        data[start + 5..start + 18].copy_from_slice(&field);
+    }
+    let sum = data[..127].iter().fold(0u8, |acc, b| acc.wrapping_add(*b));
+    data[127] = 0u8.wrapping_sub(sum);
+    data
+}
+
+/// `xrandr --query --props` listing an EDID property per output
+pub fn props(outputs: &[(&str, &[u8])]) -> String {
+    let mut text = String::from("Screen 0: minimum 8 x 8, current 4480 x 1440, maximum 32767 x 32767\n");
+    for (name, edid) in outputs {
+        text.push_str(&format!("{} connected 0x0+0+0 (normal left inverted right x axis y axis)\n", name));
+        text.push_str("\tEDID: \n");
+        for chunk in edid.chunks(16) {
+            let hex: String = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+            text.push_str(&format!("\t\t{}\n", hex));
+        }
+        text.push_str("\tnon-desktop: 0 \n\t\tsupported: 0, 1\n");
+    }
+    text
+}
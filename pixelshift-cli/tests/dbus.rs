@@ -0,0 +1,185 @@
+//! The `com.example.PixelShift` interface of `run`, called on a private session bus.
+
+mod common;
+
+use common::{spawn, DbusDaemon, FakeXrandr, Running};
+use gio::prelude::*;
+use gio::{DBusCallFlags, DBusConnection, DBusSignalFlags};
+use glib::Variant;
+use pixelshift_core::dbus::{BUS_NAME, INTERFACE, OBJECT_PATH};
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+const WAIT: Duration = Duration::from_secs(10);
+
+/// The test's end of the bus, with signals collected as they arrive
+struct Client {
+    context: glib::MainContext,
+    connection: DBusConnection,
+    signals: Rc<RefCell<Vec<(String, Variant)>>>,
+}
+
+impl Client {
+    fn new(bus: &DbusDaemon) -> Self {
+        let context = glib::MainContext::new();
+        let signals = Rc::new(RefCell::new(Vec::new()));
+        let connection = context.with_thread_default(|| {
+            let connection = bus.connect();
+            let collected = signals.clone();
+            connection.signal_subscribe(None, None, None, Some(OBJECT_PATH), None, DBusSignalFlags::NONE, move |_, _, _, interface, name, args| {
+                let name = if interface == INTERFACE { name.to_string() } else { format!("{}.{}", interface, name) };
+                collected.borrow_mut().push((name, args.clone()));
+            });
+            connection
+        });
+        Self { context, connection: connection.unwrap(), signals }
+    }
+
+    fn wait_until(&self, what: &str, done: impl Fn(&Self) -> bool) {
+        let deadline = Instant::now() + WAIT;
+        while !done(self) {
+            assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+            while self.context.iteration(false) {}
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+
+    fn call(&self, method: &str, args: Option<Variant>) -> Result<(), String> {
+        self.connection
+            .call_sync(Some(BUS_NAME), OBJECT_PATH, INTERFACE, method, args.as_ref(), None, DBusCallFlags::NONE, 5000, gio::Cancellable::NONE)
+            .map(|_| ())
+            // `GDBus.Error:<name>: <message>`
+            .map_err(|e| e.message().trim_start_matches("GDBus.Error:").split(':').next().unwrap_or_default().to_string())
+    }
+
+    fn property(&self, name: &str) -> Variant {
+        let reply = self
+            .connection
+            .call_sync(
+                Some(BUS_NAME),
+                OBJECT_PATH,
+                "org.freedesktop.DBus.Properties",
+                "Get",
+                Some(&(INTERFACE, name).to_variant()),
+                None,
+                DBusCallFlags::NONE,
+                5000,
+                gio::Cancellable::NONE,
+            )
+            .unwrap_or_else(|e| panic!("cannot read {}: {}", name, e));
+        reply.child_value(0).as_variant().unwrap()
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.property(name).get().unwrap()
+    }
+
+    fn signal(&self, name: &str) -> Option<Variant> {
+        self.signals.borrow().iter().rev().find(|(n, _)| n == name).map(|(_, args)| args.clone())
+    }
+
+    fn forget_signals(&self) {
+        self.signals.borrow_mut().clear();
+    }
+}
+
+fn error_name(name: &str) -> String {
+    format!("{}.Error.{}", INTERFACE, name)
+}
+
+fn start(test: &str) -> Option<(DbusDaemon, FakeXrandr, Running, Client)> {
+    let bus = DbusDaemon::start(test)?;
+    let fake = FakeXrandr::new(test);
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--method", "position", "--interval", "300"]);
+    command.env("DBUS_SESSION_BUS_ADDRESS", bus.address()).env("PIXELSHIFT_LOGIND_BUS", "session");
+    let run = spawn(command);
+    let client = Client::new(&bus);
+    client.wait_until("the interface", |c| c.call("Resume", None).is_err_and(|e| e == error_name("NotPaused")));
+    Some((bus, fake, run, client))
+}
+
+#[test]
+fn properties_describe_the_running_session() {
+    let Some((_bus, _fake, run, client)) = start("dbus_properties") else { return };
+
+    assert!(client.flag("Running"));
+    assert!(!client.flag("Paused"));
+    assert_eq!(client.property("ActiveDisplays").get::<Vec<String>>().unwrap(), ["HDMI-1"]);
+    assert_eq!(client.property("Displays").get::<Vec<String>>().unwrap(), ["HDMI-1", "DP-2"]);
+    let next = client.property("NextShiftTime").get::<u64>().unwrap();
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs();
+    assert!(next > now && next <= now + 300, "{} vs {}", next, now);
+    run.stop();
+}
+
+#[test]
+fn pause_and_resume_report_the_wrong_state() {
+    let Some((_bus, _fake, run, client)) = start("dbus_pause") else { return };
+
+    client.call("Pause", None).unwrap();
+    assert!(client.flag("Paused") && !client.flag("Running"));
+    assert_eq!(client.call("Pause", None), Err(error_name("NotRunning")));
+
+    client.call("Resume", None).unwrap();
+    assert!(client.flag("Running"));
+    assert_eq!(client.call("Resume", None), Err(error_name("NotPaused")));
+
+    client.call("Stop", None).unwrap();
+    assert_eq!(client.call("Pause", None), Err(error_name("NotRunning")));
+    assert!(!client.flag("Paused") && !client.flag("Running"));
+    run.stop();
+}
+
+#[test]
+fn shift_once_moves_the_display_and_signals_it() {
+    let Some((_bus, fake, run, client)) = start("dbus_shift") else { return };
+    client.forget_signals();
+
+    client.call("ShiftOnce", Some((3i32, -2i32).to_variant())).unwrap();
+    client.wait_until("Shifted", |c| c.signal("Shifted").is_some());
+    assert_eq!(client.signal("Shifted").unwrap().get::<(String, i32, i32)>().unwrap(), ("HDMI-1".to_string(), 3, -2));
+    assert_eq!(client.property("CurrentOffset").get::<(i32, i32)>().unwrap(), (3, -2));
+    assert!(fake.changes().iter().any(|c| c.starts_with("--output HDMI-1 --pos ")), "{:?}", fake.changes());
+
+    // Properties follow every change
+    client.wait_until("PropertiesChanged", |c| c.signal("org.freedesktop.DBus.Properties.PropertiesChanged").is_some());
+    let changed = client.signal("org.freedesktop.DBus.Properties.PropertiesChanged").unwrap();
+    assert_eq!(changed.child_value(0).get::<String>().unwrap(), INTERFACE);
+    let properties = glib::VariantDict::new(Some(&changed.child_value(1)));
+    assert_eq!(properties.lookup::<(i32, i32)>("CurrentOffset").unwrap(), Some((3, -2)));
+
+    client.call("Reset", None).unwrap();
+    assert_eq!(client.property("CurrentOffset").get::<(i32, i32)>().unwrap(), (0, 0));
+    run.stop();
+}
+
+#[test]
+fn start_refuses_a_second_session_and_unknown_displays() {
+    let Some((_bus, _fake, run, client)) = start("dbus_start") else { return };
+
+    assert_eq!(client.call("Start", Some(("DP-2",).to_variant())), Err(error_name("AlreadyRunning")));
+    client.call("Stop", None).unwrap();
+    assert!(!client.flag("Running"));
+    assert!(client.property("ActiveDisplays").get::<Vec<String>>().unwrap().is_empty());
+
+    assert_eq!(client.call("Start", Some(("VGA-9",).to_variant())), Err(error_name("NoDisplay")));
+    client.call("Start", Some(("DP-2",).to_variant())).unwrap();
+    assert!(client.flag("Running"));
+    assert_eq!(client.property("ActiveDisplays").get::<Vec<String>>().unwrap(), ["DP-2"]);
+    run.stop();
+}
+
+#[test]
+fn failures_come_back_as_errors_and_signals() {
+    let Some((_bus, mut fake, run, client)) = start("dbus_failure") else { return };
+    client.forget_signals();
+
+    fake.fail("*--pos*", "X Error of failed request: BadMatch");
+    let error = client.call("ShiftOnce", Some((1i32, 1i32).to_variant())).unwrap_err();
+    assert_eq!(error, error_name("Failed"));
+    client.wait_until("Error", |c| c.signal("Error").is_some());
+    let (message,) = client.signal("Error").unwrap().get::<(String,)>().unwrap();
+    assert!(message.contains("HDMI-1"), "{}", message);
+    run.stop();
+}
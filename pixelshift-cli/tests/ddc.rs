@@ -0,0 +1,96 @@
+//! DDC/CI commands and the monitor's own pixel orbit, against the emulator
+//! behind `PIXELSHIFT_DDC_DEVICE` and a quirk entry for the fake OLED.
+
+mod common;
+
+use common::{ddc_emulator, spawn, stdout, vcp, FakeXrandr};
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Keyed by product code, since no entry names the fake OLED's model
+const QUIRK: &str = "[LGD 0617]\norbit=0xE0\norbit-on=1\norbit-off=0\nrefresh=0xE1\nrefresh-start=2\n";
+
+fn with_quirk(test: &str, features: &str) -> (FakeXrandr, std::path::PathBuf) {
+    let fake = FakeXrandr::new(test);
+    let dir = fake.home().join("config/pixelshift-gtk");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("ddc-quirks.ini"), QUIRK).unwrap();
+    let socket = ddc_emulator(fake.dir(), features);
+    (fake, socket)
+}
+
+fn ddc(fake: &FakeXrandr, socket: &Path, args: &[&str]) -> std::process::Output {
+    let mut all = vec!["ddc"];
+    all.extend_from_slice(args);
+    all.extend_from_slice(&["--display", "HDMI-1"]);
+    fake.command(&all).env("PIXELSHIFT_DDC_DEVICE", socket).output().unwrap()
+}
+
+#[test]
+fn get_and_set_reach_the_monitor() {
+    let (fake, socket) = with_quirk("ddc_get_set", "0x10=80/100");
+    assert_eq!(stdout(&ddc(&fake, &socket, &["get", "0x10"])).trim(), "VCP 0x10: 80 (max 100)");
+
+    let output = ddc(&fake, &socket, &["set", "16", "35"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(vcp(&socket, 0x10).current, 35);
+
+    let output = ddc(&fake, &socket, &["get", "0x42"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("does not support VCP code 0x42"));
+}
+
+#[test]
+fn orbit_and_refresh_use_the_quirk_codes() {
+    let (fake, socket) = with_quirk("ddc_orbit", "0xE0=0/1; 0xE1=0/2");
+    assert!(stdout(&ddc(&fake, &socket, &["orbit"])).contains("Built-in pixel orbit on HDMI-1 is off"));
+
+    assert!(ddc(&fake, &socket, &["orbit", "on"]).status.success());
+    assert_eq!(vcp(&socket, 0xE0).current, 1);
+    assert!(stdout(&ddc(&fake, &socket, &["orbit"])).contains("is on"));
+
+    assert!(ddc(&fake, &socket, &["refresh"]).status.success());
+    assert_eq!(vcp(&socket, 0xE1).current, 2);
+}
+
+#[test]
+fn monitors_without_a_quirk_have_no_orbit_control() {
+    let fake = FakeXrandr::new("ddc_no_quirk");
+    let socket = ddc_emulator(fake.dir(), "0xE0=0/1");
+    let output = ddc(&fake, &socket, &["orbit", "on"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no orbit control known for HDMI-1"));
+    assert_eq!(vcp(&socket, 0xE0).current, 0);
+}
+
+#[test]
+fn hardware_orbit_replaces_software_shifting_for_the_session() {
+    let (fake, socket) = with_quirk("ddc_hardware_orbit", "0xE0=0/1");
+    fake.profiles(&[("LGD-0617-1A2B3C", "hardware-orbit=true")]);
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--interval", "5"]);
+    command.env("PIXELSHIFT_DDC_DEVICE", &socket);
+    let run = spawn(command);
+    run.wait_for("HDMI-1's built-in pixel orbit is on; not shifting in software.", Duration::from_secs(10));
+    assert_eq!(vcp(&socket, 0xE0).current, 1);
+
+    // Past the first tick, nothing has been shifted in software
+    std::thread::sleep(Duration::from_secs(6));
+    assert!(!fake.changes().iter().any(|c| c.contains("--transform") || c.contains("--panning") || c.contains("--pos")), "{:?}", fake.changes());
+
+    run.stop();
+    assert_eq!(vcp(&socket, 0xE0).current, 0, "the orbit goes back off with the session");
+}
+
+#[test]
+fn orbit_switched_on_in_the_osd_is_left_alone() {
+    let (fake, socket) = with_quirk("ddc_osd_orbit", "0xE0=1/1");
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1"]);
+    command.env("PIXELSHIFT_DDC_DEVICE", &socket);
+    let run = spawn(command);
+    run.wait_for("not shifting in software", Duration::from_secs(10));
+    run.stop();
+    assert_eq!(vcp(&socket, 0xE0).current, 1);
+}
@@ -0,0 +1,308 @@
+//! Display detection, every shift method and the reset fallbacks against a
+//! scripted `xrandr`, so they run without an X server. `tests/xvfb.rs` runs
+//! the same flows against a real one.
+
+mod common;
+
+use common::{stdout, FakeXrandr};
+
+#[test]
+fn list_reads_connected_outputs_and_edids() {
+    let fake = FakeXrandr::new("list");
+    let output = fake.run(&["list", "--json"]);
+    assert!(output.status.success());
+
+    let json = stdout(&output);
+    assert!(json.contains(r#"{"name":"HDMI-1","monitor_id":"LGD-0617-1A2B3C","model":"OLED 27GS95","width":2560,"height":1440,"#), "{}", json);
+    assert!(json.contains(r#""primary":true,"oled":true}"#), "{}", json);
+    assert!(json.contains(r#"{"name":"DP-2","monitor_id":"DEL-A0B1-XYZ","model":"DELL U2720Q","width":1920,"height":1080,"#), "{}", json);
+    assert!(json.contains(r#""primary":false,"oled":false}"#), "{}", json);
+    assert!(!json.contains("DP-1"), "disconnected output listed: {}", json);
+}
+
+#[test]
+fn list_without_edids_falls_back_to_connector_names() {
+    let mut fake = FakeXrandr::new("list_no_edid");
+    fake.clear().respond("--query", common::QUERY);
+    let json = stdout(&fake.run(&["list", "--json"]));
+    assert!(json.contains(r#""name":"HDMI-1","monitor_id":"HDMI-1","model":null"#), "{}", json);
+}
+
+#[test]
+fn commands_fail_cleanly_without_displays() {
+    let mut fake = FakeXrandr::new("no_displays");
+    fake.clear().fail("--query*", "Can't open display");
+
+    assert_eq!(stdout(&fake.run(&["list", "--json"])).trim(), "[]");
+    let output = fake.run(&["shift", "--dx", "1", "--dy", "1"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("no connected displays found"));
+    assert!(fake.changes().is_empty());
+}
+
+#[test]
+fn each_method_issues_its_xrandr_command() {
+    let fake = FakeXrandr::new("methods");
+    let cases = [
+        ("transform", "--output HDMI-1 --transform 1,0,0.000781,0,1,0.001389,0,0,1"),
+        ("panning-smooth", "--output HDMI-1 --panning 2570x1450+2+2"),
+        ("position", "--output HDMI-1 --pos 2x2"),
+        ("panning", "--output HDMI-1 --panning 2560x1440+2+2"),
+    ];
+
+    for (method, expected) in cases {
+        // The fake never moves the output, so forget the previous shift
+        fake.run(&["reset", "--display", "HDMI-1"]);
+        fake.forget_invocations();
+        let output = fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", method]);
+        assert!(output.status.success(), "{}: {}", method, stdout(&output));
+        assert_eq!(fake.changes(), [expected], "{}", method);
+    }
+}
+
+#[test]
+fn shifts_are_relative_to_the_output_origin() {
+    let fake = FakeXrandr::new("origin");
+    for (method, expected) in [
+        ("position", "--output DP-2 --pos 2559x2"),
+        ("panning", "--output DP-2 --panning 1920x1080+2559+2"),
+        ("panning-smooth", "--output DP-2 --panning 1930x1090+2559+2"),
+    ] {
+        fake.run(&["reset", "--display", "DP-2"]);
+        fake.forget_invocations();
+        fake.run(&["shift", "--display", "DP-2", "--dx", "-1", "--dy", "2", "--method", method]);
+        assert_eq!(fake.changes(), [expected], "{}", method);
+    }
+}
+
+#[test]
+fn reset_after_position_shift_returns_to_the_origin() {
+    let mut fake = FakeXrandr::new("position_reset");
+    fake.run(&["shift", "--display", "DP-2", "--dx", "2", "--dy", "1", "--method", "position"]);
+
+    // The output now reports its shifted position
+    fake.clear().respond("--query", &common::QUERY.replace("1920x1080+2560+0", "1920x1080+2562+1"));
+    fake.forget_invocations();
+    assert!(fake.run(&["reset", "--display", "DP-2"]).status.success());
+    assert_eq!(fake.changes(), ["--output DP-2 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 2560x0"]);
+}
+
+#[test]
+fn monitor_id_selects_the_display() {
+    let fake = FakeXrandr::new("select_by_id");
+    fake.run(&["shift", "--display", "DEL-A0B1-XYZ", "--dx", "1", "--dy", "0", "--method", "panning"]);
+    assert_eq!(fake.changes(), ["--output DP-2 --panning 1920x1080+2561+0"]);
+}
+
+#[test]
+fn failed_shift_reports_xrandr_error() {
+    let mut fake = FakeXrandr::new("shift_fails");
+    fake.fail("--output HDMI-1 --transform *", "X Error of failed request:  BadMatch");
+
+    let output = fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("✗ Transform failed: X Error of failed request:  BadMatch"));
+    assert!(stdout(&fake.run(&["status", "--json"])).contains(r#"{"active":false}"#));
+}
+
+#[test]
+fn shift_is_recorded_and_reset_clears_it() {
+    let fake = FakeXrandr::new("state");
+    fake.run(&["shift", "--display", "HDMI-1", "--dx", "3", "--dy", "-1", "--method", "panning"]);
+    let status = stdout(&fake.run(&["status", "--json"]));
+    assert!(status.contains(r#""active":true,"display":"HDMI-1","method":"panning","x":3,"y":-1,"pid":null"#), "{}", status);
+
+    assert!(fake.run(&["reset", "--display", "HDMI-1"]).status.success());
+    assert!(stdout(&fake.run(&["status", "--json"])).contains(r#"{"active":false}"#));
+}
+
+#[test]
+fn reset_undoes_every_method_in_one_call() {
+    let fake = FakeXrandr::new("reset_combined");
+    let output = fake.run(&["reset", "--display", "HDMI-1"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("✓ Display reset successful for HDMI-1"));
+    assert_eq!(fake.changes(), ["--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0"]);
+}
+
+#[test]
+fn reset_falls_back_in_order() {
+    let mut fake = FakeXrandr::new("reset_fallbacks");
+    fake.fail("--output HDMI-1 --transform *", "unsupported").fail("--output HDMI-1 --panning *", "unsupported");
+
+    let output = fake.run(&["reset", "--display", "HDMI-1"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).contains("✓ Position reset successful for HDMI-1"));
+    assert_eq!(
+        fake.changes(),
+        [
+            "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0",
+            "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1",
+            "--output HDMI-1 --panning 0x0",
+            "--output HDMI-1 --pos 0x0",
+        ]
+    );
+}
+
+#[test]
+fn reset_reports_when_every_method_fails() {
+    let mut fake = FakeXrandr::new("reset_fails");
+    fake.fail("--output *", "BadMatch");
+
+    let output = fake.run(&["reset", "--display", "HDMI-1"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("✗ All reset methods failed for HDMI-1"));
+    assert_eq!(
+        fake.changes(),
+        [
+            "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0",
+            "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1",
+            "--output HDMI-1 --panning 0x0",
+            "--output HDMI-1 --pos 0x0",
+            "--output HDMI-1 --auto",
+        ]
+    );
+}
+
+#[test]
+fn reset_all_covers_every_connected_output() {
+    let fake = FakeXrandr::new("reset_all");
+    assert!(fake.run(&["reset", "--all"]).status.success());
+    assert_eq!(
+        fake.changes(),
+        [
+            "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0",
+            "--output DP-2 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 2560x0",
+        ]
+    );
+}
+
+#[test]
+fn unknown_command_fails_and_help_does_not() {
+    let fake = FakeXrandr::new("unknown_command");
+    let output = fake.run(&["shfit"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(stdout(&output).is_empty());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.starts_with("pixelshift: unknown command 'shfit'\n") && stderr.contains("Show this message"), "{}", stderr);
+
+    for help in ["help", "--help"] {
+        let output = fake.run(&[help]);
+        assert!(output.status.success());
+        assert!(stdout(&output).contains("Show this message"));
+    }
+}
+
+#[test]
+fn dry_run_logs_instead_of_running() {
+    let fake = FakeXrandr::new("dry_run");
+    let output = fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "panning", "--dry-run"]);
+    assert!(output.status.success());
+    assert!(fake.changes().is_empty());
+
+    let log = std::fs::read_to_string(fake.dir().join("home/data/pixelshift-gtk/dry-run.log")).unwrap();
+    assert!(log.trim_end().ends_with(" xrandr --output HDMI-1 --panning 2560x1440+2+2"), "{}", log);
+    // Nothing was applied, so there is no shift to report or recover
+    assert!(stdout(&fake.run(&["status", "--json"])).contains(r#"{"active":false}"#));
+}
+
+/// `xrandr --query --verbose` with HDMI-1 at `geometry`, untransformed and without panning
+fn verbose(geometry: &str) -> String {
+    format!(
+        "Screen 0: minimum 8 x 8, current 4480 x 1440, maximum 32767 x 32767
+HDMI-1 connected primary {} (0x48) normal (normal left inverted right x axis y axis) 600mm x 340mm
+\tIdentifier: 0x42
+\tTransform:  1.000000 0.000000 0.000000
+\t            0.000000 1.000000 0.000000
+\t            0.000000 0.000000 1.000000
+\t           filter: 
+  2560x1440 (0x48) 241.500MHz +HSync -VSync *current +preferred
+",
+        geometry
+    )
+}
+
+#[test]
+fn probe_finds_the_methods_that_take_effect() {
+    let mut fake = FakeXrandr::new("probe");
+    // Transform is refused outright; the panning methods are accepted but change nothing
+    fake.fail("--output HDMI-1 --transform 1,0,0.*", "X Error of failed request:  BadMatch (invalid parameter attributes)")
+        .respond("--query --verbose", &verbose("2560x1440+1+1"));
+
+    let output = fake.run(&["probe", "--display", "HDMI-1", "--json"]);
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    let json = stdout(&output);
+    assert!(json.contains(r#"{"method":"transform","supported":false,"reason":"X Error of failed request:  BadMatch (invalid parameter attributes)"}"#), "{}", json);
+    assert!(json.contains(r#"{"method":"panning-smooth","supported":false,"reason":"accepted but ineffective: RandR reports no panning"}"#), "{}", json);
+    assert!(json.contains(r#"{"method":"position","supported":true,"reason":null}"#), "{}", json);
+    assert!(json.contains(r#"{"method":"panning","supported":false,"#), "{}", json);
+
+    // Each try is undone before the next one
+    let changes = fake.changes();
+    assert_eq!(changes.len(), 8, "{:?}", changes);
+    for reset in changes.iter().skip(1).step_by(2) {
+        assert_eq!(reset, "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0");
+    }
+
+    let cache = std::fs::read_to_string(fake.dir().join("home/cache/pixelshift-gtk/methods.ini")).unwrap();
+    assert!(cache.contains("[monitor LGD-0617-1A2B3C]") && cache.contains("position=ok"), "{}", cache);
+}
+
+#[test]
+fn probe_leaves_an_applied_shift_alone() {
+    let fake = FakeXrandr::new("probe_shifted");
+    fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "panning"]);
+    fake.forget_invocations();
+
+    let output = fake.run(&["probe", "--display", "HDMI-1"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("HDMI-1 has a shift applied"));
+    assert!(fake.changes().is_empty());
+}
+
+#[test]
+fn probe_all_keeps_going_past_a_refusal() {
+    let fake = FakeXrandr::new("probe_all_shifted");
+    fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "panning"]);
+    fake.forget_invocations();
+
+    let output = fake.run(&["probe", "--all"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(String::from_utf8_lossy(&output.stderr).contains("✗ Cannot probe HDMI-1: HDMI-1 has a shift applied"));
+    assert!(stdout(&output).starts_with("DP-2 (DEL-A0B1-XYZ)"), "{}", stdout(&output));
+    assert!(fake.changes().iter().all(|c| c.starts_with("--output DP-2 ")), "{:?}", fake.changes());
+
+    let cache = std::fs::read_to_string(fake.dir().join("home/cache/pixelshift-gtk/methods.ini")).unwrap();
+    assert!(cache.contains("[monitor DEL-A0B1-XYZ]") && !cache.contains("[monitor LGD-0617-1A2B3C]"), "{}", cache);
+}
+
+#[test]
+fn shift_that_cannot_be_read_back_is_applied_with_a_warning() {
+    let mut fake = FakeXrandr::new("unverifiable");
+    fake.respond("--query --verbose", "Screen 0: minimum 8 x 8\nno outputs here\n");
+
+    let output = fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "panning"]);
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert!(stdout(&output).contains("Could not verify the shift on HDMI-1: xrandr --verbose does not describe HDMI-1"), "{}", stdout(&output));
+    assert!(!stdout(&output).contains("ineffective"), "{}", stdout(&output));
+
+    fake.clear().respond("--query", common::QUERY).fail("--query --verbose", "Can't open display");
+    let output = fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "panning"]);
+    assert!(output.status.success(), "{}", stdout(&output));
+    assert!(stdout(&output).contains("Could not verify the shift on HDMI-1: xrandr --verbose failed: Can't open display"), "{}", stdout(&output));
+}
+
+#[test]
+fn shift_that_changes_nothing_is_reported_as_ineffective() {
+    let mut fake = FakeXrandr::new("ineffective");
+    fake.respond("--query --verbose", &verbose("2560x1440+0+0"));
+
+    let output = fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "panning"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout(&output).contains("✗ Basic Panning accepted but ineffective on HDMI-1: RandR reports no panning"), "{}", stdout(&output));
+    assert!(stdout(&fake.run(&["status", "--json"])).contains(r#"{"active":false}"#));
+
+    // Read back where the shift put it, the same call succeeds
+    fake.clear().respond("--query", common::QUERY).respond("--query --verbose", &verbose("2560x1440+2+2"));
+    assert!(fake.run(&["shift", "--display", "HDMI-1", "--dx", "2", "--dy", "2", "--method", "position"]).status.success());
+}
@@ -0,0 +1,97 @@
+//! Idle dimming under `run`, with idle time faked through a file and the
+//! brightness going to a DDC/CI emulator or the fake xrandr.
+
+mod common;
+
+use common::{ddc_emulator, spawn, vcp, FakeXrandr};
+use pixelshift_core::ddc::VCP_BRIGHTNESS;
+use std::fs;
+use std::time::Duration;
+
+const OLED: &str = "LGD-0617-1A2B3C";
+const WAIT: Duration = Duration::from_secs(15);
+
+fn records(fake: &FakeXrandr) -> String {
+    fs::read_to_string(fake.home().join("run/pixelshift-gtk/brightness")).unwrap_or_default()
+}
+
+#[test]
+fn ddc_brightness_dims_while_idle_and_returns_with_input() {
+    let fake = FakeXrandr::new("idle_dim_ddc");
+    fake.profiles(&[(OLED, "idle-dim=1\nidle-dim-level=30\nidle-dim-method=ddc")]);
+    let socket = ddc_emulator(fake.dir(), "0x10=80/100");
+    let idle = fake.dir().join("idle");
+    fs::write(&idle, "120").unwrap();
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1"]);
+    command.env("PIXELSHIFT_FAKE_IDLE", &idle).env("PIXELSHIFT_DDC_DEVICE", &socket);
+    let run = spawn(command);
+
+    run.wait_for("Idle: dimmed HDMI-1 to 30%", WAIT);
+    assert_eq!(vcp(&socket, VCP_BRIGHTNESS).current, 24);
+    // Noted before dimming, so a crash now can still be undone
+    let record = records(&fake);
+    assert!(record.contains("[dimmed HDMI-1]") && record.contains("method=ddc") && record.contains("previous=0.8"), "{}", record);
+
+    fs::write(&idle, "0").unwrap();
+    run.wait_for("✓ Brightness restored on HDMI-1", WAIT);
+    assert_eq!(vcp(&socket, VCP_BRIGHTNESS).current, 80);
+    assert!(!records(&fake).contains("[dimmed HDMI-1]"));
+    run.stop();
+}
+
+#[test]
+fn randr_brightness_is_restored_when_run_stops() {
+    let mut fake = FakeXrandr::new("idle_dim_randr");
+    fake.respond("--verbose", "HDMI-1 connected primary 2560x1440+0+0\n\tBrightness: 0.90\n");
+    fake.profiles(&[(OLED, "idle-dim=1\nidle-dim-level=50\nidle-dim-method=randr")]);
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1"]);
+    command.env("PIXELSHIFT_FAKE_IDLE", "600");
+    let run = spawn(command);
+    run.wait_for("Idle: dimmed HDMI-1 to 50%", WAIT);
+    assert!(fake.changes().contains(&"--output HDMI-1 --brightness 0.45".to_string()), "{:?}", fake.changes());
+
+    let output = run.stop();
+    assert!(output.contains("✓ Brightness restored on HDMI-1"), "{}", output);
+    let brightness: Vec<String> = fake.changes().into_iter().filter(|c| c.contains("--brightness")).collect();
+    assert_eq!(brightness, ["--output HDMI-1 --brightness 0.45", "--output HDMI-1 --brightness 0.90"]);
+    assert!(!records(&fake).contains("[dimmed HDMI-1]"));
+}
+
+#[test]
+fn randr_brightness_goes_to_the_dry_run_log() {
+    let mut fake = FakeXrandr::new("idle_dim_dry_run");
+    fake.respond("--verbose", "HDMI-1 connected primary 2560x1440+0+0\n\tBrightness: 0.90\n");
+    fake.profiles(&[(OLED, "idle-dim=1\nidle-dim-level=50\nidle-dim-method=randr")]);
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--dry-run"]);
+    command.env("PIXELSHIFT_FAKE_IDLE", "600");
+    let run = spawn(command);
+    run.wait_for("Idle: dimmed HDMI-1 to 50%", WAIT);
+    run.stop();
+
+    assert!(!fake.changes().iter().any(|c| c.contains("--brightness")), "{:?}", fake.changes());
+    let log = fs::read_to_string(fake.home().join("data/pixelshift-gtk/dry-run.log")).unwrap();
+    let logged: Vec<&str> = log.lines().filter_map(|l| l.split_once(" xrandr ").map(|(_, c)| c)).filter(|c| c.contains("--brightness")).collect();
+    assert_eq!(logged, ["--output HDMI-1 --brightness 0.45", "--output HDMI-1 --brightness 0.90"]);
+}
+
+#[test]
+fn recover_restores_brightness_a_dead_process_left_dimmed() {
+    let fake = FakeXrandr::new("idle_dim_recover");
+    let socket = ddc_emulator(fake.dir(), "0x10=24/100");
+    let mut dead = std::process::Command::new("true").spawn().unwrap();
+    let pid = dead.id();
+    dead.wait().unwrap();
+
+    let dir = fake.home().join("run/pixelshift-gtk");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("brightness"), format!("[dimmed HDMI-1]\npid={}\nmethod=ddc\nprevious=0.8\n", pid)).unwrap();
+
+    let output = fake.command(&["recover"]).env("PIXELSHIFT_DDC_DEVICE", &socket).output().unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stdout));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("✓ Brightness restored on HDMI-1"));
+    assert_eq!(vcp(&socket, VCP_BRIGHTNESS).current, 80);
+    assert!(!records(&fake).contains("[dimmed HDMI-1]"));
+}
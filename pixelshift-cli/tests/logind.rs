@@ -0,0 +1,104 @@
+//! Suspend and wake under `run`, with a mock logind on a private session bus.
+
+mod common;
+
+use common::{spawn, DbusDaemon, FakeXrandr, MockLogind};
+use gio::prelude::*;
+use gio::{DBusCallFlags, DBusConnection};
+use glib::Variant;
+use pixelshift_core::dbus::{BUS_NAME, INTERFACE, OBJECT_PATH};
+use std::time::{Duration, Instant};
+
+const RESET: &str = "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0";
+
+fn wait_until(what: &str, timeout: Duration, done: impl Fn() -> bool) {
+    let deadline = Instant::now() + timeout;
+    while !done() {
+        assert!(Instant::now() < deadline, "timed out waiting for {}", what);
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn call(connection: &DBusConnection, interface: &str, method: &str, args: Option<Variant>) -> Result<Variant, glib::Error> {
+    connection.call_sync(Some(BUS_NAME), OBJECT_PATH, interface, method, args.as_ref(), None, DBusCallFlags::NONE, 5000, gio::Cancellable::NONE)
+}
+
+#[test]
+fn display_is_reset_before_sleep_and_shifted_again_after_wake() {
+    let Some(bus) = DbusDaemon::start("logind_sleep") else { return };
+    let logind = MockLogind::start(&bus);
+    let fake = FakeXrandr::new("logind_sleep");
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--method", "position", "--interval", "5"]);
+    command.env("DBUS_SESSION_BUS_ADDRESS", bus.address()).env("PIXELSHIFT_LOGIND_BUS", "session");
+    let run = spawn(command);
+
+    // The delay inhibitor is what gives us time to reset before the suspend
+    wait_until("the delay inhibitor", Duration::from_secs(10), || logind.inhibitors() == 1);
+    run.wait_for("✓ Position shift applied", Duration::from_secs(10));
+    let shifted = fake.changes().last().cloned().unwrap();
+    assert!(shifted.starts_with("--output HDMI-1 --pos "), "{}", shifted);
+
+    logind.prepare_for_sleep(true);
+    run.wait_for("Suspending: display restored.", Duration::from_secs(5));
+    assert_eq!(fake.changes().last().map(String::as_str), Some(RESET));
+
+    fake.forget_invocations();
+    logind.prepare_for_sleep(false);
+    // Reapplied once the driver has settled, with a new inhibitor for the next suspend
+    wait_until("the offset to come back", Duration::from_secs(10), || fake.changes().contains(&shifted));
+    assert_eq!(fake.changes()[0], shifted, "{:?}", fake.changes());
+    assert_eq!(logind.inhibitors(), 2);
+
+    let output = run.stop();
+    assert!(!output.contains('✗'), "{}", output);
+}
+
+#[test]
+fn paused_session_stays_paused_after_wake() {
+    let Some(bus) = DbusDaemon::start("logind_paused") else { return };
+    let logind = MockLogind::start(&bus);
+    let mut fake = FakeXrandr::new("logind_paused");
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--method", "position", "--interval", "5"]);
+    command.env("DBUS_SESSION_BUS_ADDRESS", bus.address()).env("PIXELSHIFT_LOGIND_BUS", "session");
+    let run = spawn(command);
+    wait_until("the delay inhibitor", Duration::from_secs(10), || logind.inhibitors() == 1);
+
+    logind.prepare_for_sleep(true);
+    run.wait_for("Suspending: display restored.", Duration::from_secs(5));
+
+    // The monitor does not come back with the machine
+    fake.clear().respond("--query", &common::QUERY.replace("HDMI-1 connected primary 2560x1440+0+0", "HDMI-1 disconnected"));
+    fake.forget_invocations();
+    logind.prepare_for_sleep(false);
+    run.wait_for("HDMI-1 not found after resume; auto-shift paused.", Duration::from_secs(10));
+    assert!(fake.changes().is_empty(), "{:?}", fake.changes());
+    run.stop();
+}
+
+#[test]
+fn session_paused_before_sleep_stays_paused_after_wake() {
+    let Some(bus) = DbusDaemon::start("logind_user_paused") else { return };
+    let logind = MockLogind::start(&bus);
+    let fake = FakeXrandr::new("logind_user_paused");
+
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--method", "position", "--interval", "5"]);
+    command.env("DBUS_SESSION_BUS_ADDRESS", bus.address()).env("PIXELSHIFT_LOGIND_BUS", "session");
+    let run = spawn(command);
+    wait_until("the delay inhibitor", Duration::from_secs(10), || logind.inhibitors() == 1);
+
+    let connection = bus.connect();
+    wait_until("the pause", Duration::from_secs(10), || call(&connection, INTERFACE, "Pause", None).is_ok());
+    logind.prepare_for_sleep(true);
+    run.wait_for("Suspending: display restored.", Duration::from_secs(5));
+
+    fake.forget_invocations();
+    logind.prepare_for_sleep(false);
+    // Past the settle delay after wake, when a running session would have been reapplied
+    std::thread::sleep(Duration::from_secs(4));
+    assert!(fake.changes().is_empty(), "{:?}", fake.changes());
+    let paused = call(&connection, "org.freedesktop.DBus.Properties", "Get", Some((INTERFACE, "Paused").to_variant())).unwrap();
+    assert_eq!(paused.child_value(0).as_variant().and_then(|v| v.get::<bool>()), Some(true));
+    run.stop();
+}
@@ -0,0 +1,38 @@
+//! Usage statistics with two sessions up at once: only one of them may record.
+
+mod common;
+
+use common::{spawn, FakeXrandr};
+use std::time::{Duration, Instant};
+
+fn on_secs(fake: &FakeXrandr, id: &str) -> f64 {
+    let text = std::fs::read_to_string(fake.home().join("data/pixelshift-gtk/usage.ini")).unwrap_or_default();
+    text.split(&format!("[monitor {}]", id))
+        .nth(1)
+        .and_then(|group| group.lines().find_map(|l| l.strip_prefix("on=")))
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(0.0)
+}
+
+#[test]
+fn a_second_session_does_not_count_the_same_minutes() {
+    let fake = FakeXrandr::new("usage_two_sessions");
+    let started = Instant::now();
+    let first = spawn(fake.command(&["run", "--display", "HDMI-1", "--interval", "300"]));
+    first.wait_for("Starting auto-shift", Duration::from_secs(10));
+
+    let second = spawn(fake.command(&["run", "--display", "DP-2", "--interval", "300"]));
+    second.wait_for("Starting auto-shift", Duration::from_secs(10));
+    std::thread::sleep(Duration::from_secs(2));
+
+    // Both count their partial minute on the way out; only the lock holder writes it
+    let second_output = second.stop();
+    assert!(!second_output.contains("usage statistics"), "{}", second_output);
+    assert_eq!(on_secs(&fake, "LGD-0617-1A2B3C"), 0.0);
+    first.stop();
+    let lifetime = started.elapsed().as_secs_f64();
+
+    let recorded = on_secs(&fake, "LGD-0617-1A2B3C");
+    assert!(recorded >= 2.0 && recorded <= lifetime + 1.0, "{} s recorded in {} s", recorded, lifetime);
+    assert_eq!(on_secs(&fake, "DEL-A0B1-XYZ"), recorded);
+}
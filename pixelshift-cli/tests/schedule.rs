@@ -0,0 +1,50 @@
+//! Schedules under `run`, with the clock moved to just before a change
+//! through `PIXELSHIFT_FAKE_TIME`.
+
+mod common;
+
+use common::{spawn, FakeXrandr};
+use std::time::Duration;
+
+const RESET: &str = "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0";
+const WAIT: Duration = Duration::from_secs(15);
+
+fn run_at(fake: &FakeXrandr, time: &str, schedule: &str) -> common::Running {
+    let mut command = fake.command(&["run", "--display", "HDMI-1", "--method", "position", "--schedule", schedule]);
+    command.env("PIXELSHIFT_FAKE_TIME", time).env("TZ", "UTC");
+    spawn(command)
+}
+
+#[test]
+fn shifting_stops_when_the_schedule_turns_it_off() {
+    let fake = FakeXrandr::new("schedule_off");
+    // 2026-10-19 is a Monday
+    let run = run_at(&fake, "2026-10-19T08:59:50", "mon-fri 09:00-18:00 off; * 5s");
+
+    run.wait_for("✓ Position shift applied", WAIT);
+    assert!(fake.changes().iter().any(|c| c.starts_with("--output HDMI-1 --pos ")), "{:?}", fake.changes());
+
+    run.wait_for("Schedule: shifting off until Mon 18:00.", WAIT);
+    assert_eq!(fake.changes().last().map(String::as_str), Some(RESET));
+    run.stop();
+}
+
+#[test]
+fn shifting_starts_when_the_schedule_turns_it_on() {
+    let fake = FakeXrandr::new("schedule_on");
+    let run = run_at(&fake, "2026-10-19T17:59:57", "mon-fri 09:00-18:00 off; * 5s");
+
+    run.wait_for("✓ Position shift applied", WAIT);
+    let output = run.stop();
+    // Nothing moved before six
+    assert!(!output.contains("Schedule: shifting off"), "{}", output);
+    assert!(fake.changes()[0].starts_with("--output HDMI-1 --pos "), "{:?}", fake.changes());
+}
+
+#[test]
+fn invalid_fake_time_is_reported_and_ignored() {
+    let fake = FakeXrandr::new("schedule_bad_time");
+    let run = run_at(&fake, "next tuesday", "* 5s");
+    run.wait_for("✗ Ignoring invalid PIXELSHIFT_FAKE_TIME 'next tuesday'", WAIT);
+    run.stop();
+}
@@ -0,0 +1,43 @@
+//! The scheduler driven in-process against the fake xrandr, for paths no
+//! command reaches on its own.
+
+mod common;
+
+use common::FakeXrandr;
+use pixelshift_core::scheduler::{AutoShiftSession, Scheduler};
+use pixelshift_core::SetTextSafe;
+
+const RESET: &str = "--output HDMI-1 --transform 1,0,0,0,1,0,0,0,1 --panning 0x0 --pos 0x0";
+
+struct Silent;
+
+impl SetTextSafe for Silent {
+    fn set_text_safe(&self, _text: &str) {}
+
+    fn append_text_safe(&self, _text: &str) {}
+}
+
+#[test]
+fn starting_over_a_paused_session_puts_its_display_back() {
+    // glib reads these once per process, before anything else here touches it
+    let fake = FakeXrandr::new("scheduler_start_paused");
+    let home = fake.home();
+    std::env::set_var("PATH", format!("{}:{}", fake.dir().display(), std::env::var("PATH").unwrap_or_default()));
+    for (variable, dir) in [("XDG_CONFIG_HOME", "config"), ("XDG_CACHE_HOME", "cache"), ("XDG_DATA_HOME", "data"), ("XDG_RUNTIME_DIR", "run")] {
+        std::env::set_var(variable, home.join(dir));
+    }
+
+    let scheduler = Scheduler::new(Silent);
+    let display = |name: &str| scheduler.displays().into_iter().find(|d| d.name == name).unwrap();
+    // Position offsets, every five minutes
+    scheduler.start(AutoShiftSession::new(display("HDMI-1"), 2, 2, true, 300));
+    scheduler.shift_once(3, -2).unwrap();
+    assert!(scheduler.pause());
+    fake.forget_invocations();
+
+    scheduler.start(AutoShiftSession::new(display("DP-2"), 2, 2, true, 300));
+    assert!(scheduler.is_running());
+    assert_eq!(scheduler.session().map(|s| s.display.name), Some("DP-2".to_string()));
+    assert_eq!(fake.changes().first().map(String::as_str), Some(RESET), "{:?}", fake.changes());
+    scheduler.stop();
+}
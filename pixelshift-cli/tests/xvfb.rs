@@ -0,0 +1,133 @@
+//! The real shift and reset flows on a private Xvfb, checked through RandR
+//! itself: whatever a method changes must come back exactly as it was.
+//! Skipped when Xvfb or xrandr is missing (see `tests/common/mod.rs`).
+
+mod common;
+
+use common::{spawn, stdout, Xvfb};
+use std::time::Duration;
+
+/// What the shift methods can change, read from `xrandr --verbose`
+#[derive(Debug, PartialEq)]
+struct Snapshot {
+    screen: String,
+    output: String,
+    geometry: String,
+    transform: Vec<String>,
+    panning: Option<String>,
+}
+
+fn snapshot(server: &Xvfb) -> Snapshot {
+    let text = stdout(&server.xrandr(&["--verbose"]));
+    let mut lines = text.lines();
+    let screen = lines.next().and_then(|l| l.split_once("current ")).map(|(_, rest)| rest.split(',').next().unwrap_or(rest).to_string()).unwrap_or_default();
+
+    let header = lines.by_ref().find(|l| l.contains(" connected")).expect("no connected output");
+    let mut snapshot = Snapshot {
+        screen,
+        output: header.split_whitespace().next().unwrap_or_default().to_string(),
+        geometry: header.split_whitespace().find(|p| p.starts_with(|c: char| c.is_ascii_digit()) && p.contains('+')).unwrap_or_default().to_string(),
+        transform: Vec::new(),
+        panning: None,
+    };
+
+    let mut block = lines.take_while(|l| l.starts_with(' ') || l.starts_with('\t')).map(str::trim);
+    while let Some(line) = block.next() {
+        if let Some(first_row) = line.strip_prefix("Transform:") {
+            snapshot.transform = std::iter::once(first_row.trim()).chain(block.by_ref().take(2)).map(str::to_string).collect();
+        } else if let Some(panning) = line.strip_prefix("Panning:") {
+            snapshot.panning = Some(panning.trim().to_string());
+        }
+    }
+    snapshot
+}
+
+/// Shift with `method`; if the server takes it something must have changed,
+/// if not nothing may have. Either way `reset` has to restore the snapshot.
+fn shift_and_restore(test: &str, method: &str) {
+    let Some(server) = Xvfb::start(test) else { return };
+    let before = snapshot(&server);
+
+    let shifted = server.run(&["shift", "--dx", "2", "--dy", "2", "--method", method]);
+    let during = snapshot(&server);
+    if shifted.status.success() {
+        assert_ne!(during, before, "{} reported success but RandR shows no change", method);
+    } else {
+        assert_eq!(during, before, "{} failed but left the output changed", method);
+        eprintln!("{}: rejected by this server: {}", method, stdout(&shifted).trim());
+    }
+
+    let reset = server.run(&["reset", "--display", &before.output]);
+    assert!(reset.status.success(), "reset failed: {}", stdout(&reset));
+    assert_eq!(snapshot(&server), before, "{} did not come back", method);
+}
+
+#[test]
+fn transform_shift_restores() {
+    shift_and_restore("xvfb_transform", "transform");
+}
+
+#[test]
+fn panning_shift_restores() {
+    shift_and_restore("xvfb_panning", "panning");
+}
+
+#[test]
+fn smooth_panning_shift_restores() {
+    shift_and_restore("xvfb_panning_smooth", "panning-smooth");
+}
+
+#[test]
+fn position_shift_moves_the_output_and_restores() {
+    let Some(server) = Xvfb::start("xvfb_position") else { return };
+    let before = snapshot(&server);
+
+    // Moving a CRTC needs nothing from the driver, so every server has to accept it
+    let shifted = server.run(&["shift", "--dx", "3", "--dy", "2", "--method", "position"]);
+    assert!(shifted.status.success(), "{}", stdout(&shifted));
+    let during = snapshot(&server);
+    assert!(during.geometry.ends_with("+3+2"), "output at {}", during.geometry);
+
+    assert!(server.run(&["reset", "--display", &before.output]).status.success());
+    assert_eq!(snapshot(&server), before);
+}
+
+#[test]
+fn recover_restores_what_a_dead_session_left() {
+    let Some(server) = Xvfb::start("xvfb_recover") else { return };
+    let before = snapshot(&server);
+
+    assert!(server.run(&["shift", "--dx", "4", "--dy", "1", "--method", "position"]).status.success());
+    assert_ne!(snapshot(&server), before);
+
+    let recovered = server.run(&["recover"]);
+    assert!(recovered.status.success(), "{}", stdout(&recovered));
+    assert_eq!(snapshot(&server), before);
+    assert!(stdout(&server.run(&["status", "--json"])).contains(r#"{"active":false}"#));
+}
+
+#[test]
+fn probe_finds_position_works_and_restores() {
+    let Some(server) = Xvfb::start("xvfb_probe") else { return };
+    let before = snapshot(&server);
+
+    let probed = server.run(&["probe", "--json"]);
+    assert!(probed.status.success(), "{}", String::from_utf8_lossy(&probed.stderr));
+    let json = stdout(&probed);
+    assert!(json.contains(r#"{"method":"position","supported":true,"reason":null}"#), "{}", json);
+    assert_eq!(snapshot(&server), before);
+}
+
+#[test]
+fn adaptive_sampling_reads_the_root_window() {
+    let Some(server) = Xvfb::start("xvfb_adaptive") else { return };
+    let mut command = server.command(&["run", "--method", "position", "--interval", "5", "--adaptive"]);
+    command.env_remove("WAYLAND_DISPLAY");
+    let run = spawn(command);
+    run.wait_for("Starting auto-shift", Duration::from_secs(10));
+
+    // A failed first sample would leave the base interval and be reported on its first tick
+    std::thread::sleep(Duration::from_secs(7));
+    let output = run.stop();
+    assert!(!output.contains("Adaptive interval unavailable"), "{}", output);
+}
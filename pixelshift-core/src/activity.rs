@@ -0,0 +1,166 @@
+//! Adaptive interval: sample the session's display every few seconds, track
+//! how long each region has stayed unchanged, and shorten the shift interval
+//! when large parts of the panel are static (or lengthen it while everything
+//! is moving anyway).
+
+use glib::source::SourceId;
+use glib::ControlFlow;
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::framebuffer::{self, Grid};
+use crate::DisplayInfo;
+
+pub const SAMPLE_SECS: u64 = 10;
+pub const GRID_COLS: usize = 32;
+pub const GRID_ROWS: usize = 18;
+/// Change in a cell's mean luminance that still counts as unchanged
+pub const STILL_THRESHOLD: f32 = 2.0 / 255.0;
+/// A cell has to be unchanged this long before it counts as static
+const STATIC_AFTER_SECS: f64 = 60.0;
+/// Static share of the panel at which the interval is shortest
+const FULL_RISK_FRACTION: f64 = 0.5;
+const MIN_INTERVAL_SECS: u64 = 5;
+const MAX_INTERVAL_SECS: u64 = 3600;
+
+/// Per-cell stillness of consecutive grids
+#[derive(Debug, Default)]
+pub struct ActivityTracker {
+    previous: Option<Grid>,
+    still_secs: Vec<f64>,
+}
+
+impl ActivityTracker {
+    pub fn update(&mut self, grid: Grid, elapsed_secs: f64) {
+        match &self.previous {
+            Some(previous) if previous.cells.len() == grid.cells.len() => {
+                for ((still, old), new) in self.still_secs.iter_mut().zip(&previous.cells).zip(&grid.cells) {
+                    if (old - new).abs() <= STILL_THRESHOLD {
+                        *still += elapsed_secs;
+                    } else {
+                        *still = 0.0;
+                    }
+                }
+            }
+            // First sample or the geometry changed: start over
+            _ => self.still_secs = vec![0.0; grid.cells.len()],
+        }
+        self.previous = Some(grid);
+    }
+
+    /// Share of cells unchanged for at least a minute, if anything was sampled
+    pub fn static_fraction(&self) -> Option<f64> {
+        if self.still_secs.is_empty() {
+            return None;
+        }
+        let still = self.static_cells().iter().filter(|s| **s).count();
+        Some(still as f64 / self.still_secs.len() as f64)
+    }
+
+    /// Which cells have been unchanged for at least a minute, row by row
+    pub fn static_cells(&self) -> Vec<bool> {
+        self.still_secs.iter().map(|s| *s >= STATIC_AFTER_SECS).collect()
+    }
+}
+
+/// Scale `base` between 4× (nothing static) and ¼ (half the panel or more static)
+pub fn adaptive_interval(base: u64, static_fraction: f64) -> u64 {
+    let risk = (static_fraction / FULL_RISK_FRACTION).clamp(0.0, 1.0);
+    let factor = 4f64.powf(1.0 - 2.0 * risk);
+    ((base as f64 * factor).round() as u64).clamp(MIN_INTERVAL_SECS, MAX_INTERVAL_SECS)
+}
+
+struct Inner {
+    display: RefCell<DisplayInfo>,
+    tracker: RefCell<ActivityTracker>,
+    last_sample: Cell<Option<Instant>>,
+    timer: RefCell<Option<SourceId>>,
+    /// Capture error, reported once through `take_error`
+    error: RefCell<Option<String>>,
+    failed: Cell<bool>,
+}
+
+/// Samples one display on a timer while it exists
+pub struct ActivitySampler {
+    inner: Rc<Inner>,
+}
+
+impl ActivitySampler {
+    pub fn start(display: DisplayInfo) -> Self {
+        let inner = Rc::new(Inner {
+            display: RefCell::new(display),
+            tracker: RefCell::new(ActivityTracker::default()),
+            last_sample: Cell::new(None),
+            timer: RefCell::new(None),
+            error: RefCell::new(None),
+            failed: Cell::new(false),
+        });
+
+        sample(&inner);
+        let weak = Rc::downgrade(&inner);
+        let sid = glib::timeout_add_local(Duration::from_secs(SAMPLE_SECS), move || match weak.upgrade() {
+            Some(inner) => {
+                sample(&inner);
+                ControlFlow::Continue
+            }
+            None => ControlFlow::Break,
+        });
+        *inner.timer.borrow_mut() = Some(sid);
+
+        Self { inner }
+    }
+
+    /// Follow the display after a re-layout moved it
+    pub fn set_display(&self, display: DisplayInfo) {
+        *self.inner.display.borrow_mut() = display;
+    }
+
+    pub fn static_fraction(&self) -> Option<f64> {
+        self.inner.tracker.borrow().static_fraction()
+    }
+
+    /// `base` adjusted for what was sampled so far; `base` itself until then
+    pub fn interval(&self, base: u64) -> u64 {
+        self.static_fraction().map(|f| adaptive_interval(base, f)).unwrap_or(base)
+    }
+
+    pub fn take_error(&self) -> Option<String> {
+        self.inner.error.borrow_mut().take()
+    }
+}
+
+impl Drop for ActivitySampler {
+    fn drop(&mut self) {
+        if let Some(id) = self.inner.timer.borrow_mut().take() {
+            id.remove();
+        }
+    }
+}
+
+fn sample(inner: &Rc<Inner>) {
+    let weak = Rc::downgrade(inner);
+    framebuffer::capture(move |result| {
+        let Some(inner) = weak.upgrade() else { return };
+        match result {
+            Ok(frame) => {
+                // Another sampler's frame may come round again; it shows nothing new
+                let last = inner.last_sample.get();
+                if last == Some(frame.captured) {
+                    return;
+                }
+                inner.last_sample.set(Some(frame.captured));
+                let elapsed = last.map(|t| frame.captured.saturating_duration_since(t).as_secs_f64()).unwrap_or(0.0);
+                let grid = frame.grid(&inner.display.borrow(), GRID_COLS, GRID_ROWS);
+                inner.tracker.borrow_mut().update(grid, elapsed);
+                inner.failed.set(false);
+            }
+            Err(e) => {
+                // Report the first failure of a run, not one per sample
+                if !inner.failed.replace(true) {
+                    *inner.error.borrow_mut() = Some(e);
+                }
+            }
+        }
+    });
+}
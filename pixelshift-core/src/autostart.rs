@@ -0,0 +1,211 @@
+//! Start shifting on login, either as a systemd user service or as an XDG
+//! autostart entry. Both run the headless `pixelshift run` for one monitor, so
+//! its saved profile is picked up each time the session starts.
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::DisplayInfo;
+
+pub const SERVICE_NAME: &str = "pixelshift-gtk.service";
+const DESKTOP_NAME: &str = "pixelshift-gtk.desktop";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AutostartKind {
+    Systemd,
+    Desktop,
+}
+
+impl AutostartKind {
+    /// systemd when a user manager is running, the autostart directory otherwise
+    pub fn preferred() -> Self {
+        if glib::user_runtime_dir().join("systemd").exists() {
+            AutostartKind::Systemd
+        } else {
+            AutostartKind::Desktop
+        }
+    }
+
+    pub fn path(self) -> PathBuf {
+        match self {
+            AutostartKind::Systemd => glib::user_config_dir().join("systemd").join("user").join(SERVICE_NAME),
+            AutostartKind::Desktop => glib::user_config_dir().join("autostart").join(DESKTOP_NAME),
+        }
+    }
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            AutostartKind::Systemd => "systemd user service",
+            AutostartKind::Desktop => "XDG autostart entry",
+        }
+    }
+}
+
+/// What is currently installed, if anything
+#[derive(Debug, Clone)]
+pub struct AutostartStatus {
+    pub kind: AutostartKind,
+    /// The unit is enabled (always true for an autostart entry we wrote)
+    pub enabled: bool,
+    /// Monitor passed to `run --display`
+    pub display: Option<String>,
+}
+
+impl AutostartStatus {
+    pub fn summary(&self) -> String {
+        let target = self.display.as_deref().map(|d| format!(" for {}", d)).unwrap_or_default();
+        let state = if self.enabled { "enabled" } else { "installed but disabled" };
+        format!("{} {}{}", self.kind.describe(), state, target)
+    }
+}
+
+pub fn status() -> Option<AutostartStatus> {
+    [AutostartKind::Systemd, AutostartKind::Desktop].into_iter().find_map(|kind| {
+        let contents = std::fs::read_to_string(kind.path()).ok()?;
+        let enabled = match kind {
+            AutostartKind::Systemd => systemctl(&["is-enabled", SERVICE_NAME]).is_ok_and(|out| out.trim() == "enabled"),
+            AutostartKind::Desktop => true,
+        };
+        Some(AutostartStatus {
+            kind,
+            enabled,
+            display: parse_display_arg(&contents),
+        })
+    })
+}
+
+/// Write and enable the unit or entry for `display`, replacing any previous one
+pub fn install(kind: AutostartKind, display: &DisplayInfo) -> Result<PathBuf, String> {
+    uninstall()?;
+
+    let exe = cli_executable();
+    if !exe.is_absolute() {
+        return Err("cannot find the `pixelshift` command next to this program".to_string());
+    }
+    let exe = exe.to_string_lossy();
+    let monitor = display.monitor_id();
+
+    let contents = match kind {
+        AutostartKind::Systemd => format!(
+            "[Unit]\n\
+             Description=OLED pixel shifter for {monitor}\n\
+             PartOf=graphical-session.target\n\
+             After=graphical-session.target\n\
+             \n\
+             [Service]\n\
+             Type=simple\n\
+             ExecStart={start}\n\
+             # Restore the display before the scheduler is signalled; SIGTERM resets it too\n\
+             ExecStop={stop}\n\
+             Restart=on-failure\n\
+             \n\
+             [Install]\n\
+             WantedBy=graphical-session.target\n",
+            monitor = monitor,
+            start = command_line(kind, &[&exe, "run", "--display", &monitor]),
+            stop = command_line(kind, &[&exe, "recover", "--force"]),
+        ),
+        AutostartKind::Desktop => format!(
+            "[Desktop Entry]\n\
+             Type=Application\n\
+             Name=OLED Pixel Shifter\n\
+             Comment=Shift {monitor} periodically to prevent burn-in\n\
+             Exec={start}\n\
+             Icon=video-display\n\
+             Terminal=false\n\
+             NoDisplay=true\n\
+             X-GNOME-Autostart-enabled=true\n",
+            monitor = monitor,
+            start = command_line(kind, &[&exe, "run", "--display", &monitor]),
+        ),
+    };
+
+    let path = kind.path();
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("cannot create {}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&path, contents).map_err(|e| format!("cannot write {}: {}", path.display(), e))?;
+
+    if kind == AutostartKind::Systemd {
+        systemctl(&["daemon-reload"])?;
+        // Enable only: starting now would fight a scheduler that is already running
+        systemctl(&["enable", SERVICE_NAME])?;
+    }
+    Ok(path)
+}
+
+/// The headless `pixelshift` command: next to the running executable when
+/// installed together, otherwise whichever is first on `PATH`
+pub fn cli_executable() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .map(|exe| exe.with_file_name("pixelshift"))
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("pixelshift"))
+}
+
+/// Disable and remove whichever kinds are installed
+pub fn uninstall() -> Result<(), String> {
+    let unit = AutostartKind::Systemd.path();
+    if unit.exists() {
+        // Stopping runs ExecStop, which restores the display
+        let _ = systemctl(&["disable", "--now", SERVICE_NAME]);
+        std::fs::remove_file(&unit).map_err(|e| format!("cannot remove {}: {}", unit.display(), e))?;
+        let _ = systemctl(&["daemon-reload"]);
+    }
+
+    let entry = AutostartKind::Desktop.path();
+    if entry.exists() {
+        std::fs::remove_file(&entry).map_err(|e| format!("cannot remove {}: {}", entry.display(), e))?;
+    }
+    Ok(())
+}
+
+fn systemctl(args: &[&str]) -> Result<String, String> {
+    let output = Command::new("systemctl")
+        .arg("--user")
+        .args(args)
+        .output()
+        .map_err(|e| format!("cannot run systemctl: {}", e))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+    if output.status.success() {
+        Ok(stdout)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        // `is-enabled` reports "disabled" with a non-zero exit code
+        Err(if stderr.trim().is_empty() { stdout } else { stderr.to_string() })
+    }
+}
+
+/// Quote each argument for an ExecStart= or Exec= line
+fn command_line(kind: AutostartKind, args: &[&str]) -> String {
+    args.iter()
+        .map(|arg| {
+            let plain = !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_./:=+,".contains(c));
+            if plain {
+                return arg.to_string();
+            }
+            let mut quoted = String::from("\"");
+            for c in arg.chars() {
+                match (kind, c) {
+                    (_, '"' | '\\') => quoted.extend(['\\', c]),
+                    (AutostartKind::Desktop, '`' | '$') => quoted.extend(['\\', c]),
+                    (AutostartKind::Systemd, '$' | '%') => quoted.extend([c, c]),
+                    _ => quoted.push(c),
+                }
+            }
+            quoted.push('"');
+            quoted
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Recover the `--display` argument from an installed ExecStart= or Exec= line
+fn parse_display_arg(contents: &str) -> Option<String> {
+    let line = contents.lines().find(|l| l.starts_with("ExecStart=") || l.starts_with("Exec="))?;
+    let mut words = line.split_whitespace();
+    words.find(|w| *w == "--display")?;
+    words.next().map(|w| w.trim_matches('"').to_string())
+}
@@ -0,0 +1,238 @@
+//! The RandR shift methods and the reset that undoes them, all through
+//! `xrandr` (or the dry-run log, see `dryrun`). Drivers often take a transform
+//! or panning request and ignore it, so every shift is read back from RandR
+//! (see `readback`) before it counts as applied.
+
+use std::fmt;
+
+use crate::history::Level;
+use crate::profiles::method_name;
+use crate::readback::{self, Verification};
+use crate::{dryrun, DisplayInfo, SetTextSafe};
+
+/// Why a shift did not take
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShiftError {
+    /// xrandr refused it; its message
+    Refused(String),
+    /// xrandr took it but RandR reads back something else; what it reports
+    Ineffective(String),
+}
+
+impl fmt::Display for ShiftError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ShiftError::Refused(reason) => f.write_str(reason),
+            ShiftError::Ineffective(actual) => write!(f, "accepted but ineffective: RandR reports {}", actual),
+        }
+    }
+}
+
+/// Safe pixel shift using only panning (no transform matrices or framebuffer changes)
+pub fn apply_pixel_shift_panning(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &dyn SetTextSafe) -> Result<(), String> {
+    // Simple panning - the panning area is in screen coordinates, so start from the output's origin
+    let panning_spec = format!("{}x{}+{}+{}", 
+        display.width, display.height, display.x + x_offset, display.y + y_offset);
+    
+    status_label.set_text_safe(&format!("Applying panning: xrandr --output {} --panning {}", 
+        display.name, panning_spec));
+
+    let result = dryrun::output("xrandr", &["--output", &display.name, "--panning", &panning_spec], status_label);
+
+    match result {
+        Ok(output) => {
+            if output.status.success() {
+                status_label.set_text_safe(&format!("✓ Panning applied: +{}+{}", x_offset, y_offset));
+                Ok(())
+            } else {
+                let err = String::from_utf8_lossy(&output.stderr);
+                status_label.set_text_safe(&format!("✗ Panning failed: {}", err));
+                Err(refusal(&err))
+            }
+        }
+        Err(e) => {
+            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
+            Err(format!("cannot run xrandr: {}", e))
+        }
+    }
+}
+
+/// Alternative method using CRTC position changes (fixed format)
+pub fn apply_pixel_shift_position(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &dyn SetTextSafe) -> Result<(), String> {
+    // `--pos` is absolute and only takes "XxY", so move relative to where the output sits
+    let pos_str = format!("{}x{}", display.x + x_offset, display.y + y_offset);
+    
+    status_label.set_text_safe(&format!("Applying position shift: xrandr --output {} --pos {}", 
+        display.name, pos_str));
+
+    let result = dryrun::output("xrandr", &["--output", &display.name, "--pos", &pos_str], status_label);
+
+    match result {
+        Ok(output) => {
+            if output.status.success() {
+                status_label.set_text_safe(&format!("✓ Position shift applied: {}", pos_str));
+                Ok(())
+            } else {
+                let err = String::from_utf8_lossy(&output.stderr);
+                status_label.set_text_safe(&format!("✗ Position shift failed: {}", err));
+                Err(refusal(&err))
+            }
+        }
+        Err(e) => {
+            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
+            Err(format!("cannot run xrandr: {}", e))
+        }
+    }
+}
+
+/// New method: Use transform matrix without framebuffer changes (most stable)
+pub fn apply_pixel_shift_transform(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &dyn SetTextSafe) -> Result<(), String> {
+    // Calculate transform values as ratios (more precise than small pixel values)
+    let tx = x_offset as f64 / display.width as f64;
+    let ty = y_offset as f64 / display.height as f64;
+    
+    // Create transform matrix: translation only
+    let transform_str = format!("1,0,{:.6},0,1,{:.6},0,0,1", tx, ty);
+    
+    status_label.set_text_safe(&format!("Applying transform shift: xrandr --output {} --transform {}", 
+        display.name, transform_str));
+
+    let result = dryrun::output("xrandr", &["--output", &display.name, "--transform", &transform_str], status_label);
+
+    match result {
+        Ok(output) => {
+            if output.status.success() {
+                status_label.set_text_safe(&format!("✓ Transform applied: {}px offset", 
+                    if x_offset != 0 { x_offset } else { y_offset }));
+                Ok(())
+            } else {
+                let err = String::from_utf8_lossy(&output.stderr);
+                status_label.set_text_safe(&format!("✗ Transform failed: {}", err));
+                Err(refusal(&err))
+            }
+        }
+        Err(e) => {
+            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
+            Err(format!("cannot run xrandr: {}", e))
+        }
+    }
+}
+
+/// Flicker-free panning with proper reset
+pub fn apply_pixel_shift_panning_smooth(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &dyn SetTextSafe) -> Result<(), String> {
+    // Use a slightly larger panning area to avoid edge issues
+    let panning_w = display.width + 10;
+    let panning_h = display.height + 10;
+    let panning_spec = format!("{}x{}+{}+{}", panning_w, panning_h, display.x + x_offset, display.y + y_offset);
+    
+    status_label.set_text_safe(&format!("Applying smooth panning: xrandr --output {} --panning {}", 
+        display.name, panning_spec));
+
+    let result = dryrun::output("xrandr", &["--output", &display.name, "--panning", &panning_spec], status_label);
+
+    match result {
+        Ok(output) => {
+            if output.status.success() {
+                status_label.set_text_safe(&format!("✓ Smooth panning applied: +{}+{}", x_offset, y_offset));
+                Ok(())
+            } else {
+                let err = String::from_utf8_lossy(&output.stderr);
+                status_label.set_text_safe(&format!("✗ Smooth panning failed: {}", err));
+                Err(refusal(&err))
+            }
+        }
+        Err(e) => {
+            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
+            Err(format!("cannot run xrandr: {}", e))
+        }
+    }
+}
+
+/// The line of xrandr's complaint that names the problem
+fn refusal(stderr: &str) -> String {
+    match stderr.lines().map(str::trim).find(|line| !line.is_empty()) {
+        Some(line) => line.to_string(),
+        None => "xrandr refused it".to_string(),
+    }
+}
+
+const IDENTITY_TRANSFORM: &str = "1,0,0,0,1,0,0,0,1";
+
+/// Reset display to normal state (enhanced)
+pub fn reset_display_safe(display: &DisplayInfo, status_label: &dyn SetTextSafe) -> bool {
+    dryrun::transcribed(status_label, |status_label| reset_display_fallbacks(display, status_label))
+}
+
+fn reset_display_fallbacks(display: &DisplayInfo, status_label: &dyn SetTextSafe) -> bool {
+    let origin = format!("{}x{}", display.x, display.y);
+    let succeeded = |args: &[&str]| {
+        let mut full = vec!["--output", display.name.as_str()];
+        full.extend_from_slice(args);
+        dryrun::output("xrandr", &full, status_label).is_ok_and(|output| output.status.success())
+    };
+
+    // Method 1: undo whatever any shift method left behind, in one call
+    if succeeded(&["--transform", IDENTITY_TRANSFORM, "--panning", "0x0", "--pos", &origin]) {
+        status_label.set_text_safe(&format!("✓ Display reset successful for {}", display.name));
+        return true;
+    }
+
+    // Servers reject the whole call if they lack one part (Xvfb has neither
+    // transforms nor panning), so undo each kind of shift on its own
+    let mut any = false;
+    for (what, args) in [
+        ("Transform", ["--transform", IDENTITY_TRANSFORM]),
+        ("Panning", ["--panning", "0x0"]),
+        ("Position", ["--pos", origin.as_str()]),
+    ] {
+        if succeeded(&args) {
+            status_label.set_text_safe(&format!("✓ {} reset successful for {}", what, display.name));
+            any = true;
+        }
+    }
+    if any {
+        return true;
+    }
+
+    // Last resort: full auto reset
+    if succeeded(&["--auto"]) {
+        status_label.set_text_safe(&format!("✓ Auto reset successful for {}", display.name));
+        true
+    } else {
+        status_label.set_text_safe(&format!("✗ All reset methods failed for {}", display.name));
+        false
+    }
+}
+
+/// Shift with the method selected in the method combo, then check with RandR
+/// that the offset actually took effect
+pub fn shift(method_idx: u32, display: &DisplayInfo, offset: (i32, i32), status_label: &dyn SetTextSafe) -> Result<(), ShiftError> {
+    let (x_offset, y_offset) = offset;
+    dryrun::transcribed(status_label, |status_label| match method_idx {
+        0 => apply_pixel_shift_transform(display, x_offset, y_offset, status_label),
+        1 => apply_pixel_shift_panning_smooth(display, x_offset, y_offset, status_label),
+        2 => apply_pixel_shift_position(display, x_offset, y_offset, status_label),
+        3 => apply_pixel_shift_panning(display, x_offset, y_offset, status_label),
+        _ => apply_pixel_shift_transform(display, x_offset, y_offset, status_label),
+    })
+    .map_err(ShiftError::Refused)?;
+
+    match readback::verify(method_idx, display, offset) {
+        Verification::Applied => Ok(()),
+        Verification::Ineffective(actual) => {
+            status_label.set_text_safe(&format!("✗ {} accepted but ineffective on {}: RandR reports {}", method_name(method_idx), display.name, actual));
+            Err(ShiftError::Ineffective(actual))
+        }
+        // Accepted, and nothing says otherwise
+        Verification::Unknown(reason) => {
+            status_label.log_safe(Level::Warning, &format!("Could not verify the shift on {}: {}", display.name, reason));
+            Ok(())
+        }
+    }
+}
+
+/// `shift`, for callers that only need to know whether it took
+pub fn apply_pixel_shift(method_idx: u32, display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &dyn SetTextSafe) -> bool {
+    shift(method_idx, display, (x_offset, y_offset), status_label).is_ok()
+}
+
@@ -0,0 +1,356 @@
+//! Output brightness through RandR or DDC/CI, and dimming it while the user
+//! is away.
+//!
+//! RandR brightness is a gamma scale applied by the X server; the panel keeps
+//! its backlight or emission level and only the content gets darker. DDC/CI
+//! sets the monitor's own brightness (VCP 0x10), which is what actually
+//! reduces OLED wear, but needs i2c-dev access (see `ddc`).
+//!
+//! Idle dimming follows each monitor's profile (`idle-dim`, `idle-dim-level`,
+//! `idle-dim-method`, see `profiles`): after that many minutes without
+//! input the brightness drops to the given share of what it was, and the
+//! previous value comes back with the first input. Reading and writing the
+//! brightness can take a while over DDC/CI, so it happens on worker threads.
+
+use glib::source::SourceId;
+use glib::{ControlFlow, KeyFile, KeyFileFlags};
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::ddc::{self, Ddc};
+use crate::history::Level;
+use crate::idle;
+use crate::profiles::ProfileStore;
+use crate::scheduler::Scheduler;
+use crate::{dryrun, DisplayInfo, Quiet, SetTextSafe};
+
+/// Idle time is polled this often so brightness comes back quickly
+const POLL_SECS: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BrightnessMethod {
+    #[default]
+    Randr,
+    Ddc,
+}
+
+impl BrightnessMethod {
+    pub fn key(&self) -> &'static str {
+        match self {
+            Self::Randr => "randr",
+            Self::Ddc => "ddc",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<Self> {
+        match key {
+            "randr" => Some(Self::Randr),
+            "ddc" => Some(Self::Ddc),
+            _ => None,
+        }
+    }
+
+    /// Current brightness as a fraction of the maximum
+    pub fn read(&self, display: &DisplayInfo) -> Result<f64, String> {
+        match self {
+            Self::Randr => Ok(randr_levels().get(&display.name).copied().unwrap_or(1.0)),
+            Self::Ddc => {
+                let value = Ddc::for_display(display)?.get_vcp(ddc::VCP_BRIGHTNESS)?;
+                Ok(value.current as f64 / value.maximum.max(1) as f64)
+            }
+        }
+    }
+
+    pub fn write(&self, display: &DisplayInfo, level: f64) -> Result<(), String> {
+        let level = level.clamp(0.0, 1.0);
+        match self {
+            Self::Randr => {
+                // Runs on worker threads, away from any status area
+                let output = dryrun::output("xrandr", &["--output", &display.name, "--brightness", &format!("{:.2}", level)], &Quiet)
+                    .map_err(|e| format!("cannot run xrandr: {}", e))?;
+                if output.status.success() {
+                    Ok(())
+                } else {
+                    Err(format!("xrandr --brightness failed: {}", String::from_utf8_lossy(&output.stderr).trim()))
+                }
+            }
+            Self::Ddc => {
+                let mut ddc = Ddc::for_display(display)?;
+                let maximum = ddc.get_vcp(ddc::VCP_BRIGHTNESS)?.maximum;
+                ddc.set_vcp(ddc::VCP_BRIGHTNESS, (level * maximum as f64).round() as u16)
+            }
+        }
+    }
+}
+
+/// RandR brightness per connector from `xrandr --verbose`
+pub fn randr_levels() -> HashMap<String, f64> {
+    let mut brightness = HashMap::new();
+    let Ok(output) = Command::new("xrandr").arg("--verbose").output() else { return brightness };
+    let text = String::from_utf8_lossy(&output.stdout);
+
+    let mut current: Option<&str> = None;
+    for line in text.lines() {
+        if !line.starts_with(' ') && !line.starts_with('\t') {
+            current = line.split_whitespace().next();
+        } else if let (Some(name), Some(value)) = (current, line.trim().strip_prefix("Brightness:")) {
+            if let Ok(value) = value.trim().parse::<f64>() {
+                brightness.insert(name.to_string(), value);
+            }
+        }
+    }
+    brightness
+}
+
+/// A monitor dimmed by us and what to put back. Written to
+/// `$XDG_RUNTIME_DIR/pixelshift-gtk/brightness` before the dim is applied, so
+/// `recover` can undo it after a crash the way it undoes a shift.
+#[derive(Debug, Clone)]
+pub struct DimRecord {
+    /// Process that dimmed it
+    pub pid: u32,
+    pub display: String,
+    pub method: BrightnessMethod,
+    pub previous: f64,
+}
+
+impl DimRecord {
+    /// Whether the dimming process is still alive
+    pub fn owner_running(&self) -> bool {
+        PathBuf::from(format!("/proc/{}", self.pid)).exists()
+    }
+}
+
+const RECORD_PREFIX: &str = "dimmed ";
+
+/// Dims run on worker threads, so updates to the file take turns
+static RECORDS_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn records_path() -> PathBuf {
+    glib::user_runtime_dir().join("pixelshift-gtk").join("brightness")
+}
+
+fn load_key_file() -> KeyFile {
+    let key_file = KeyFile::new();
+    let _ = key_file.load_from_file(records_path(), KeyFileFlags::NONE);
+    key_file
+}
+
+fn save_key_file(key_file: &KeyFile) {
+    let path = records_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    // Best effort, like the shift state: losing it only degrades `recover`
+    let _ = key_file.save_to_file(&path);
+}
+
+/// Every dim not yet put back, from any process
+pub fn dim_records() -> Vec<DimRecord> {
+    let _guard = RECORDS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let key_file = load_key_file();
+    key_file
+        .groups()
+        .iter()
+        .filter_map(|group| {
+            Some(DimRecord {
+                pid: key_file.integer(group, "pid").ok()? as u32,
+                display: group.strip_prefix(RECORD_PREFIX)?.to_string(),
+                method: BrightnessMethod::from_key(&key_file.string(group, "method").ok()?)?,
+                previous: key_file.double(group, "previous").ok()?,
+            })
+        })
+        .collect()
+}
+
+pub fn save_dim_record(record: &DimRecord) {
+    let _guard = RECORDS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let key_file = load_key_file();
+    let group = format!("{}{}", RECORD_PREFIX, record.display);
+    key_file.set_integer(&group, "pid", record.pid as i32);
+    key_file.set_string(&group, "method", record.method.key());
+    key_file.set_double(&group, "previous", record.previous);
+    save_key_file(&key_file);
+}
+
+pub fn clear_dim_record(display: &str) {
+    let _guard = RECORDS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let key_file = load_key_file();
+    if key_file.remove_group(&format!("{}{}", RECORD_PREFIX, display)).is_ok() {
+        save_key_file(&key_file);
+    }
+}
+
+/// Read the brightness, note it and drop it to `share` of itself. Blocks on
+/// the monitor, so the dimmer runs it off the main loop.
+fn dim(display: &DisplayInfo, method: BrightnessMethod, share: f64) -> Result<DimRecord, String> {
+    let record = DimRecord { pid: std::process::id(), display: display.name.clone(), method, previous: method.read(display)? };
+    save_dim_record(&record);
+    if let Err(e) = method.write(display, record.previous * share) {
+        clear_dim_record(&display.name);
+        return Err(e);
+    }
+    Ok(record)
+}
+
+fn undim(display: &DisplayInfo, record: &DimRecord) -> Result<(), String> {
+    record.method.write(display, record.previous)?;
+    clear_dim_record(&record.display);
+    Ok(())
+}
+
+struct Dimmed {
+    display: DisplayInfo,
+    record: DimRecord,
+}
+
+struct Inner {
+    scheduler: Scheduler,
+    status: Box<dyn SetTextSafe>,
+    dimmed: RefCell<Vec<Dimmed>>,
+    /// Monitors with a dim or restore under way on a worker thread
+    busy: RefCell<HashSet<String>>,
+    /// Monitors that could not be dimmed this time away, not retried until input
+    failed: RefCell<HashSet<String>>,
+    timer: RefCell<Option<SourceId>>,
+}
+
+impl Inner {
+    fn poll(self: &Rc<Self>) {
+        // Any input brings everything back
+        let idle = match idle::idle_time() {
+            Some(idle) if idle >= Duration::from_secs(POLL_SECS * 2) => idle,
+            _ => {
+                self.failed.borrow_mut().clear();
+                self.restore();
+                return;
+            }
+        };
+        let profiles = ProfileStore::load();
+
+        for display in self.scheduler.displays() {
+            let Some(profile) = profiles.get(&display.monitor_id()) else { continue };
+            if profile.idle_dim == 0 || idle < Duration::from_secs(profile.idle_dim as u64 * 60) {
+                continue;
+            }
+            if self.dimmed.borrow().iter().any(|d| d.display.is_same_monitor(&display))
+                || self.busy.borrow().contains(&display.name)
+                || self.failed.borrow().contains(&display.name)
+            {
+                continue;
+            }
+
+            self.busy.borrow_mut().insert(display.name.clone());
+            let (method, level) = (profile.idle_dim_method, profile.idle_dim_level);
+            let weak = Rc::downgrade(self);
+            glib::spawn_future_local(async move {
+                let worker_display = display.clone();
+                let result = gio::spawn_blocking(move || dim(&worker_display, method, level as f64 / 100.0))
+                    .await
+                    .unwrap_or_else(|_| Err("the brightness worker panicked".to_string()));
+                // Gone means stopped: the record lets `recover` undo a dim that lands now
+                let Some(inner) = weak.upgrade() else { return };
+                inner.busy.borrow_mut().remove(&display.name);
+                match result {
+                    Ok(record) => {
+                        inner.status.log_safe(Level::Info, &format!("Idle: dimmed {} to {}%", display.name, level));
+                        inner.dimmed.borrow_mut().push(Dimmed { display, record });
+                    }
+                    Err(e) => {
+                        inner.status.set_text_safe(&format!("✗ Idle dimming on {}: {}", display.name, e));
+                        inner.failed.borrow_mut().insert(display.name);
+                    }
+                }
+            });
+        }
+    }
+
+    /// Put back every dim, on worker threads
+    fn restore(self: &Rc<Self>) {
+        let dimmed: Vec<Dimmed> = self.dimmed.borrow_mut().drain(..).collect();
+        for d in dimmed {
+            self.busy.borrow_mut().insert(d.display.name.clone());
+            let weak = Rc::downgrade(self);
+            glib::spawn_future_local(async move {
+                let Dimmed { display, record } = d;
+                let worker_display = display.clone();
+                let result = gio::spawn_blocking(move || undim(&worker_display, &record))
+                    .await
+                    .unwrap_or_else(|_| Err("the brightness worker panicked".to_string()));
+                let Some(inner) = weak.upgrade() else { return };
+                inner.busy.borrow_mut().remove(&display.name);
+                inner.report_restore(&display, result);
+            });
+        }
+    }
+
+    /// Put back every dim before returning, for shutdown
+    fn restore_now(&self) {
+        let dimmed: Vec<Dimmed> = self.dimmed.borrow_mut().drain(..).collect();
+        for d in dimmed {
+            self.report_restore(&d.display, undim(&d.display, &d.record));
+        }
+    }
+
+    fn report_restore(&self, display: &DisplayInfo, result: Result<(), String>) {
+        match result {
+            Ok(()) => self.status.log_safe(Level::Info, &format!("✓ Brightness restored on {}", display.name)),
+            Err(e) => self.status.set_text_safe(&format!("✗ Could not restore brightness on {}: {}", display.name, e)),
+        }
+    }
+}
+
+/// Dims monitors whose profile asks for it while the user is idle
+pub struct IdleDimmer {
+    inner: Rc<Inner>,
+}
+
+impl IdleDimmer {
+    pub fn start(scheduler: &Scheduler, status: impl SetTextSafe + 'static) -> Self {
+        let inner = Rc::new(Inner {
+            scheduler: scheduler.clone(),
+            status: Box::new(status),
+            dimmed: RefCell::new(Vec::new()),
+            busy: RefCell::new(HashSet::new()),
+            failed: RefCell::new(HashSet::new()),
+            timer: RefCell::new(None),
+        });
+
+        // Take over dims left by a process that died, so the next input undoes
+        // them instead of the next dim starting from the darkened level
+        let displays = scheduler.displays();
+        for record in dim_records().into_iter().filter(|r| !r.owner_running()) {
+            let Some(display) = displays.iter().find(|d| d.name == record.display) else { continue };
+            let record = DimRecord { pid: std::process::id(), ..record };
+            save_dim_record(&record);
+            inner.dimmed.borrow_mut().push(Dimmed { display: display.clone(), record });
+        }
+
+        let weak = Rc::downgrade(&inner);
+        let sid = glib::timeout_add_local(Duration::from_secs(POLL_SECS), move || match weak.upgrade() {
+            Some(inner) => {
+                inner.poll();
+                ControlFlow::Continue
+            }
+            None => ControlFlow::Break,
+        });
+        *inner.timer.borrow_mut() = Some(sid);
+
+        Self { inner }
+    }
+}
+
+impl Drop for IdleDimmer {
+    fn drop(&mut self) {
+        // Never leave a monitor dark behind us
+        self.inner.restore_now();
+        if let Some(id) = self.inner.timer.borrow_mut().take() {
+            id.remove();
+        }
+    }
+}
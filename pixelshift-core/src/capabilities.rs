@@ -0,0 +1,292 @@
+//! Which shift methods an output's driver actually honours.
+//!
+//! `probe` tries each method with a 1-pixel shift, reads the result back from
+//! RandR (see `readback`) and resets the output before trying the next, so the
+//! display ends up where it started. Results are cached per monitor identity
+//! in `$XDG_CACHE_HOME/pixelshift-gtk/methods.ini`:
+//!
+//! ```ini
+//! [monitor LGD-0617-1A2B3C]
+//! name=OLED 27GS95
+//! probed=1760000000
+//! transform=ok
+//! panning-smooth=X Error of failed request:  BadMatch
+//! position=ok
+//! panning=accepted but ineffective: RandR reports no panning
+//! runtime-position=1;1760003600;xrandr refused it
+//! ```
+//!
+//! A method is `ok` or the reason the probe saw it fail; methods without a key
+//! have not been tried. Failures the scheduler runs into while shifting are
+//! kept apart under `runtime-<method>` as count, time of the latest and reason:
+//! one failed tick can be a hotplug or a busy X server, so a method is only
+//! skipped once it has failed `RUNTIME_FAILURE_LIMIT` times in a row, and that
+//! is forgotten after `RUNTIME_FAILURE_TTL` without another failure. A new
+//! probe replaces both.
+
+use glib::{KeyFile, KeyFileFlags};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::profiles::{METHOD_KEYS, METHOD_NAMES};
+use crate::backend::shift;
+use crate::{dryrun, reset_display_safe, state, DisplayInfo, Quiet};
+
+const GROUP_PREFIX: &str = "monitor ";
+const RUNTIME_PREFIX: &str = "runtime-";
+
+/// Failures in a row before the scheduler stops using a method
+pub const RUNTIME_FAILURE_LIMIT: u32 = 3;
+
+/// Seconds after the latest runtime failure before a method gets another chance
+pub const RUNTIME_FAILURE_TTL: u64 = 24 * 60 * 60;
+
+/// Small enough not to be noticed during the probe, large enough to read back
+const PROBE_OFFSET: (i32, i32) = (1, 1);
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Support {
+    #[default]
+    Unknown,
+    Works,
+    Fails(String),
+}
+
+/// Failures of one method while auto-shifting, since the last success
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeFailure {
+    pub count: u32,
+    /// Unix time of the latest
+    pub last: u64,
+    pub reason: String,
+}
+
+impl RuntimeFailure {
+    /// Failed often and recently enough to skip the method
+    pub fn established(&self, now: u64) -> bool {
+        self.count >= RUNTIME_FAILURE_LIMIT && !self.expired(now)
+    }
+
+    fn expired(&self, now: u64) -> bool {
+        now.saturating_sub(self.last) >= RUNTIME_FAILURE_TTL
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        let mut fields = value.splitn(3, ';');
+        Some(Self {
+            count: fields.next()?.parse().ok()?,
+            last: fields.next()?.parse().ok()?,
+            reason: fields.next().unwrap_or_default().to_string(),
+        })
+    }
+
+    fn to_value(&self) -> String {
+        format!("{};{};{}", self.count, self.last, self.reason)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Capabilities {
+    pub name: String,
+    /// Unix time of the last full probe, 0 if only runtime failures are known
+    pub probed: u64,
+    /// What the probe found, indexed like `METHOD_KEYS`
+    pub methods: [Support; 4],
+    /// What went wrong while shifting since, indexed the same way
+    pub runtime: [Option<RuntimeFailure>; 4],
+}
+
+impl Capabilities {
+    /// Not known to fail; untried methods and ones that failed only now and
+    /// then count as supported
+    pub fn supports(&self, method_idx: u32) -> bool {
+        self.reason(method_idx).is_none()
+    }
+
+    /// Why `method_idx` is not supported
+    pub fn reason(&self, method_idx: u32) -> Option<&str> {
+        if let Some(Support::Fails(reason)) = self.methods.get(method_idx as usize) {
+            return Some(reason);
+        }
+        match self.runtime.get(method_idx as usize) {
+            Some(Some(failure)) if failure.established(now()) => Some(&failure.reason),
+            _ => None,
+        }
+    }
+
+    /// Methods to use instead of `method_idx`, in combo order starting after
+    /// it, leaving out those known to fail
+    pub fn fallbacks(&self, method_idx: u32) -> impl Iterator<Item = u32> + '_ {
+        let count = METHOD_KEYS.len() as u32;
+        (1..count).map(move |step| (method_idx + step) % count).filter(|&m| self.supports(m))
+    }
+
+    /// The first of `fallbacks`
+    pub fn fallback(&self, method_idx: u32) -> Option<u32> {
+        self.fallbacks(method_idx).next()
+    }
+
+    /// One line per method, e.g. for the CLI or a tooltip
+    pub fn summary(&self) -> Vec<String> {
+        let now = now();
+        self.methods
+            .iter()
+            .zip(&self.runtime)
+            .zip(METHOD_NAMES)
+            .map(|((support, runtime), name)| {
+                let line = match support {
+                    Support::Unknown => format!("{}: not tried", name),
+                    Support::Works => format!("{}: works", name),
+                    Support::Fails(reason) => return format!("{}: unsupported ({})", name, reason),
+                };
+                match runtime {
+                    Some(failure) if failure.established(now) => {
+                        format!("{}: unsupported, failed {} times while shifting ({})", name, failure.count, failure.reason)
+                    }
+                    Some(failure) if !failure.expired(now) => {
+                        format!("{}, failed {} of {} times while shifting ({})", line, failure.count, RUNTIME_FAILURE_LIMIT, failure.reason)
+                    }
+                    _ => line,
+                }
+            })
+            .collect()
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+pub struct CapabilityStore {
+    path: PathBuf,
+    records: HashMap<String, Capabilities>,
+}
+
+impl CapabilityStore {
+    pub fn default_path() -> PathBuf {
+        glib::user_cache_dir().join("pixelshift-gtk").join("methods.ini")
+    }
+
+    pub fn load() -> Self {
+        let path = Self::default_path();
+        let key_file = KeyFile::new();
+        let mut records = HashMap::new();
+
+        if key_file.load_from_file(&path, KeyFileFlags::NONE).is_ok() {
+            for group in key_file.groups().iter() {
+                let Some(id) = group.strip_prefix(GROUP_PREFIX) else { continue };
+                let mut capabilities = Capabilities {
+                    name: key_file.string(group, "name").map(|s| s.to_string()).unwrap_or_default(),
+                    probed: key_file.uint64(group, "probed").unwrap_or(0),
+                    ..Default::default()
+                };
+                for (support, key) in capabilities.methods.iter_mut().zip(METHOD_KEYS) {
+                    *support = match key_file.string(group, key) {
+                        Ok(value) if value == "ok" => Support::Works,
+                        Ok(reason) => Support::Fails(reason.to_string()),
+                        Err(_) => Support::Unknown,
+                    };
+                }
+                for (failure, key) in capabilities.runtime.iter_mut().zip(METHOD_KEYS) {
+                    let key = format!("{}{}", RUNTIME_PREFIX, key);
+                    *failure = key_file.string(group, &key).ok().and_then(|value| RuntimeFailure::parse(&value));
+                }
+                records.insert(id.to_string(), capabilities);
+            }
+        }
+
+        Self { path, records }
+    }
+
+    pub fn get(&self, monitor_id: &str) -> Option<&Capabilities> {
+        self.records.get(monitor_id)
+    }
+
+    /// Replace everything known about a monitor, e.g. with a new probe
+    pub fn insert(&mut self, monitor_id: &str, capabilities: Capabilities) {
+        self.records.insert(monitor_id.to_string(), capabilities);
+    }
+
+    /// Note that one method failed while shifting `display`, counting on from
+    /// earlier failures unless they have expired
+    pub fn record_failure(&mut self, display: &DisplayInfo, method_idx: u32, reason: &str) {
+        let now = now();
+        let capabilities = self.records.entry(display.monitor_id()).or_default();
+        capabilities.name = display_name(display);
+        let Some(slot) = capabilities.runtime.get_mut(method_idx as usize) else { return };
+        let count = match slot {
+            Some(failure) if !failure.expired(now) => failure.count + 1,
+            _ => 1,
+        };
+        *slot = Some(RuntimeFailure { count, last: now, reason: reason.to_string() });
+    }
+
+    /// Note that one method worked while shifting `display`, so its earlier
+    /// failures no longer count. True if there were any to forget.
+    pub fn record_success(&mut self, display: &DisplayInfo, method_idx: u32) -> bool {
+        let Some(capabilities) = self.records.get_mut(&display.monitor_id()) else { return false };
+        capabilities.runtime.get_mut(method_idx as usize).and_then(Option::take).is_some()
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let key_file = KeyFile::new();
+        let mut ids: Vec<&String> = self.records.keys().collect();
+        ids.sort();
+        for id in ids {
+            let capabilities = &self.records[id];
+            let group = format!("{}{}", GROUP_PREFIX, id);
+            key_file.set_string(&group, "name", &capabilities.name);
+            key_file.set_uint64(&group, "probed", capabilities.probed);
+            for (support, key) in capabilities.methods.iter().zip(METHOD_KEYS) {
+                match support {
+                    Support::Unknown => {}
+                    Support::Works => key_file.set_string(&group, key, "ok"),
+                    Support::Fails(reason) => key_file.set_string(&group, key, reason),
+                }
+            }
+            for (failure, key) in capabilities.runtime.iter().zip(METHOD_KEYS) {
+                if let Some(failure) = failure {
+                    key_file.set_string(&group, &format!("{}{}", RUNTIME_PREFIX, key), &failure.to_value());
+                }
+            }
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                glib::Error::new(glib::FileError::Failed, &format!("Cannot create {}: {}", dir.display(), e))
+            })?;
+        }
+        key_file.save_to_file(&self.path)
+    }
+}
+
+fn display_name(display: &DisplayInfo) -> String {
+    display.edid.as_ref().map(|e| e.display_name()).unwrap_or_else(|| display.name.clone())
+}
+
+/// Try every method on `display` and put it back as it was. Refuses while a
+/// shift is applied to it, since the probe's reset would undo that.
+pub fn probe(display: &DisplayInfo) -> Result<Capabilities, String> {
+    if dryrun::is_enabled() {
+        return Err("probing needs to change the display, which a dry run does not".to_string());
+    }
+    if state::load().is_some_and(|s| s.display == display.name) {
+        return Err(format!("{} has a shift applied; stop auto-shift or reset it first", display.name));
+    }
+
+    let mut capabilities = Capabilities {
+        name: display_name(display),
+        probed: now(),
+        ..Default::default()
+    };
+    for (method_idx, support) in capabilities.methods.iter_mut().enumerate() {
+        let method_idx = method_idx as u32;
+        *support = match shift(method_idx, display, PROBE_OFFSET, &Quiet) {
+            Ok(()) => Support::Works,
+            Err(e) => Support::Fails(e.to_string()),
+        };
+        reset_display_safe(display, &Quiet);
+    }
+    Ok(capabilities)
+}
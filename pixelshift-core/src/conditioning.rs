@@ -0,0 +1,374 @@
+//! Pixel conditioning: a fullscreen window on one output cycling uniform
+//! colors and a slowly moving gradient, which lets the panel even out wear
+//! while nobody is using it.
+//!
+//! A run lasts the profile's `conditioning-minutes` and shows each step for
+//! `STEP_SECS` before moving to the next. Any key, click, scroll or real
+//! pointer movement ends it early. Runs start from the settings window or
+//! `pixelshift-gtk condition`, after `conditioning-idle` minutes without
+//! input, or daily at the `conditioning-at` times (see `profiles`).
+//! The auto-shift session on the same monitor is paused meanwhile.
+
+use glib::source::SourceId;
+use glib::ControlFlow;
+use gtk4::gdk::RGBA;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::dimmer::gdk_monitor;
+use crate::history::Level;
+use crate::idle;
+use crate::profiles::{Profile, ProfileStore};
+use crate::scheduler::Scheduler;
+use crate::{DisplayInfo, SetTextSafe};
+
+pub const DEFAULT_STEPS: &str = "white; red; green; blue; gradient";
+pub const STEP_SECS: u64 = 30;
+/// Time for the gradient to travel one full period across the screen
+const GRADIENT_PERIOD_SECS: f64 = 60.0;
+/// Input right after the window appears is most likely the click that opened it
+const GRACE_SECS: f64 = 1.0;
+/// Pointer travel that counts as the user being back
+const MOTION_THRESHOLD: f64 = 8.0;
+const CHECK_SECS: u32 = 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Color(RGBA),
+    Gradient,
+}
+
+/// `white; #ff0000; gradient`; empty gives `DEFAULT_STEPS`
+pub fn parse_steps(text: &str) -> Result<Vec<Step>, String> {
+    let text = if text.trim().is_empty() { DEFAULT_STEPS } else { text };
+    text.split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            if s.eq_ignore_ascii_case("gradient") {
+                Ok(Step::Gradient)
+            } else {
+                RGBA::parse(s).map(Step::Color).map_err(|_| format!("unknown color '{}'", s))
+            }
+        })
+        .collect()
+}
+
+/// `03:00; 13:30` as minutes after midnight
+pub fn parse_times(text: &str) -> Result<Vec<u32>, String> {
+    text.split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| {
+            let invalid = || format!("invalid time '{}', expected HH:MM", s);
+            let (h, m) = s.split_once(':').ok_or_else(invalid)?;
+            let (h, m) = (h.parse::<u32>().map_err(|_| invalid())?, m.parse::<u32>().map_err(|_| invalid())?);
+            if h > 23 || m > 59 {
+                return Err(invalid());
+            }
+            Ok(h * 60 + m)
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Completed,
+    Interrupted,
+}
+
+type FinishCallback = Box<dyn FnOnce(Outcome)>;
+
+struct RunInner {
+    window: gtk4::Window,
+    on_finished: RefCell<Option<FinishCallback>>,
+}
+
+impl RunInner {
+    fn finish(&self, outcome: Outcome) {
+        let Some(on_finished) = self.on_finished.borrow_mut().take() else { return };
+        self.window.destroy();
+        on_finished(outcome);
+    }
+}
+
+/// A conditioning window on screen; dropping it does not stop it, `stop` does
+pub struct ConditioningRun {
+    inner: Rc<RunInner>,
+}
+
+impl ConditioningRun {
+    /// Cover `display` until `duration` has passed or the user gets back
+    pub fn start(display: &DisplayInfo, steps: Vec<Step>, duration: Duration, on_finished: impl FnOnce(Outcome) + 'static) -> Result<Self, String> {
+        if steps.is_empty() {
+            return Err("nothing to show".to_string());
+        }
+        let monitor = gdk_monitor(&display.name).ok_or_else(|| format!("GTK does not know output {}", display.name))?;
+
+        let window = gtk4::Window::builder().decorated(false).title("Pixel Conditioning").build();
+        window.set_cursor_from_name(Some("none"));
+        let area = gtk4::DrawingArea::new();
+        window.set_child(Some(&area));
+
+        let started = Instant::now();
+        area.set_draw_func(move |_, cr, width, height| {
+            let elapsed = started.elapsed().as_secs_f64();
+            match &steps[(elapsed as u64 / STEP_SECS) as usize % steps.len()] {
+                Step::Color(color) => cr.set_source_rgb(color.red() as f64, color.green() as f64, color.blue() as f64),
+                Step::Gradient => {
+                    // Reflected white-to-black ramp, two screen widths per period
+                    let phase = (elapsed % GRADIENT_PERIOD_SECS) / GRADIENT_PERIOD_SECS;
+                    let start = phase * 2.0 * width as f64;
+                    let gradient = gtk4::cairo::LinearGradient::new(start, 0.0, start + width as f64, 0.0);
+                    gradient.add_color_stop_rgb(0.0, 1.0, 1.0, 1.0);
+                    gradient.add_color_stop_rgb(1.0, 0.0, 0.0, 0.0);
+                    gradient.set_extend(gtk4::cairo::Extend::Reflect);
+                    let _ = cr.set_source(&gradient);
+                }
+            }
+            cr.rectangle(0.0, 0.0, width as f64, height as f64);
+            let _ = cr.fill();
+        });
+
+        let inner = Rc::new(RunInner { window: window.clone(), on_finished: RefCell::new(Some(Box::new(on_finished))) });
+        let interrupt = {
+            let weak = Rc::downgrade(&inner);
+            move || {
+                if started.elapsed().as_secs_f64() < GRACE_SECS {
+                    return;
+                }
+                if let Some(inner) = weak.upgrade() {
+                    // Don't destroy the window from inside its own event handler
+                    glib::idle_add_local_once(move || inner.finish(Outcome::Interrupted));
+                }
+            }
+        };
+
+        let keys = gtk4::EventControllerKey::new();
+        keys.connect_key_pressed({
+            let interrupt = interrupt.clone();
+            move |_, _, _, _| {
+                interrupt();
+                glib::Propagation::Stop
+            }
+        });
+        window.add_controller(keys);
+
+        let click = gtk4::GestureClick::new();
+        click.set_button(0);
+        click.connect_pressed({
+            let interrupt = interrupt.clone();
+            move |_, _, _, _| interrupt()
+        });
+        window.add_controller(click);
+
+        let scroll = gtk4::EventControllerScroll::new(gtk4::EventControllerScrollFlags::BOTH_AXES);
+        scroll.connect_scroll({
+            let interrupt = interrupt.clone();
+            move |_, _, _| {
+                interrupt();
+                glib::Propagation::Stop
+            }
+        });
+        window.add_controller(scroll);
+
+        // Motion also fires when the window appears under a resting pointer
+        let motion = gtk4::EventControllerMotion::new();
+        let origin: Cell<Option<(f64, f64)>> = Cell::new(None);
+        motion.connect_motion({
+            let interrupt = interrupt.clone();
+            move |_, x, y| match origin.get() {
+                None => origin.set(Some((x, y))),
+                Some((x0, y0)) if (x - x0).hypot(y - y0) > MOTION_THRESHOLD => interrupt(),
+                Some(_) => {}
+            }
+        });
+        window.add_controller(motion);
+
+        window.connect_close_request({
+            let weak = Rc::downgrade(&inner);
+            move |_| {
+                if let Some(inner) = weak.upgrade() {
+                    glib::idle_add_local_once(move || inner.finish(Outcome::Interrupted));
+                }
+                glib::Propagation::Stop
+            }
+        });
+
+        area.add_tick_callback({
+            let weak = Rc::downgrade(&inner);
+            move |area, _| {
+                let Some(inner) = weak.upgrade() else { return ControlFlow::Break };
+                if started.elapsed() >= duration {
+                    glib::idle_add_local_once(move || inner.finish(Outcome::Completed));
+                    return ControlFlow::Break;
+                }
+                area.queue_draw();
+                ControlFlow::Continue
+            }
+        });
+
+        window.fullscreen_on_monitor(&monitor);
+        window.present();
+        Ok(Self { inner })
+    }
+
+    pub fn stop(&self) {
+        self.inner.finish(Outcome::Interrupted);
+    }
+}
+
+struct Inner {
+    scheduler: Scheduler,
+    status: Box<dyn SetTextSafe>,
+    run: RefCell<Option<ConditioningRun>>,
+    /// Monitors conditioned during the current idle period
+    idle_done: RefCell<HashSet<String>>,
+    /// Last `conditioning-at` minute that triggered a run per monitor, as "YYYY-MM-DD HH:MM"
+    last_scheduled: RefCell<HashMap<String, String>>,
+    timer: RefCell<Option<SourceId>>,
+}
+
+/// Starts conditioning runs on request, on idle and on schedule
+#[derive(Clone)]
+pub struct Conditioner {
+    inner: Rc<Inner>,
+}
+
+impl Conditioner {
+    pub fn new(scheduler: &Scheduler, status: impl SetTextSafe + 'static) -> Self {
+        let inner = Rc::new(Inner {
+            scheduler: scheduler.clone(),
+            status: Box::new(status),
+            run: RefCell::new(None),
+            idle_done: RefCell::new(HashSet::new()),
+            last_scheduled: RefCell::new(HashMap::new()),
+            timer: RefCell::new(None),
+        });
+
+        let weak = Rc::downgrade(&inner);
+        let sid = glib::timeout_add_seconds_local(CHECK_SECS, move || match weak.upgrade() {
+            Some(inner) => {
+                Conditioner { inner }.check();
+                ControlFlow::Continue
+            }
+            None => ControlFlow::Break,
+        });
+        *inner.timer.borrow_mut() = Some(sid);
+
+        Self { inner }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.inner.run.borrow().is_some()
+    }
+
+    /// Condition `display` with its profile's settings now
+    pub fn run_now(&self, display: &DisplayInfo) -> Result<(), String> {
+        let profile = ProfileStore::load().get(&display.monitor_id()).cloned().unwrap_or_default();
+        self.start(display, &profile)
+    }
+
+    pub fn stop(&self) {
+        let run = self.inner.run.borrow_mut().take();
+        if let Some(run) = run {
+            run.stop();
+        }
+    }
+
+    fn start(&self, display: &DisplayInfo, profile: &Profile) -> Result<(), String> {
+        if self.is_running() {
+            return Err("conditioning is already running".to_string());
+        }
+        let steps = parse_steps(&profile.conditioning)?;
+        let minutes = profile.conditioning_minutes.clamp(1, 120);
+
+        // Shifting underneath a fullscreen window only wears the RandR state
+        let scheduler = self.inner.scheduler.clone();
+        let paused = scheduler.is_running() && scheduler.session().is_some_and(|s| s.display.is_same_monitor(display));
+        if paused {
+            scheduler.pause();
+        }
+
+        let weak = Rc::downgrade(&self.inner);
+        let name = display.name.clone();
+        let result = ConditioningRun::start(display, steps, Duration::from_secs(minutes as u64 * 60), move |outcome| {
+            if paused {
+                scheduler.resume();
+            }
+            let Some(inner) = weak.upgrade() else { return };
+            inner.run.borrow_mut().take();
+            inner.status.log_safe(Level::Info, &match outcome {
+                Outcome::Completed => format!("✓ Pixel conditioning on {} finished", name),
+                Outcome::Interrupted => format!("Pixel conditioning on {} stopped", name),
+            });
+        });
+
+        match result {
+            Ok(run) => {
+                *self.inner.run.borrow_mut() = Some(run);
+                self.inner.status.log_safe(Level::Info, &format!(
+                    "Pixel conditioning on {} for {} min; any input stops it",
+                    display.name, minutes
+                ));
+                Ok(())
+            }
+            Err(e) => {
+                if paused {
+                    self.inner.scheduler.resume();
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Start idle or scheduled runs that are due
+    fn check(&self) {
+        let profiles = ProfileStore::load();
+        let idle = idle::idle_time();
+        let now = self.inner.scheduler.clock().now();
+        let minute = (now.hour() * 60 + now.minute()) as u32;
+        let stamp = now.format("%F %H:%M").map(|s| s.to_string()).unwrap_or_default();
+
+        for display in self.inner.scheduler.displays() {
+            let id = display.monitor_id();
+            let Some(profile) = profiles.get(&id) else { continue };
+
+            let scheduled = parse_times(&profile.conditioning_at).is_ok_and(|times| times.contains(&minute))
+                && self.inner.last_scheduled.borrow().get(&id) != Some(&stamp);
+
+            let threshold = Duration::from_secs(profile.conditioning_idle as u64 * 60);
+            let idle_due = match idle {
+                Some(idle) if profile.conditioning_idle > 0 && idle >= threshold => !self.inner.idle_done.borrow().contains(&id),
+                _ => {
+                    self.inner.idle_done.borrow_mut().remove(&id);
+                    false
+                }
+            };
+
+            if !(scheduled || idle_due) || self.is_running() {
+                continue;
+            }
+            if scheduled {
+                self.inner.last_scheduled.borrow_mut().insert(id.clone(), stamp.clone());
+            }
+            if idle_due {
+                self.inner.idle_done.borrow_mut().insert(id);
+            }
+            if let Err(e) = self.start(&display, profile) {
+                self.inner.status.set_text_safe(&format!("✗ Pixel conditioning: {}", e));
+            }
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(id) = self.timer.get_mut().take() {
+            id.remove();
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Session-bus control interface, `com.example.PixelShift` at `/com/example/PixelShift`.
+
+use gio::prelude::*;
+use gio::{BusNameOwnerFlags, BusType, DBusConnection, DBusNodeInfo, OwnerId};
+use glib::Variant;
+use std::collections::HashMap;
+
+use crate::profiles::METHOD_KEYS;
+use crate::scheduler::{AutoShiftSession, Scheduler, SchedulerEvent};
+
+pub const BUS_NAME: &str = "com.example.PixelShift";
+pub const OBJECT_PATH: &str = "/com/example/PixelShift";
+pub const INTERFACE: &str = "com.example.PixelShift";
+
+const INTROSPECTION_XML: &str = r#"
+<node>
+  <interface name="com.example.PixelShift">
+    <method name="Start">
+      <arg type="s" name="display" direction="in"/>
+    </method>
+    <method name="Stop"/>
+    <method name="Pause"/>
+    <method name="Resume"/>
+    <method name="ShiftOnce">
+      <arg type="i" name="x" direction="in"/>
+      <arg type="i" name="y" direction="in"/>
+    </method>
+    <method name="Reset"/>
+    <property name="Running" type="b" access="read"/>
+    <property name="Paused" type="b" access="read"/>
+    <property name="CurrentOffset" type="(ii)" access="read"/>
+    <property name="PatternIndex" type="u" access="read"/>
+    <property name="NextShiftTime" type="t" access="read"/>
+    <property name="ActiveDisplays" type="as" access="read"/>
+    <property name="Displays" type="as" access="read"/>
+    <signal name="Shifted">
+      <arg type="s" name="display"/>
+      <arg type="i" name="x"/>
+      <arg type="i" name="y"/>
+    </signal>
+    <signal name="Error">
+      <arg type="s" name="message"/>
+    </signal>
+    <signal name="DisplaysChanged">
+      <arg type="as" name="displays"/>
+    </signal>
+    <signal name="MethodFallback">
+      <arg type="s" name="display"/>
+      <arg type="s" name="from"/>
+      <arg type="s" name="to"/>
+      <arg type="s" name="reason"/>
+    </signal>
+  </interface>
+</node>
+"#;
+
+const PROPERTIES: [&str; 7] = [
+    "Running",
+    "Paused",
+    "CurrentOffset",
+    "PatternIndex",
+    "NextShiftTime",
+    "ActiveDisplays",
+    "Displays",
+];
+
+/// Own the bus name on the session bus and export the scheduler under it
+pub fn own_name(scheduler: &Scheduler) -> OwnerId {
+    let scheduler = scheduler.clone();
+    let lost_scheduler = scheduler.clone();
+    gio::bus_own_name(
+        BusType::Session,
+        BUS_NAME,
+        BusNameOwnerFlags::NONE,
+        move |connection, _| {
+            if let Err(e) = export(&connection, &scheduler) {
+                scheduler.status().set_text_safe(&format!("✗ Could not export D-Bus interface: {}", e));
+            }
+        },
+        |_, _| {},
+        move |_, name| lost_scheduler.status().set_text_safe(&format!("✗ Lost D-Bus name {}", name)),
+    )
+}
+
+/// Register the object and forward scheduler events as signals; usable on
+/// any connection, e.g. a private bus in tests
+pub fn export(connection: &DBusConnection, scheduler: &Scheduler) -> Result<(), glib::Error> {
+    let node = DBusNodeInfo::for_xml(INTROSPECTION_XML)?;
+    let interface = node.lookup_interface(INTERFACE).expect("interface is in the XML");
+
+    let method_scheduler = scheduler.clone();
+    let property_scheduler = scheduler.clone();
+    connection
+        .register_object(OBJECT_PATH, &interface)
+        .method_call(move |_, _, _, _, method, parameters, invocation| {
+            let result = call_method(&method_scheduler, method, &parameters);
+            match result {
+                Ok(()) => invocation.return_value(None),
+                Err((name, message)) => invocation.return_dbus_error(&format!("{}.Error.{}", INTERFACE, name), &message),
+            }
+        })
+        .property(move |_, _, _, _, property| get_property(&property_scheduler, property))
+        .build()?;
+
+    let weak_connection = connection.downgrade();
+    let signal_scheduler = scheduler.clone();
+    scheduler.connect_event(move |event| {
+        let Some(connection) = weak_connection.upgrade() else { return };
+        let emit = |name: &str, args: Variant| {
+            let _ = connection.emit_signal(None, OBJECT_PATH, INTERFACE, name, Some(&args));
+        };
+
+        match event {
+            SchedulerEvent::Shifted => {
+                if let Some(session) = signal_scheduler.session() {
+                    let (x, y) = session.current_offset;
+                    emit("Shifted", (session.display.name, x, y).to_variant());
+                }
+            }
+            SchedulerEvent::Error(message) => emit("Error", (message.as_str(),).to_variant()),
+            SchedulerEvent::DisplaysChanged => emit("DisplaysChanged", (display_names(&signal_scheduler),).to_variant()),
+            SchedulerEvent::MethodFallback { from, to, reason } => {
+                if let Some(session) = signal_scheduler.session() {
+                    let key = |m: &u32| METHOD_KEYS.get(*m as usize).copied().unwrap_or_default();
+                    emit("MethodFallback", (session.display.name, key(from), key(to), reason.as_str()).to_variant());
+                }
+            }
+            SchedulerEvent::StateChanged => {}
+        }
+
+        // Cheap enough to resend everything on every change
+        let changed: HashMap<String, Variant> = PROPERTIES
+            .iter()
+            .map(|p| (p.to_string(), get_property(&signal_scheduler, p)))
+            .collect();
+        let invalidated: Vec<String> = Vec::new();
+        let _ = connection.emit_signal(
+            None,
+            OBJECT_PATH,
+            "org.freedesktop.DBus.Properties",
+            "PropertiesChanged",
+            Some(&(INTERFACE, changed, invalidated).to_variant()),
+        );
+    });
+
+    Ok(())
+}
+
+fn call_method(scheduler: &Scheduler, method: &str, parameters: &Variant) -> Result<(), (&'static str, String)> {
+    match method {
+        "Start" => {
+            if scheduler.session().is_some() {
+                return Err(("AlreadyRunning", "A session is already active".to_string()));
+            }
+            let query = parameters.get::<(String,)>().map(|(d,)| d).unwrap_or_default();
+            let display = if query.is_empty() {
+                scheduler.preferred_display().map_err(|e| ("NoDisplay", e))?
+            } else {
+                scheduler
+                    .displays()
+                    .into_iter()
+                    .find(|d| d.name == query || d.monitor_id() == query)
+                    .ok_or_else(|| ("NoDisplay", format!("No connected display matches '{}'", query)))?
+            };
+            scheduler.start(AutoShiftSession::from_profile(display, scheduler.status()));
+            Ok(())
+        }
+        "Stop" => {
+            scheduler.stop();
+            Ok(())
+        }
+        "Pause" => {
+            if scheduler.pause() {
+                Ok(())
+            } else {
+                Err(("NotRunning", "No running session to pause".to_string()))
+            }
+        }
+        "Resume" => {
+            if scheduler.resume() {
+                Ok(())
+            } else {
+                Err(("NotPaused", "No paused session to resume".to_string()))
+            }
+        }
+        "ShiftOnce" => {
+            let (x, y) = parameters.get::<(i32, i32)>().unwrap_or((0, 0));
+            scheduler.shift_once(x, y).map_err(|e| ("Failed", e))
+        }
+        "Reset" => scheduler.reset_display().map_err(|e| ("Failed", e)),
+        _ => Err(("UnknownMethod", format!("Unknown method {}", method))),
+    }
+}
+
+fn get_property(scheduler: &Scheduler, property: &str) -> Variant {
+    let session = scheduler.session();
+    match property {
+        "Running" => scheduler.is_running().to_variant(),
+        "Paused" => scheduler.is_paused().to_variant(),
+        "CurrentOffset" => session.map(|s| s.current_offset).unwrap_or((0, 0)).to_variant(),
+        "PatternIndex" => (session.map(|s| s.pattern_index()).unwrap_or(0) as u32).to_variant(),
+        "NextShiftTime" => scheduler.next_shift_time().unwrap_or(0).to_variant(),
+        "ActiveDisplays" => session.map(|s| vec![s.display.name]).unwrap_or_default().to_variant(),
+        "Displays" => display_names(scheduler).to_variant(),
+        _ => ().to_variant(),
+    }
+}
+
+fn display_names(scheduler: &Scheduler) -> Vec<String> {
+    scheduler.displays().into_iter().map(|d| d.name).collect()
+}
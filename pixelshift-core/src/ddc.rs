@@ -0,0 +1,328 @@
+//! DDC/CI over i2c-dev: reading and setting VCP features (brightness and
+//! friends) on the monitor itself rather than in the X server.
+//!
+//! The monitor's bus is found by matching EDIDs under `/sys/class/drm`; the
+//! `i2c-dev` module has to be loaded and the user needs access to
+//! `/dev/i2c-*` (usually the `i2c` group). `PIXELSHIFT_DDC_DEVICE` names a
+//! device to use instead; if it is a Unix socket, messages are exchanged over
+//! it as they would be over the bus, so tests can answer from a fake device.
+//! `Emulator` is such a device for the tests: it keeps a table of VCP values
+//! and answers Get/Set VCP like a monitor, either in process or behind a
+//! socket.
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::dryrun;
+use crate::edid;
+use crate::DisplayInfo;
+
+pub const VCP_BRIGHTNESS: u8 = 0x10;
+
+/// 7-bit I²C address of the DDC/CI endpoint
+const DDC_ADDRESS: libc::c_ulong = 0x37;
+/// `I2C_SLAVE` ioctl from linux/i2c-dev.h
+const I2C_SLAVE: libc::c_ulong = 0x0703;
+const HOST_ADDRESS: u8 = 0x51;
+/// Destination address byte that seeds the checksum of requests
+const DISPLAY_ADDRESS: u8 = 0x6E;
+/// Virtual host address that seeds the checksum of replies
+const REPLY_CHECKSUM_SEED: u8 = 0x50;
+const GET_VCP: u8 = 0x01;
+const GET_VCP_REPLY: u8 = 0x02;
+const SET_VCP: u8 = 0x03;
+const GET_VCP_REPLY_LEN: usize = 11;
+/// Minimum wait the DDC/CI spec asks for before reading a reply or sending the next request
+const REPLY_DELAY: Duration = Duration::from_millis(40);
+const SET_DELAY: Duration = Duration::from_millis(50);
+
+/// Anything messages can be written to and read from: the i2c device or a socket
+pub trait Transport: Read + Write {}
+
+impl<T: Read + Write> Transport for T {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcpValue {
+    pub current: u16,
+    pub maximum: u16,
+}
+
+/// Host-to-display message with length byte and checksum
+pub fn request(payload: &[u8]) -> Vec<u8> {
+    let mut message = vec![HOST_ADDRESS, 0x80 | payload.len() as u8];
+    message.extend_from_slice(payload);
+    let checksum = message.iter().fold(DISPLAY_ADDRESS, |acc, b| acc ^ b);
+    message.push(checksum);
+    message
+}
+
+/// Checksum byte a display appends to a reply starting with `bytes`
+pub fn reply_checksum(bytes: &[u8]) -> u8 {
+    bytes.iter().fold(REPLY_CHECKSUM_SEED, |acc, b| acc ^ b)
+}
+
+pub fn parse_vcp_reply(code: u8, reply: &[u8]) -> Result<VcpValue, String> {
+    if reply.len() >= 2 && reply[1] == 0x80 {
+        return Err("the monitor sent an empty reply (busy, or DDC/CI is off in its menu)".to_string());
+    }
+    if reply.len() < GET_VCP_REPLY_LEN {
+        return Err("short DDC/CI reply".to_string());
+    }
+    if reply_checksum(&reply[..GET_VCP_REPLY_LEN - 1]) != reply[GET_VCP_REPLY_LEN - 1] {
+        return Err("DDC/CI reply checksum mismatch".to_string());
+    }
+    if reply[2] != GET_VCP_REPLY || reply[4] != code {
+        return Err(format!("unexpected DDC/CI reply {:02x?}", &reply[..GET_VCP_REPLY_LEN]));
+    }
+    if reply[3] != 0 {
+        return Err(format!("the monitor does not support VCP code 0x{:02x}", code));
+    }
+    Ok(VcpValue {
+        maximum: u16::from_be_bytes([reply[6], reply[7]]),
+        current: u16::from_be_bytes([reply[8], reply[9]]),
+    })
+}
+
+/// A DDC/CI connection to one monitor
+pub struct Ddc {
+    transport: Box<dyn Transport>,
+}
+
+impl Ddc {
+    pub fn new(transport: Box<dyn Transport>) -> Self {
+        Self { transport }
+    }
+
+    /// Open `/dev/i2c-N`, or connect when `path` is a socket
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let is_socket = std::fs::metadata(path).map(|m| m.file_type().is_socket()).unwrap_or(false);
+        if is_socket {
+            let stream = UnixStream::connect(path).map_err(|e| format!("cannot connect to {}: {}", path.display(), e))?;
+            return Ok(Self::new(Box::new(stream)));
+        }
+
+        let device: File = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| format!("cannot open {}: {}", path.display(), e))?;
+        if unsafe { libc::ioctl(device.as_raw_fd(), I2C_SLAVE as _, DDC_ADDRESS) } < 0 {
+            return Err(format!("cannot address the monitor on {}: {}", path.display(), std::io::Error::last_os_error()));
+        }
+        Ok(Self::new(Box::new(device)))
+    }
+
+    /// Connection to the monitor behind `display`
+    pub fn for_display(display: &DisplayInfo) -> Result<Self, String> {
+        Self::open(&device_for(display)?)
+    }
+
+    pub fn get_vcp(&mut self, code: u8) -> Result<VcpValue, String> {
+        self.transport.write_all(&request(&[GET_VCP, code])).map_err(|e| format!("DDC/CI write failed: {}", e))?;
+        std::thread::sleep(REPLY_DELAY);
+        let mut reply = [0u8; GET_VCP_REPLY_LEN];
+        self.transport.read_exact(&mut reply).map_err(|e| format!("DDC/CI read failed: {}", e))?;
+        parse_vcp_reply(code, &reply)
+    }
+
+    pub fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), String> {
+        let [high, low] = value.to_be_bytes();
+        let message = request(&[SET_VCP, code, high, low]);
+        if dryrun::is_enabled() {
+            return dryrun::record(&format!("ddc set-vcp 0x{:02x} {} {:02x?}", code, value, message), None)
+                .map_err(|e| format!("cannot write dry-run log {}: {}", dryrun::log_path().display(), e));
+        }
+        self.transport.write_all(&message).map_err(|e| format!("DDC/CI write failed: {}", e))?;
+        std::thread::sleep(SET_DELAY);
+        Ok(())
+    }
+}
+
+/// `/dev/i2c-N` of the monitor, found by EDID identity
+pub fn device_for(display: &DisplayInfo) -> Result<PathBuf, String> {
+    if let Some(path) = std::env::var_os("PIXELSHIFT_DDC_DEVICE") {
+        return Ok(PathBuf::from(path));
+    }
+
+    let identity = display
+        .edid
+        .as_ref()
+        .map(|e| e.identity())
+        .ok_or_else(|| format!("{} has no EDID to find its DDC bus by", display.name))?;
+    let connectors = std::fs::read_dir("/sys/class/drm").map_err(|e| format!("cannot list DRM connectors: {}", e))?;
+    let mut matching: Vec<PathBuf> = connectors
+        .flatten()
+        .map(|connector| connector.path())
+        .filter(|path| {
+            std::fs::read(path.join("edid"))
+                .ok()
+                .and_then(|bytes| edid::parse_edid(&bytes))
+                .is_some_and(|e| e.identity() == identity)
+        })
+        .collect();
+    // Twins without a serial share an identity; try the one on our connector first
+    matching.sort_by_key(|path| !same_connector(path, &display.name));
+
+    for path in matching {
+        // Newer kernels link the bus as `ddc`; older ones nest an `i2c-N` directory
+        let bus = std::fs::read_link(path.join("ddc"))
+            .ok()
+            .and_then(|link| link.file_name().map(|n| n.to_string_lossy().into_owned()))
+            .or_else(|| {
+                std::fs::read_dir(&path)
+                    .ok()?
+                    .flatten()
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .find(|n| n.starts_with("i2c-"))
+            });
+        if let Some(bus) = bus {
+            return Ok(PathBuf::from("/dev").join(bus));
+        }
+    }
+    Err(format!("no DDC bus found for {}", display.name))
+}
+
+/// `card0-HDMI-A-1` for RandR's `HDMI-1`; drivers that number differently
+/// simply don't match and fall back to EDID order
+fn same_connector(drm_path: &Path, randr_name: &str) -> bool {
+    let Some(name) = drm_path.file_name().map(|n| n.to_string_lossy()) else {
+        return false;
+    };
+    let connector = name.split_once('-').map_or(&*name, |(_, rest)| rest);
+    connector == randr_name || connector.replacen("-A-", "-", 1) == randr_name
+}
+
+/// `0x10` or `16`
+pub fn parse_number(text: &str) -> Option<u16> {
+    let text = text.trim();
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
+
+/// A monitor's DDC/CI endpoint in software
+#[derive(Debug, Clone, Default)]
+pub struct Emulator {
+    features: BTreeMap<u8, VcpValue>,
+    input: Vec<u8>,
+    output: VecDeque<u8>,
+}
+
+impl Emulator {
+    pub fn new(features: impl IntoIterator<Item = (u8, VcpValue)>) -> Self {
+        Self {
+            features: features.into_iter().collect(),
+            ..Self::default()
+        }
+    }
+
+    /// `0x10=80/100; 0xE0=0/1`
+    pub fn parse_features(text: &str) -> Result<Vec<(u8, VcpValue)>, String> {
+        text.split([';', ','])
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(|item| {
+                let invalid = || format!("invalid feature '{}', expected CODE=VALUE/MAX", item);
+                let (code, value) = item.split_once('=').ok_or_else(invalid)?;
+                let (current, maximum) = value.split_once('/').unwrap_or((value, "100"));
+                let code = parse_number(code).filter(|c| *c <= 0xff).ok_or_else(invalid)? as u8;
+                Ok((
+                    code,
+                    VcpValue {
+                        current: parse_number(current).ok_or_else(invalid)?,
+                        maximum: parse_number(maximum).ok_or_else(invalid)?,
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// Reply to one complete host message, if it calls for one
+    pub fn handle(&mut self, message: &[u8]) -> Option<Vec<u8>> {
+        // A real monitor ignores garbage; the host times out
+        let (&checksum, body) = message.split_last()?;
+        if body.len() < 3 || body[0] != HOST_ADDRESS || body.iter().fold(DISPLAY_ADDRESS, |acc, b| acc ^ b) != checksum {
+            return None;
+        }
+
+        match body[2..] {
+            [GET_VCP, code] => {
+                let mut reply = match self.features.get(&code) {
+                    Some(value) => {
+                        let [max_hi, max_lo] = value.maximum.to_be_bytes();
+                        let [cur_hi, cur_lo] = value.current.to_be_bytes();
+                        vec![DISPLAY_ADDRESS, 0x88, GET_VCP_REPLY, 0x00, code, 0x00, max_hi, max_lo, cur_hi, cur_lo]
+                    }
+                    None => vec![DISPLAY_ADDRESS, 0x88, GET_VCP_REPLY, 0x01, code, 0x00, 0, 0, 0, 0],
+                };
+                reply.push(reply_checksum(&reply));
+                Some(reply)
+            }
+            [SET_VCP, code, high, low] => {
+                if let Some(value) = self.features.get_mut(&code) {
+                    value.current = u16::from_be_bytes([high, low]).min(value.maximum);
+                }
+                None
+            }
+            _ => None,
+        }
+    }
+
+    /// Answer connections on a Unix socket at `path` until the process ends
+    pub fn serve(mut self, path: &Path) -> Result<(), String> {
+        let _ = std::fs::remove_file(path);
+        let listener = UnixListener::bind(path).map_err(|e| format!("cannot listen on {}: {}", path.display(), e))?;
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let mut buf = [0u8; 64];
+            while let Ok(n) = stream.read(&mut buf) {
+                if n == 0 {
+                    break;
+                }
+                self.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+                let reply: Vec<u8> = self.output.drain(..).collect();
+                if stream.write_all(&reply).is_err() {
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bytes written are host messages; replies become readable
+impl Write for Emulator {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.input.extend_from_slice(buf);
+        // source, length, payload, checksum
+        while self.input.len() >= 2 && self.input.len() >= (self.input[1] & 0x7f) as usize + 3 {
+            let len = (self.input[1] & 0x7f) as usize + 3;
+            let message: Vec<u8> = self.input.drain(..len).collect();
+            if let Some(reply) = self.handle(&message) {
+                self.output.extend(reply);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Emulator {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = buf.len().min(self.output.len());
+        for (slot, byte) in buf.iter_mut().zip(self.output.drain(..n)) {
+            *slot = byte;
+        }
+        Ok(n)
+    }
+}
@@ -0,0 +1,632 @@
+//! Static-area dimming: translucent black windows over parts of the screen
+//! that never change (taskbars, docks, clocks), which pixel shifting alone
+//! cannot protect.
+//!
+//! The overlays ignore input and follow the session's shift offset. On X11
+//! they are override-redirect windows placed directly in screen coordinates,
+//! which needs a compositing manager for the translucency. On Wayland they are
+//! wlr-layer-shell surfaces via `libgtk4-layer-shell`, loaded at runtime; the
+//! library has to be loaded before the Wayland client library, so start the
+//! app with `LD_PRELOAD=libgtk4-layer-shell.so.0` if placement reports it as
+//! unsupported.
+//!
+//! Which areas are dimmed comes from the session monitor's profile (`dim` and
+//! `dim-level`, see `profiles`). With `auto` the screen is sampled like
+//! the adaptive interval does and cells that stayed unchanged for a minute are
+//! merged into rectangles.
+
+use gio::prelude::*;
+use glib::source::SourceId;
+use glib::ControlFlow;
+use gtk4::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::fmt;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use crate::activity::{self, ActivityTracker};
+use crate::framebuffer;
+use crate::profiles::ProfileStore;
+use crate::scheduler::{ListenerId, Scheduler, SchedulerEvent};
+use crate::{DisplayInfo, SetTextSafe};
+
+const CSS_CLASS: &str = "pixelshift-dim";
+
+/// Rectangle in a monitor's own pixels, before any shift
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Region {
+    /// `x,y,WxH`
+    fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("invalid area '{}', expected x,y,WxH", text);
+        let mut parts = text.split(',').map(str::trim);
+        let (Some(x), Some(y), Some(size), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(invalid());
+        };
+        let (width, height) = size.split_once('x').ok_or_else(invalid)?;
+        let region = Self {
+            x: x.parse().map_err(|_| invalid())?,
+            y: y.parse().map_err(|_| invalid())?,
+            width: width.parse().map_err(|_| invalid())?,
+            height: height.parse().map_err(|_| invalid())?,
+        };
+        if region.width == 0 || region.height == 0 {
+            return Err(format!("area '{}' is empty", text));
+        }
+        Ok(region)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{},{}x{}", self.x, self.y, self.width, self.height)
+    }
+}
+
+/// The profile's `dim` setting
+#[derive(Debug, Clone, PartialEq)]
+pub enum DimAreas {
+    Off,
+    Auto,
+    Fixed(Vec<Region>),
+}
+
+impl DimAreas {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let text = text.trim();
+        if text.is_empty() || text.eq_ignore_ascii_case("off") {
+            return Ok(Self::Off);
+        }
+        if text.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        text.split(';')
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(Region::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::Fixed)
+    }
+}
+
+/// Merge the static cells of a `cols` × `rows` grid into rectangles covering `display`
+pub fn regions_from_cells(cells: &[bool], cols: usize, rows: usize, display: &DisplayInfo) -> Vec<Region> {
+    let cell_x = |c: usize| (c * display.width as usize / cols) as i32;
+    let cell_y = |r: usize| (r * display.height as usize / rows) as i32;
+
+    // Horizontal runs per row, grown downwards while the next row has the same run
+    let mut open: Vec<(usize, usize, usize)> = Vec::new(); // (first col, end col, first row)
+    let mut regions = Vec::new();
+    let close = |(c0, c1, r0): (usize, usize, usize), r1: usize, regions: &mut Vec<Region>| {
+        regions.push(Region {
+            x: cell_x(c0),
+            y: cell_y(r0),
+            width: (cell_x(c1) - cell_x(c0)) as u32,
+            height: (cell_y(r1) - cell_y(r0)) as u32,
+        });
+    };
+
+    for row in 0..=rows {
+        let mut runs = Vec::new();
+        if row < rows {
+            let mut col = 0;
+            while col < cols {
+                if cells.get(row * cols + col).copied().unwrap_or(false) {
+                    let start = col;
+                    while col < cols && cells.get(row * cols + col).copied().unwrap_or(false) {
+                        col += 1;
+                    }
+                    runs.push((start, col));
+                } else {
+                    col += 1;
+                }
+            }
+        }
+
+        let mut next_open = Vec::new();
+        for run in open.drain(..) {
+            if let Some(i) = runs.iter().position(|r| *r == (run.0, run.1)) {
+                runs.remove(i);
+                next_open.push(run);
+            } else {
+                close(run, row, &mut regions);
+            }
+        }
+        next_open.extend(runs.into_iter().map(|(c0, c1)| (c0, c1, row)));
+        open = next_open;
+    }
+    regions
+}
+
+/// Native calls for placing overlay windows, resolved at runtime
+mod native {
+    use glib::translate::ToGlibPtr;
+    use gtk4::prelude::*;
+    use std::ffi::{c_char, c_int, c_long, c_uint, c_ulong, c_void, CStr};
+
+    struct Library(*mut c_void);
+
+    impl Library {
+        fn open(name: &CStr) -> Option<Self> {
+            let handle = unsafe { libc::dlopen(name.as_ptr(), libc::RTLD_NOW | libc::RTLD_GLOBAL) };
+            (!handle.is_null()).then_some(Self(handle))
+        }
+
+        /// Symbols already linked into the process (GTK's own backend helpers)
+        fn process() -> Self {
+            Self(libc::RTLD_DEFAULT)
+        }
+
+        /// # Safety
+        /// `T` must be the function pointer type of the symbol
+        unsafe fn get<T: Copy>(&self, name: &CStr) -> Option<T> {
+            let symbol = libc::dlsym(self.0, name.as_ptr());
+            (!symbol.is_null()).then(|| std::mem::transmute_copy(&symbol))
+        }
+    }
+
+    #[repr(C)]
+    #[derive(Default)]
+    struct XSetWindowAttributes {
+        background_pixmap: c_ulong,
+        background_pixel: c_ulong,
+        border_pixmap: c_ulong,
+        border_pixel: c_ulong,
+        bit_gravity: c_int,
+        win_gravity: c_int,
+        backing_store: c_int,
+        backing_planes: c_ulong,
+        backing_pixel: c_ulong,
+        save_under: c_int,
+        event_mask: c_long,
+        do_not_propagate_mask: c_long,
+        override_redirect: c_int,
+        colormap: c_ulong,
+        cursor: c_ulong,
+    }
+
+    const CW_OVERRIDE_REDIRECT: c_ulong = 1 << 9;
+
+    pub struct X11 {
+        surface_xid: unsafe extern "C" fn(*mut c_void) -> c_ulong,
+        xdisplay: unsafe extern "C" fn(*mut c_void) -> *mut c_void,
+        change_attributes: unsafe extern "C" fn(*mut c_void, c_ulong, c_ulong, *mut XSetWindowAttributes) -> c_int,
+        move_resize: unsafe extern "C" fn(*mut c_void, c_ulong, c_int, c_int, c_uint, c_uint) -> c_int,
+        raise: unsafe extern "C" fn(*mut c_void, c_ulong) -> c_int,
+        flush: unsafe extern "C" fn(*mut c_void) -> c_int,
+    }
+
+    impl X11 {
+        pub fn load() -> Option<Self> {
+            let gdk = Library::process();
+            let xlib = Library::open(c"libX11.so.6")?;
+            unsafe {
+                Some(Self {
+                    surface_xid: gdk.get(c"gdk_x11_surface_get_xid")?,
+                    xdisplay: gdk.get(c"gdk_x11_display_get_xdisplay")?,
+                    change_attributes: xlib.get(c"XChangeWindowAttributes")?,
+                    move_resize: xlib.get(c"XMoveResizeWindow")?,
+                    raise: xlib.get(c"XRaiseWindow")?,
+                    flush: xlib.get(c"XFlush")?,
+                })
+            }
+        }
+
+        fn handles(&self, surface: &gtk4::gdk::Surface) -> (*mut c_void, c_ulong) {
+            let display: *mut gtk4::gdk::ffi::GdkDisplay = surface.display().to_glib_none().0;
+            let surface: *mut gtk4::gdk::ffi::GdkSurface = surface.to_glib_none().0;
+            unsafe { ((self.xdisplay)(display as *mut c_void), (self.surface_xid)(surface as *mut c_void)) }
+        }
+
+        /// Keep the window manager out of it; must happen before the window is mapped
+        pub fn set_override_redirect(&self, surface: &gtk4::gdk::Surface) {
+            let (xdisplay, xid) = self.handles(surface);
+            let mut attributes = XSetWindowAttributes { override_redirect: 1, ..Default::default() };
+            unsafe {
+                (self.change_attributes)(xdisplay, xid, CW_OVERRIDE_REDIRECT, &mut attributes);
+            }
+        }
+
+        pub fn place(&self, surface: &gtk4::gdk::Surface, x: i32, y: i32, width: u32, height: u32) {
+            let (xdisplay, xid) = self.handles(surface);
+            unsafe {
+                (self.move_resize)(xdisplay, xid, x, y, width.max(1), height.max(1));
+                (self.raise)(xdisplay, xid);
+                (self.flush)(xdisplay);
+            }
+        }
+    }
+
+    const LAYER_OVERLAY: c_int = 3;
+    const EDGE_LEFT: c_int = 0;
+    const EDGE_TOP: c_int = 2;
+    const KEYBOARD_NONE: c_int = 0;
+
+    pub struct LayerShell {
+        init_for_window: unsafe extern "C" fn(*mut c_void),
+        set_namespace: unsafe extern "C" fn(*mut c_void, *const c_char),
+        set_layer: unsafe extern "C" fn(*mut c_void, c_int),
+        set_anchor: unsafe extern "C" fn(*mut c_void, c_int, c_int),
+        set_margin: unsafe extern "C" fn(*mut c_void, c_int, c_int),
+        set_exclusive_zone: unsafe extern "C" fn(*mut c_void, c_int),
+        set_keyboard_mode: unsafe extern "C" fn(*mut c_void, c_int),
+        set_monitor: unsafe extern "C" fn(*mut c_void, *mut c_void),
+    }
+
+    impl LayerShell {
+        pub fn load() -> Option<Self> {
+            let lib = Library::open(c"libgtk4-layer-shell.so.0")?;
+            unsafe {
+                let is_supported: unsafe extern "C" fn() -> c_int = lib.get(c"gtk_layer_is_supported")?;
+                if is_supported() == 0 {
+                    return None;
+                }
+                Some(Self {
+                    init_for_window: lib.get(c"gtk_layer_init_for_window")?,
+                    set_namespace: lib.get(c"gtk_layer_set_namespace")?,
+                    set_layer: lib.get(c"gtk_layer_set_layer")?,
+                    set_anchor: lib.get(c"gtk_layer_set_anchor")?,
+                    set_margin: lib.get(c"gtk_layer_set_margin")?,
+                    set_exclusive_zone: lib.get(c"gtk_layer_set_exclusive_zone")?,
+                    set_keyboard_mode: lib.get(c"gtk_layer_set_keyboard_mode")?,
+                    set_monitor: lib.get(c"gtk_layer_set_monitor")?,
+                })
+            }
+        }
+
+        fn ptr(window: &gtk4::Window) -> *mut c_void {
+            let window: *mut gtk4::ffi::GtkWindow = window.to_glib_none().0;
+            window as *mut c_void
+        }
+
+        /// Turn an unrealized window into a top-left anchored overlay surface
+        pub fn init(&self, window: &gtk4::Window, monitor: Option<&gtk4::gdk::Monitor>) {
+            let w = Self::ptr(window);
+            unsafe {
+                (self.init_for_window)(w);
+                (self.set_namespace)(w, c"pixelshift-dim".as_ptr());
+                (self.set_layer)(w, LAYER_OVERLAY);
+                (self.set_anchor)(w, EDGE_LEFT, 1);
+                (self.set_anchor)(w, EDGE_TOP, 1);
+                (self.set_exclusive_zone)(w, -1);
+                (self.set_keyboard_mode)(w, KEYBOARD_NONE);
+                if let Some(monitor) = monitor {
+                    let monitor: *mut gtk4::gdk::ffi::GdkMonitor = monitor.to_glib_none().0;
+                    (self.set_monitor)(w, monitor as *mut c_void);
+                }
+            }
+        }
+
+        /// Position relative to the monitor's top-left corner
+        pub fn place(&self, window: &gtk4::Window, x: i32, y: i32) {
+            let w = Self::ptr(window);
+            unsafe {
+                (self.set_margin)(w, EDGE_LEFT, x);
+                (self.set_margin)(w, EDGE_TOP, y);
+            }
+        }
+    }
+}
+
+enum Placement {
+    X11(native::X11),
+    LayerShell(native::LayerShell),
+}
+
+impl Placement {
+    fn detect() -> Result<Self, String> {
+        let display = gtk4::gdk::Display::default().ok_or_else(|| "no display connection".to_string())?;
+        match display.type_().name() {
+            "GdkX11Display" => native::X11::load().map(Self::X11).ok_or_else(|| "libX11 is not available".to_string()),
+            "GdkWaylandDisplay" => native::LayerShell::load()
+                .map(Self::LayerShell)
+                .ok_or_else(|| "the compositor or libgtk4-layer-shell does not support layer-shell overlays".to_string()),
+            other => Err(format!("overlays are not supported on {}", other)),
+        }
+    }
+}
+
+/// One translucent window over one region
+struct Overlay {
+    window: gtk4::Window,
+    region: Region,
+}
+
+impl Overlay {
+    fn show(placement: &Rc<Placement>, display: &DisplayInfo, region: Region, offset: (i32, i32)) -> Self {
+        let window = gtk4::Window::builder()
+            .decorated(false)
+            .resizable(false)
+            .focusable(false)
+            .can_target(false)
+            .default_width(region.width as i32)
+            .default_height(region.height as i32)
+            .title("Pixel Shifter Dimming")
+            .build();
+        window.add_css_class(CSS_CLASS);
+
+        if let Placement::LayerShell(ref layer) = **placement {
+            layer.init(&window, gdk_monitor(&display.name).as_ref());
+        }
+
+        window.connect_realize({
+            let placement = placement.clone();
+            move |window| {
+                let Some(surface) = window.surface() else { return };
+                // An empty input region passes every click to whatever is below
+                surface.set_input_region(&gtk4::cairo::Region::create());
+                if let Placement::X11(ref x11) = *placement {
+                    x11.set_override_redirect(&surface);
+                }
+            }
+        });
+
+        let overlay = Self { window, region };
+        overlay.window.present();
+        overlay.place(placement, display, offset);
+        overlay
+    }
+
+    fn place(&self, placement: &Placement, display: &DisplayInfo, (dx, dy): (i32, i32)) {
+        let x = self.region.x + dx;
+        let y = self.region.y + dy;
+        match placement {
+            Placement::X11(x11) => {
+                if let Some(surface) = self.window.surface() {
+                    x11.place(&surface, display.x + x, display.y + y, self.region.width, self.region.height);
+                }
+            }
+            Placement::LayerShell(layer) => layer.place(&self.window, x, y),
+        }
+    }
+}
+
+impl Drop for Overlay {
+    fn drop(&mut self) {
+        self.window.destroy();
+    }
+}
+
+/// GDK's monitor for an output, matched by connector name
+pub fn gdk_monitor(connector: &str) -> Option<gtk4::gdk::Monitor> {
+    let monitors = gtk4::gdk::Display::default()?.monitors();
+    (0..monitors.n_items())
+        .filter_map(|i| monitors.item(i).and_downcast::<gtk4::gdk::Monitor>())
+        .find(|m| m.connector().as_deref() == Some(connector))
+}
+
+/// Samples the screen to find static areas for `auto`
+struct Detector {
+    display: DisplayInfo,
+    tracker: ActivityTracker,
+    last_sample: Option<Instant>,
+    regions: Vec<Region>,
+}
+
+struct Inner {
+    scheduler: Scheduler,
+    status: Box<dyn SetTextSafe>,
+    placement: RefCell<Option<Rc<Placement>>>,
+    provider: RefCell<Option<gtk4::CssProvider>>,
+    level: Cell<u32>,
+    /// Display and regions the overlays were built for
+    shown: RefCell<Option<(String, Vec<Region>)>>,
+    overlays: RefCell<Vec<Overlay>>,
+    detector: RefCell<Option<Detector>>,
+    detector_timer: RefCell<Option<SourceId>>,
+    listener: Cell<Option<ListenerId>>,
+    /// Placement error already reported
+    failed: Cell<bool>,
+}
+
+/// Keeps the overlays in line with the scheduler's session and profile
+#[derive(Clone)]
+pub struct DimController {
+    inner: Rc<Inner>,
+}
+
+impl DimController {
+    pub fn new(scheduler: &Scheduler, status: impl SetTextSafe + 'static) -> Self {
+        let inner = Rc::new(Inner {
+            scheduler: scheduler.clone(),
+            status: Box::new(status),
+            placement: RefCell::new(None),
+            provider: RefCell::new(None),
+            level: Cell::new(0),
+            shown: RefCell::new(None),
+            overlays: RefCell::new(Vec::new()),
+            detector: RefCell::new(None),
+            detector_timer: RefCell::new(None),
+            listener: Cell::new(None),
+            failed: Cell::new(false),
+        });
+
+        let weak = Rc::downgrade(&inner);
+        let id = scheduler.connect_event(move |event| {
+            let Some(inner) = weak.upgrade() else { return };
+            if matches!(event, SchedulerEvent::StateChanged | SchedulerEvent::Shifted | SchedulerEvent::DisplaysChanged) {
+                DimController { inner }.refresh();
+            }
+        });
+        inner.listener.set(Some(id));
+
+        let controller = Self { inner };
+        controller.refresh();
+        controller
+    }
+
+    /// Re-read the session monitor's profile and update the overlays
+    pub fn refresh(&self) {
+        let Some(session) = self.inner.scheduler.session() else {
+            self.clear();
+            return;
+        };
+        let profile = ProfileStore::load().get(&session.display.monitor_id()).cloned().unwrap_or_default();
+        let areas = match DimAreas::parse(&profile.dim) {
+            Ok(areas) => areas,
+            Err(e) => {
+                self.inner.status.set_text_safe(&format!("✗ Dimming: {}", e));
+                DimAreas::Off
+            }
+        };
+
+        let regions = match areas {
+            DimAreas::Off => {
+                self.clear();
+                return;
+            }
+            DimAreas::Fixed(regions) => {
+                self.stop_detector();
+                regions
+            }
+            DimAreas::Auto => self.start_detector(&session.display),
+        };
+
+        if let Err(e) = self.show(&session.display, regions, profile.dim_level, session.current_offset) {
+            if !self.inner.failed.replace(true) {
+                self.inner.status.set_text_safe(&format!("✗ Dimming unavailable: {}", e));
+            }
+        }
+    }
+
+    fn show(&self, display: &DisplayInfo, regions: Vec<Region>, level: u32, offset: (i32, i32)) -> Result<(), String> {
+        let placement = self.placement()?;
+        self.set_level(level);
+
+        let wanted = Some((display.name.clone(), regions));
+        if *self.inner.shown.borrow() != wanted {
+            let overlays = match wanted {
+                Some((_, ref regions)) => regions.iter().map(|r| Overlay::show(&placement, display, *r, offset)).collect(),
+                None => Vec::new(),
+            };
+            *self.inner.overlays.borrow_mut() = overlays;
+            *self.inner.shown.borrow_mut() = wanted;
+        } else {
+            for overlay in self.inner.overlays.borrow().iter() {
+                overlay.place(&placement, display, offset);
+            }
+        }
+        Ok(())
+    }
+
+    fn clear(&self) {
+        self.stop_detector();
+        self.inner.overlays.borrow_mut().clear();
+        self.inner.shown.borrow_mut().take();
+    }
+
+    fn placement(&self) -> Result<Rc<Placement>, String> {
+        if let Some(ref placement) = *self.inner.placement.borrow() {
+            return Ok(placement.clone());
+        }
+        let placement = Rc::new(Placement::detect()?);
+        *self.inner.placement.borrow_mut() = Some(placement.clone());
+        Ok(placement)
+    }
+
+    fn set_level(&self, level: u32) {
+        if self.inner.level.replace(level) == level && self.inner.provider.borrow().is_some() {
+            return;
+        }
+        let Some(display) = gtk4::gdk::Display::default() else { return };
+        let mut provider = self.inner.provider.borrow_mut();
+        let provider = provider.get_or_insert_with(|| {
+            let provider = gtk4::CssProvider::new();
+            gtk4::style_context_add_provider_for_display(&display, &provider, gtk4::STYLE_PROVIDER_PRIORITY_APPLICATION);
+            provider
+        });
+        provider.load_from_data(&format!(
+            "window.{} {{ background: rgba(0, 0, 0, {:.2}); box-shadow: none; }}",
+            CSS_CLASS,
+            level.clamp(10, 90) as f64 / 100.0
+        ));
+    }
+
+    /// Regions detected so far; starts sampling on first use
+    fn start_detector(&self, display: &DisplayInfo) -> Vec<Region> {
+        let mut detector = self.inner.detector.borrow_mut();
+        match *detector {
+            Some(ref mut detector) if detector.display.name == display.name => {
+                detector.display = display.clone();
+                return detector.regions.clone();
+            }
+            _ => {}
+        }
+        *detector = Some(Detector { display: display.clone(), tracker: ActivityTracker::default(), last_sample: None, regions: Vec::new() });
+        drop(detector);
+
+        if self.inner.detector_timer.borrow().is_none() {
+            let weak = Rc::downgrade(&self.inner);
+            let sid = glib::timeout_add_local(Duration::from_secs(activity::SAMPLE_SECS), move || match weak.upgrade() {
+                Some(inner) => {
+                    sample(&inner);
+                    ControlFlow::Continue
+                }
+                None => ControlFlow::Break,
+            });
+            *self.inner.detector_timer.borrow_mut() = Some(sid);
+        }
+        Vec::new()
+    }
+
+    fn stop_detector(&self) {
+        self.inner.detector.borrow_mut().take();
+        if let Some(id) = self.inner.detector_timer.borrow_mut().take() {
+            id.remove();
+        }
+    }
+}
+
+fn sample(inner: &Rc<Inner>) {
+    let weak = Rc::downgrade(inner);
+    framebuffer::capture(move |result| {
+        let Some(inner) = weak.upgrade() else { return };
+        let changed = {
+            let mut detector = inner.detector.borrow_mut();
+            let Some(detector) = detector.as_mut() else { return };
+            let frame = match result {
+                Ok(frame) => frame,
+                Err(e) => {
+                    if !inner.failed.replace(true) {
+                        inner.status.set_text_safe(&format!("✗ Static area detection: {}", e));
+                    }
+                    return;
+                }
+            };
+            if detector.last_sample == Some(frame.captured) {
+                return;
+            }
+            let elapsed = detector
+                .last_sample
+                .replace(frame.captured)
+                .map(|t| frame.captured.saturating_duration_since(t).as_secs_f64())
+                .unwrap_or(0.0);
+            let grid = frame.grid(&detector.display, activity::GRID_COLS, activity::GRID_ROWS);
+            detector.tracker.update(grid, elapsed);
+            let regions = regions_from_cells(&detector.tracker.static_cells(), activity::GRID_COLS, activity::GRID_ROWS, &detector.display);
+            let changed = detector.regions != regions;
+            detector.regions = regions;
+            changed
+        };
+        if changed {
+            DimController { inner }.refresh();
+        }
+    });
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        if let Some(id) = self.detector_timer.get_mut().take() {
+            id.remove();
+        }
+        if let Some(id) = self.listener.get() {
+            self.scheduler.disconnect(id);
+        }
+    }
+}
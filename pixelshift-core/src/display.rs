@@ -0,0 +1,249 @@
+//! Connected displays as xrandr reports them, with EDID identity.
+
+use std::collections::HashMap;
+use std::process::Command;
+
+use crate::{edid, profiles, state};
+
+#[derive(Debug, Clone)]
+pub struct DisplayInfo {
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Position of the output within the X screen
+    pub x: i32,
+    pub y: i32,
+    pub refresh_rate: f64,
+    pub is_primary: bool,
+    pub edid: Option<edid::EdidInfo>,
+    /// Another connected output has the same EDID and neither has a serial
+    /// (see `tell_twins_apart`)
+    pub twin: bool,
+}
+
+impl DisplayInfo {
+    /// EDID identity when available, otherwise the connector name. Twins of
+    /// a serial-less model get the connector as well, e.g. `LGD-0617@HDMI-1`,
+    /// or they would share profiles and caches.
+    pub fn monitor_id(&self) -> String {
+        match &self.edid {
+            Some(edid) if self.twin => format!("{}@{}", edid.identity(), self.name),
+            Some(edid) => edid.identity(),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Same physical monitor, compared by EDID since connector names change
+    /// between docks; only serial-less twins are tied to their connector
+    pub fn is_same_monitor(&self, other: &DisplayInfo) -> bool {
+        self.monitor_id() == other.monitor_id()
+    }
+
+    pub fn is_likely_oled(&self) -> bool {
+        self.edid.as_ref().is_some_and(|e| e.is_likely_oled())
+    }
+}
+
+/// Enhanced display detection with better parsing
+pub fn get_connected_displays() -> Vec<DisplayInfo> {
+    let output = match Command::new("xrandr").arg("--query").output() {
+        Ok(o) => String::from_utf8_lossy(&o.stdout).into_owned(),
+        Err(_) => return Vec::new(),
+    };
+    let mut edids = get_display_edids();
+
+    let mut displays = Vec::new();
+    
+    for line in output.lines() {
+        if line.contains(" connected") && !line.contains("disconnected") {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if let Some(name) = parts.first() {
+                let name = name.to_string();
+                let is_primary = line.contains("primary");
+                let (x, y) = parse_output_position(line).unwrap_or((0, 0));
+                
+                // Find current resolution and refresh rate
+                if let Some((width, height, refresh_rate)) = parse_current_mode(&output, &name) {
+                    displays.push(DisplayInfo {
+                        name: name.clone(),
+                        width,
+                        height,
+                        x,
+                        y,
+                        refresh_rate,
+                        is_primary,
+                        edid: edids.remove(&name).and_then(|bytes| edid::parse_edid(&bytes)),
+                        twin: false,
+                    });
+                }
+            }
+        }
+    }
+    tell_twins_apart(&mut displays);
+
+    // Panning and position shifts move the output itself; report where it belongs
+    if let Some(shift) = state::load().filter(|s| s.method != profiles::METHOD_KEYS[0]) {
+        for display in displays.iter_mut().filter(|d| d.name == shift.display) {
+            display.x -= shift.offset.0;
+            display.y -= shift.offset.1;
+        }
+    }
+    
+    displays
+}
+
+/// Mark outputs whose EDID has no serial and matches another connected one,
+/// so only monitors that cannot be told apart otherwise depend on their connector
+pub fn tell_twins_apart(displays: &mut [DisplayInfo]) {
+    let mut seen: HashMap<String, usize> = HashMap::new();
+    for edid in displays.iter().filter_map(|d| d.edid.as_ref()).filter(|e| !e.has_serial()) {
+        *seen.entry(edid.identity()).or_default() += 1;
+    }
+    for display in displays {
+        display.twin = display.edid.as_ref().is_some_and(|e| !e.has_serial() && seen[&e.identity()] > 1);
+    }
+}
+
+/// `+X+Y` from the geometry on an output's "connected" line, e.g. `1920x1080+2560+0`
+pub fn parse_output_position(line: &str) -> Option<(i32, i32)> {
+    line.split_whitespace().find_map(|part| {
+        let (_, position) = part.split_once('+')?;
+        let (x, y) = position.split_once('+')?;
+        Some((x.parse().ok()?, y.parse().ok()?))
+    })
+}
+
+/// Raw EDID blobs per connector, read from `xrandr --props`
+fn get_display_edids() -> HashMap<String, Vec<u8>> {
+    match Command::new("xrandr").args(["--query", "--props"]).output() {
+        Ok(o) => parse_edid_properties(&String::from_utf8_lossy(&o.stdout)),
+        Err(_) => HashMap::new(),
+    }
+}
+
+pub fn parse_edid_properties(xrandr_output: &str) -> HashMap<String, Vec<u8>> {
+    let mut edids = HashMap::new();
+    let mut current_output: Option<String> = None;
+    let mut hex = String::new();
+    let mut in_edid = false;
+
+    for line in xrandr_output.lines() {
+        if !line.starts_with(' ') && !line.starts_with('\t') {
+            // New output (or screen) header
+            current_output = line.split_whitespace().next().map(str::to_string);
+            in_edid = false;
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed == "EDID:" {
+            in_edid = true;
+            hex.clear();
+            continue;
+        }
+
+        if in_edid {
+            if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_hexdigit()) {
+                hex.push_str(trimmed);
+                continue;
+            }
+
+            in_edid = false;
+            if let (Some(name), Some(bytes)) = (&current_output, decode_hex(&hex)) {
+                edids.insert(name.clone(), bytes);
+            }
+        }
+    }
+
+    if in_edid {
+        if let (Some(name), Some(bytes)) = (&current_output, decode_hex(&hex)) {
+            edids.insert(name.clone(), bytes);
+        }
+    }
+
+    edids
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.is_empty() || !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub fn parse_current_mode(xrandr_output: &str, display_name: &str) -> Option<(u32, u32, f64)> {
+    let lines: Vec<&str> = xrandr_output.lines().collect();
+    let mut found_display = false;
+    
+    for line in lines {
+        if line.starts_with(display_name) && line.contains("connected") {
+            found_display = true;
+            
+            // Try to find resolution in the connected line first
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            for part in parts {
+                if part.contains('x') && part.contains('+') {
+                    if let Some((res_part, _)) = part.split_once('+') {
+                        if let Some((w_str, h_str)) = res_part.split_once('x') {
+                            if let (Ok(width), Ok(height)) = (w_str.parse::<u32>(), h_str.parse::<u32>()) {
+                                return Some((width, height, 60.0)); // Default refresh rate
+                            }
+                        }
+                    }
+                }
+            }
+            continue;
+        }
+        
+        if found_display && line.trim().starts_with(char::is_numeric) {
+            // This is a mode line for our display
+            if line.contains('*') && line.contains('+') {
+                // Current active mode
+                let parts: Vec<&str> = line.trim().split_whitespace().collect();
+                if let Some(mode_str) = parts.first() {
+                    if let Some((w_str, h_str)) = mode_str.split_once('x') {
+                        if let (Ok(width), Ok(height)) = (w_str.parse::<u32>(), h_str.parse::<u32>()) {
+                            // Try to extract refresh rate
+                            let refresh_rate = parts.iter()
+                                .find(|p| p.contains('*'))
+                                .and_then(|p| p.trim_end_matches('*').trim_end_matches('+').parse().ok())
+                                .unwrap_or(60.0);
+                            return Some((width, height, refresh_rate));
+                        }
+                    }
+                }
+            }
+        } else if found_display && !line.starts_with(' ') && !line.starts_with('\t') {
+            // We've moved to another display
+            break;
+        }
+    }
+    
+    None
+}
+
+/// First likely OLED panel, otherwise the first display
+pub fn preferred_display_index(displays: &[DisplayInfo]) -> Option<usize> {
+    displays
+        .iter()
+        .position(|d| d.is_likely_oled())
+        .or(if displays.is_empty() { None } else { Some(0) })
+}
+
+
+/// Pick a display by connector name or monitor id, or the preferred one
+pub fn select_display(displays: &[DisplayInfo], query: Option<&str>) -> Result<DisplayInfo, String> {
+    match query {
+        Some(query) => displays
+            .iter()
+            .find(|d| d.name == query || d.monitor_id() == query)
+            .cloned()
+            .ok_or_else(|| format!("no connected display matches '{}'", query)),
+        None => preferred_display_index(displays)
+            .map(|i| displays[i].clone())
+            .ok_or_else(|| "no connected displays found".to_string()),
+    }
+}
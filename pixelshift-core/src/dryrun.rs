@@ -0,0 +1,121 @@
+//! Dry-run mode: record the commands and DDC/CI requests a shift would issue
+//! instead of sending them, so a method, pattern or schedule can be reviewed
+//! (or two runs diffed) before it touches a real panel.
+//!
+//! Turned on by `PIXELSHIFT_DRY_RUN` (any value but `0`; a path also picks the
+//! log file), `--dry-run` on the command line or the switch in the window.
+//! Every record goes to the status area and is appended to
+//! `$XDG_DATA_HOME/pixelshift-gtk/dry-run.log`, one line each:
+//!
+//! ```text
+//! 2026-10-18 09:30:00.125013 xrandr --output HDMI-1 --transform 1,0,0.001042,0,1,0.001852,0,0,1
+//! 2026-10-18 09:30:00.131870 ddc set-vcp 0xe8 1 [51 84 03 e8 00 01 b2]
+//! ```
+
+use std::cell::RefCell;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::os::unix::process::ExitStatusExt;
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Output};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::SetTextSafe;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Pick the mode up from `PIXELSHIFT_DRY_RUN`
+pub fn init_from_env() {
+    if std::env::var("PIXELSHIFT_DRY_RUN").is_ok_and(|v| !v.is_empty() && v != "0") {
+        set_enabled(true);
+    }
+}
+
+pub fn is_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_enabled(enabled: bool) {
+    ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+pub fn log_path() -> PathBuf {
+    match std::env::var("PIXELSHIFT_DRY_RUN") {
+        Ok(path) if path.contains('/') => PathBuf::from(path),
+        _ => glib::user_data_dir().join("pixelshift-gtk").join("dry-run.log"),
+    }
+}
+
+/// Run `program`, or in dry-run mode record it and pretend it succeeded
+pub fn output(program: &str, args: &[&str], status: &dyn SetTextSafe) -> std::io::Result<Output> {
+    if !is_enabled() {
+        return Command::new(program).args(args).output();
+    }
+    let line = std::iter::once(program).chain(args.iter().copied()).map(quote).collect::<Vec<_>>().join(" ");
+    if let Err(e) = record(&line, Some(status)) {
+        status.set_text_safe(&format!("✗ Cannot write dry-run log {}: {}", log_path().display(), e));
+    }
+    Ok(Output {
+        status: ExitStatus::from_raw(0),
+        stdout: Vec::new(),
+        stderr: Vec::new(),
+    })
+}
+
+/// Timestamp `line` and write it to the log and, if given, the status area
+pub fn record(line: &str, status: Option<&dyn SetTextSafe>) -> std::io::Result<()> {
+    let time = glib::DateTime::now_local()
+        .and_then(|now| now.format("%Y-%m-%d %H:%M:%S.%f"))
+        .map(|t| t.to_string())
+        .unwrap_or_default();
+    if let Some(status) = status {
+        status.set_text_safe(&format!("[dry run {}] {}", time, line));
+    }
+
+    let path = log_path();
+    path.parent()
+        .map_or(Ok(()), std::fs::create_dir_all)
+        .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+        .and_then(|mut file| writeln!(file, "{} {}", time, line))
+}
+
+/// Shell-style quoting so logged lines can be pasted into a terminal
+fn quote(arg: &str) -> String {
+    let plain = !arg.is_empty() && arg.chars().all(|c| c.is_ascii_alphanumeric() || "-_.,:+=/@%".contains(c));
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', "'\\''"))
+    }
+}
+
+/// Status messages of one dry-run apply, shown together at the end so the
+/// recorded command is not replaced straight away by the result line
+#[derive(Default)]
+struct Transcript {
+    lines: RefCell<Vec<String>>,
+}
+
+impl SetTextSafe for Transcript {
+    fn set_text_safe(&self, text: &str) {
+        self.lines.borrow_mut().push(text.to_string());
+    }
+
+    fn append_text_safe(&self, text: &str) {
+        self.lines.borrow_mut().push(text.to_string());
+    }
+}
+
+/// Run an apply or reset against `status`, keeping all of its messages in
+/// dry-run mode rather than only the last one
+pub fn transcribed<T>(status: &dyn SetTextSafe, apply: impl FnOnce(&dyn SetTextSafe) -> T) -> T {
+    if !is_enabled() {
+        return apply(status);
+    }
+    let transcript = Transcript::default();
+    let result = apply(&transcript);
+    // "Applying ..." lines repeat what the record shows
+    let lines: Vec<String> = transcript.lines.into_inner().into_iter().filter(|l| !l.starts_with("Applying ")).collect();
+    status.set_text_safe(&lines.join("\n"));
+    result
+}
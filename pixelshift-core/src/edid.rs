@@ -0,0 +1,227 @@
+const EDID_HEADER: [u8; 8] = [0x00, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00];
+const BLOCK_LEN: usize = 128;
+
+const CTA_EXTENSION_TAG: u8 = 0x02;
+const DISPLAYID_EXTENSION_TAG: u8 = 0x70;
+
+/// Panel technology as far as the EDID lets us tell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanelTechnology {
+    Unknown,
+    Crt,
+    Lcd,
+    Plasma,
+    Oled,
+}
+
+/// Decoded subset of an EDID blob
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdidInfo {
+    /// Three-letter PNP vendor id, e.g. "DEL" or "SDC"
+    pub manufacturer: String,
+    pub product_code: u16,
+    pub serial_number: u32,
+    pub serial_string: Option<String>,
+    pub model_name: Option<String>,
+    /// Free-form text descriptor; laptops often put the panel part number here
+    pub panel_text: Option<String>,
+    pub manufacture_year: Option<u16>,
+    pub width_mm: u32,
+    pub height_mm: u32,
+    pub technology: PanelTechnology,
+    /// CTA HDR static metadata desired minimum luminance, in cd/m²
+    pub min_luminance: Option<f64>,
+}
+
+impl EdidInfo {
+    /// Stable key for this monitor that survives moving it between connectors.
+    /// Without a serial it names the model rather than the unit; see
+    /// `DisplayInfo::monitor_id`.
+    pub fn identity(&self) -> String {
+        match (&self.serial_string, self.serial_number) {
+            (Some(serial), _) => format!("{}-{:04X}-{}", self.manufacturer, self.product_code, serial),
+            (None, 0) => format!("{}-{:04X}", self.manufacturer, self.product_code),
+            (None, serial) => format!("{}-{:04X}-{}", self.manufacturer, self.product_code, serial),
+        }
+    }
+
+    /// Whether the EDID tells units of the same model apart
+    pub fn has_serial(&self) -> bool {
+        self.serial_string.is_some() || self.serial_number != 0
+    }
+
+    /// Human readable model, falling back to vendor and product code
+    pub fn display_name(&self) -> String {
+        self.model_name
+            .clone()
+            .unwrap_or_else(|| format!("{} {:04X}", self.manufacturer, self.product_code))
+    }
+
+    /// Why we think this is an OLED panel, if we do
+    pub fn oled_hint(&self) -> Option<&'static str> {
+        if self.technology == PanelTechnology::Oled {
+            return Some("DisplayID reports an organic LED panel");
+        }
+
+        let mentions_oled = |text: &Option<String>| {
+            text.as_deref().is_some_and(|t| t.to_ascii_uppercase().contains("OLED"))
+        };
+        if mentions_oled(&self.model_name) || mentions_oled(&self.panel_text) {
+            return Some("model name mentions OLED");
+        }
+
+        // Samsung Display's laptop OLED panels use ATNA/ATANA part numbers
+        if self.manufacturer == "SDC"
+            && self.panel_text.as_deref().is_some_and(|t| t.starts_with("ATNA") || t.starts_with("ATANA"))
+        {
+            return Some("Samsung Display OLED panel part number");
+        }
+
+        // Self-emissive panels advertise an essentially zero black level
+        if self.min_luminance.is_some_and(|l| l < 0.0005) {
+            return Some("HDR metadata reports a near-zero black level");
+        }
+
+        None
+    }
+
+    pub fn is_likely_oled(&self) -> bool {
+        self.oled_hint().is_some()
+    }
+}
+
+/// Decode the base block plus any CTA-861 and DisplayID extensions. A base
+/// block that fails its checksum was misread (a flaky cable or adapter), so
+/// nothing in it identifies the monitor; extensions that fail are skipped.
+pub fn parse_edid(data: &[u8]) -> Option<EdidInfo> {
+    if data.len() < BLOCK_LEN || data[..8] != EDID_HEADER || !checksum_ok(&data[..BLOCK_LEN]) {
+        return None;
+    }
+
+    let mfg = u16::from_be_bytes([data[8], data[9]]);
+    let manufacturer: String = [(mfg >> 10) & 0x1f, (mfg >> 5) & 0x1f, mfg & 0x1f]
+        .iter()
+        .map(|&c| (b'A' + c as u8).wrapping_sub(1) as char)
+        .collect();
+
+    let product_code = u16::from_le_bytes([data[10], data[11]]);
+    let serial_number = u32::from_le_bytes([data[12], data[13], data[14], data[15]]);
+    let manufacture_year = match data[17] {
+        0 | 0xff => None,
+        y => Some(1990 + y as u16),
+    };
+
+    let mut info = EdidInfo {
+        manufacturer,
+        product_code,
+        serial_number,
+        serial_string: None,
+        model_name: None,
+        panel_text: None,
+        manufacture_year,
+        // Byte 21/22 are in centimetres; prefer the first detailed timing's millimetres
+        width_mm: data[21] as u32 * 10,
+        height_mm: data[22] as u32 * 10,
+        technology: PanelTechnology::Unknown,
+        min_luminance: None,
+    };
+
+    let mut got_dtd_size = false;
+    for descriptor in data[54..126].chunks(18) {
+        if descriptor[0] != 0 || descriptor[1] != 0 {
+            if !got_dtd_size {
+                let w = descriptor[12] as u32 | ((descriptor[14] as u32 & 0xf0) << 4);
+                let h = descriptor[13] as u32 | ((descriptor[14] as u32 & 0x0f) << 8);
+                if w > 0 && h > 0 {
+                    info.width_mm = w;
+                    info.height_mm = h;
+                }
+                got_dtd_size = true;
+            }
+            continue;
+        }
+
+        let text = descriptor_text(&descriptor[5..18]);
+        match descriptor[3] {
+            0xfc => info.model_name = text,
+            0xff => info.serial_string = text,
+            0xfe => info.panel_text = info.panel_text.take().or(text),
+            _ => {}
+        }
+    }
+
+    let extension_count = data[126] as usize;
+    for block in data[BLOCK_LEN..].chunks_exact(BLOCK_LEN).take(extension_count).filter(|b| checksum_ok(b)) {
+        match block[0] {
+            CTA_EXTENSION_TAG => parse_cta_block(block, &mut info),
+            DISPLAYID_EXTENSION_TAG => parse_displayid_block(block, &mut info),
+            _ => {}
+        }
+    }
+
+    Some(info)
+}
+
+/// Every block's bytes add up to zero
+fn checksum_ok(block: &[u8]) -> bool {
+    block.iter().fold(0u8, |sum, b| sum.wrapping_add(*b)) == 0
+}
+
+fn descriptor_text(bytes: &[u8]) -> Option<String> {
+    let end = bytes.iter().position(|&b| b == b'\n').unwrap_or(bytes.len());
+    let text = String::from_utf8_lossy(&bytes[..end]).trim().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn parse_cta_block(block: &[u8], info: &mut EdidInfo) {
+    let dtd_offset = (block[2] as usize).min(BLOCK_LEN - 1);
+    let mut pos = 4;
+
+    while pos < dtd_offset {
+        let tag = block[pos] >> 5;
+        let len = (block[pos] & 0x1f) as usize;
+        let payload = &block[(pos + 1).min(dtd_offset)..(pos + 1 + len).min(dtd_offset)];
+
+        // Extended tag 6: HDR static metadata (EOTF, SM, max, max-FALL, min)
+        if tag == 7 && payload.len() >= 6 && payload[0] == 0x06 {
+            let max_cv = payload[3] as f64;
+            let min_cv = payload[5] as f64;
+            if max_cv > 0.0 {
+                let max_luminance = 50.0 * 2f64.powf(max_cv / 32.0);
+                info.min_luminance = Some(max_luminance * (min_cv / 255.0).powi(2) / 100.0);
+            }
+        }
+
+        pos += 1 + len;
+    }
+}
+
+fn parse_displayid_block(block: &[u8], info: &mut EdidInfo) {
+    // Extension tag, then the DisplayID section header (version, length, type, count)
+    let section_len = block[2] as usize;
+    let end = (5 + section_len).min(BLOCK_LEN - 1);
+    let mut pos = 5;
+
+    while pos + 3 <= end {
+        let tag = block[pos];
+        let len = block[pos + 2] as usize;
+        let payload = &block[(pos + 3).min(end)..(pos + 3 + len).min(end)];
+
+        // Display Device Data block: high nibble of the first byte is the technology
+        if tag == 0x0c && !payload.is_empty() {
+            info.technology = match payload[0] >> 4 {
+                0x0 => PanelTechnology::Crt,
+                0x1 => PanelTechnology::Lcd,
+                0x2 => PanelTechnology::Plasma,
+                0x3 => PanelTechnology::Oled,
+                _ => PanelTechnology::Unknown,
+            };
+        }
+
+        pos += 3 + len;
+    }
+}
@@ -0,0 +1,381 @@
+//! Low-resolution framebuffer sampling.
+//!
+//! On X11 the root window is read straight from the server, one row in every
+//! `SAMPLE_STEP`, which works the same on a real server and on Xvfb. On
+//! Wayland the screenshot portal is used; the compositor may ask for
+//! permission the first time. Setting `PIXELSHIFT_FRAME_SOURCE` to an image
+//! file (any format gdk-pixbuf reads, or an `.xwd` dump) samples that file
+//! instead, re-reading it on every capture so tests can swap in synthetic
+//! frames.
+//!
+//! The adaptive interval, static-area dimming and wear recording all sample
+//! the screen, so `capture` shares one frame between them: a frame younger
+//! than `REUSE_SECS` (`PORTAL_REUSE_SECS` for portal screenshots) is handed
+//! out again, and callers that ask while a capture is running wait for it.
+
+use gio::prelude::*;
+use gio::{BusType, DBusCallFlags, DBusSignalFlags};
+use gdk_pixbuf::Pixbuf;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+use x11rb::connection::Connection;
+use x11rb::protocol::xproto::{ConnectionExt, ImageFormat, ImageOrder};
+
+use crate::DisplayInfo;
+
+/// Screen pixels between samples of a live capture, both ways. A 2560×1440
+/// panel still gets 10×10 samples per cell of a 32×18 grid.
+pub const SAMPLE_STEP: u32 = 8;
+/// Just under the 10 s sampling interval, so samplers on that timer share frames
+const REUSE_SECS: u64 = 9;
+/// Portal screenshots are full-size files written to disk
+const PORTAL_REUSE_SECS: u64 = 30;
+
+/// Luminance of a screen capture, one byte per sample; samples are `step`
+/// screen pixels apart
+pub struct Frame {
+    /// Size of the screen in pixels
+    pub width: u32,
+    pub height: u32,
+    pub step: u32,
+    /// When the screen was read; a frame handed out again keeps its time
+    pub captured: Instant,
+    luma: Vec<u8>,
+}
+
+/// Mean luminance (0.0-1.0) of each cell of a display, row by row
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grid {
+    pub cols: usize,
+    pub rows: usize,
+    pub cells: Vec<f32>,
+}
+
+fn luminance(r: u8, g: u8, b: u8) -> u8 {
+    // Rec. 709 weights
+    (0.2126 * r as f32 + 0.7152 * g as f32 + 0.0722 * b as f32).round() as u8
+}
+
+/// Luminance of one packed TrueColor pixel
+fn packed_luminance(bytes: &[u8], lsb_first: bool, masks: [u32; 3]) -> u8 {
+    let pixel = bytes.iter().enumerate().fold(0u32, |acc, (i, byte)| {
+        let shift = if lsb_first { i } else { bytes.len() - 1 - i };
+        acc | (*byte as u32) << (8 * shift)
+    });
+    // Scale each channel from its mask to 0-255
+    let channel = |mask: u32| -> u8 {
+        if mask == 0 {
+            return 0;
+        }
+        let max = mask >> mask.trailing_zeros();
+        (((pixel & mask) >> mask.trailing_zeros()) * 255 / max) as u8
+    };
+    luminance(channel(masks[0]), channel(masks[1]), channel(masks[2]))
+}
+
+impl Frame {
+    /// Samples across, and down
+    fn samples(&self) -> (u32, u32) {
+        (self.width.div_ceil(self.step), self.height.div_ceil(self.step))
+    }
+
+    pub fn from_pixbuf(pixbuf: &Pixbuf) -> Self {
+        Self::from_pixbuf_every(pixbuf, 1)
+    }
+
+    fn from_pixbuf_every(pixbuf: &Pixbuf, step: u32) -> Self {
+        let width = pixbuf.width().max(0) as u32;
+        let height = pixbuf.height().max(0) as u32;
+        let channels = pixbuf.n_channels() as usize;
+        let stride = pixbuf.rowstride() as usize;
+        let bytes = pixbuf.read_pixel_bytes();
+
+        let mut luma = Vec::with_capacity((width.div_ceil(step) * height.div_ceil(step)) as usize);
+        for y in (0..height as usize).step_by(step as usize) {
+            for x in (0..width as usize).step_by(step as usize) {
+                let i = y * stride + x * channels;
+                luma.push(luminance(bytes[i], bytes[i + 1], bytes[i + 2]));
+            }
+        }
+        Self { width, height, step, captured: Instant::now(), luma }
+    }
+
+    /// Parse an X Window Dump as written by `xwd` (TrueColor, 16/24/32 bpp)
+    pub fn from_xwd(data: &[u8]) -> Result<Self, String> {
+        // The header is 25 big-endian CARD32s regardless of the server's byte order
+        let field = |i: usize| -> Result<u32, String> {
+            data.get(i * 4..i * 4 + 4)
+                .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+                .ok_or_else(|| "truncated XWD header".to_string())
+        };
+        let header_size = field(0)? as usize;
+        if field(1)? != 7 {
+            return Err("not an XWD version 7 file".to_string());
+        }
+        if field(2)? != 2 {
+            return Err("only ZPixmap XWD images are supported".to_string());
+        }
+        let width = field(4)?;
+        let height = field(5)?;
+        let lsb_first = field(7)? == 0;
+        let bits_per_pixel = field(11)?;
+        let bytes_per_line = field(12)? as usize;
+        let masks = [field(14)?, field(15)?, field(16)?];
+        let ncolors = field(19)? as usize;
+
+        let bytes_per_pixel = match bits_per_pixel {
+            16 | 24 | 32 => bits_per_pixel as usize / 8,
+            other => return Err(format!("unsupported XWD depth of {} bits per pixel", other)),
+        };
+        let image = data
+            .get(header_size + ncolors * 12..)
+            .filter(|image| image.len() >= bytes_per_line * height as usize)
+            .ok_or_else(|| "truncated XWD image".to_string())?;
+
+        let mut luma = Vec::with_capacity((width * height) as usize);
+        for y in 0..height as usize {
+            let row = &image[y * bytes_per_line..];
+            for x in 0..width as usize {
+                luma.push(packed_luminance(&row[x * bytes_per_pixel..(x + 1) * bytes_per_pixel], lsb_first, masks));
+            }
+        }
+        Ok(Self { width, height, step: 1, captured: Instant::now(), luma })
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        if path.ends_with(".xwd") {
+            let data = std::fs::read(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+            return Self::from_xwd(&data);
+        }
+        Pixbuf::from_file(path).map(|p| Self::from_pixbuf(&p)).map_err(|e| format!("cannot load {}: {}", path, e))
+    }
+
+    /// Average the part of the frame covered by `display` into `cols` × `rows` cells
+    pub fn grid(&self, display: &DisplayInfo, cols: usize, rows: usize) -> Grid {
+        let mut sums = vec![0u64; cols * rows];
+        let mut counts = vec![0u64; cols * rows];
+
+        let x0 = display.x.max(0) as u32;
+        let y0 = display.y.max(0) as u32;
+        let x1 = (x0 + display.width).min(self.width);
+        let y1 = (y0 + display.height).min(self.height);
+        let (w, h) = (x1.saturating_sub(x0).max(1) as usize, y1.saturating_sub(y0).max(1) as usize);
+        let (across, _) = self.samples();
+
+        // The samples that fall on the display, in screen pixels
+        let first = |start: u32| start.div_ceil(self.step) * self.step;
+        for y in (first(y0)..y1).step_by(self.step as usize) {
+            let row = ((y - y0) as usize * rows / h).min(rows - 1);
+            for x in (first(x0)..x1).step_by(self.step as usize) {
+                let col = ((x - x0) as usize * cols / w).min(cols - 1);
+                sums[row * cols + col] += self.luma[((y / self.step) * across + x / self.step) as usize] as u64;
+                counts[row * cols + col] += 1;
+            }
+        }
+
+        let cells = sums
+            .iter()
+            .zip(&counts)
+            .map(|(sum, count)| if *count == 0 { 0.0 } else { *sum as f32 / *count as f32 / 255.0 })
+            .collect();
+        Grid { cols, rows, cells }
+    }
+}
+
+type Waiter = Box<dyn FnOnce(Result<Rc<Frame>, String>)>;
+
+#[derive(Default)]
+struct Shared {
+    /// The last capture, and how long it may be handed out again
+    last: Option<(Instant, Duration, Result<Rc<Frame>, String>)>,
+    /// Callers waiting for the capture in progress, if there is one
+    waiting: Option<Vec<Waiter>>,
+}
+
+thread_local! {
+    static SHARED: RefCell<Shared> = RefCell::new(Shared::default());
+    static PORTAL_REQUESTS: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Hand a recent capture of the whole screen to `callback`, capturing anew
+/// when there is none. The callback may run later from the main loop
+/// (portal) or right away (X11, file, a reused frame).
+pub fn capture(callback: impl FnOnce(Result<Rc<Frame>, String>) + 'static) {
+    if let Ok(path) = std::env::var("PIXELSHIFT_FRAME_SOURCE") {
+        callback(Frame::load(&path).map(Rc::new));
+        return;
+    }
+
+    let reused = SHARED.with(|shared| {
+        let shared = shared.borrow();
+        shared.last.as_ref().filter(|(at, reuse, _)| at.elapsed() < *reuse).map(|(_, _, result)| result.clone())
+    });
+    if let Some(result) = reused {
+        callback(result);
+        return;
+    }
+
+    let first = SHARED.with(|shared| match &mut shared.borrow_mut().waiting {
+        Some(waiting) => {
+            waiting.push(Box::new(callback));
+            false
+        }
+        waiting => {
+            *waiting = Some(vec![Box::new(callback)]);
+            true
+        }
+    });
+    if !first {
+        return;
+    }
+
+    let finish = |result: Result<Frame, String>, reuse: u64| {
+        let result = result.map(Rc::new);
+        let waiting = SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            shared.last = Some((Instant::now(), Duration::from_secs(reuse), result.clone()));
+            shared.waiting.take().unwrap_or_default()
+        });
+        for callback in waiting {
+            callback(result.clone());
+        }
+    };
+
+    let wayland = std::env::var_os("WAYLAND_DISPLAY").is_some() && std::env::var("XDG_SESSION_TYPE").as_deref() != Ok("x11");
+    if wayland {
+        capture_portal(move |result| finish(result, PORTAL_REUSE_SECS));
+    } else {
+        finish(capture_x11(), REUSE_SECS);
+    }
+}
+
+/// Read every `SAMPLE_STEP`th row of the root window, all requests sent
+/// before the first reply is awaited
+fn capture_x11() -> Result<Frame, String> {
+    let (connection, screen_num) = x11rb::connect(None).map_err(|e| format!("cannot open X display: {}", e))?;
+    let setup = connection.setup();
+    let screen = &setup.roots[screen_num];
+    let (width, height) = (screen.width_in_pixels, screen.height_in_pixels);
+
+    let visual = screen
+        .allowed_depths
+        .iter()
+        .flat_map(|d| &d.visuals)
+        .find(|v| v.visual_id == screen.root_visual)
+        .ok_or("root visual not found")?;
+    let masks = [visual.red_mask, visual.green_mask, visual.blue_mask];
+    let bits_per_pixel = setup
+        .pixmap_formats
+        .iter()
+        .find(|f| f.depth == screen.root_depth)
+        .map(|f| f.bits_per_pixel)
+        .ok_or("no pixmap format for the root depth")?;
+    let bytes_per_pixel = match bits_per_pixel {
+        16 | 24 | 32 => bits_per_pixel as usize / 8,
+        other => return Err(format!("unsupported X depth of {} bits per pixel", other)),
+    };
+    let lsb_first = setup.image_byte_order == ImageOrder::LSB_FIRST;
+
+    let cookies = (0..height)
+        .step_by(SAMPLE_STEP as usize)
+        .map(|y| connection.get_image(ImageFormat::Z_PIXMAP, screen.root, 0, y as i16, width, 1, !0))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("cannot read the screen: {}", e))?;
+
+    let mut luma = Vec::with_capacity(cookies.len() * (width as u32).div_ceil(SAMPLE_STEP) as usize);
+    for cookie in cookies {
+        let row = cookie.reply().map_err(|e| format!("cannot read the screen: {}", e))?.data;
+        for x in (0..width as usize).step_by(SAMPLE_STEP as usize) {
+            let pixel = row.get(x * bytes_per_pixel..(x + 1) * bytes_per_pixel).ok_or("short image row from the X server")?;
+            luma.push(packed_luminance(pixel, lsb_first, masks));
+        }
+    }
+    Ok(Frame { width: width as u32, height: height as u32, step: SAMPLE_STEP, captured: Instant::now(), luma })
+}
+
+fn capture_portal(callback: impl FnOnce(Result<Frame, String>) + 'static) {
+    let connection = match gio::bus_get_sync(BusType::Session, gio::Cancellable::NONE) {
+        Ok(c) => c,
+        Err(e) => return callback(Err(format!("no session bus: {}", e))),
+    };
+
+    // Subscribe to the request's Response before calling, as the portal spec asks
+    let token = PORTAL_REQUESTS.with(|n| {
+        n.set(n.get() + 1);
+        format!("pixelshift{}", n.get())
+    });
+    let sender = connection.unique_name().map(|n| n.trim_start_matches(':').replace('.', "_")).unwrap_or_default();
+    let request_path = format!("/org/freedesktop/portal/desktop/request/{}/{}", sender, token);
+
+    let callback = Rc::new(RefCell::new(Some(callback)));
+    let subscription = Rc::new(RefCell::new(None));
+    let id = connection.signal_subscribe(
+        Some("org.freedesktop.portal.Desktop"),
+        Some("org.freedesktop.portal.Request"),
+        Some("Response"),
+        Some(&request_path),
+        None,
+        DBusSignalFlags::NONE,
+        {
+            let callback = callback.clone();
+            let subscription = subscription.clone();
+            move |connection, _, _, _, _, parameters| {
+                if let Some(id) = subscription.borrow_mut().take() {
+                    connection.signal_unsubscribe(id);
+                }
+                let Some(callback) = callback.borrow_mut().take() else { return };
+
+                let result = match parameters.get::<(u32, HashMap<String, glib::Variant>)>() {
+                    Some((0, results)) => results
+                        .get("uri")
+                        .and_then(|v| v.get::<String>())
+                        .ok_or_else(|| "portal returned no screenshot".to_string())
+                        .and_then(|uri| load_portal_file(&uri)),
+                    Some((1, _)) => Err("screenshot permission was denied".to_string()),
+                    _ => Err("screenshot portal request failed".to_string()),
+                };
+                callback(result);
+            }
+        },
+    );
+    *subscription.borrow_mut() = Some(id);
+
+    let mut options: HashMap<String, glib::Variant> = HashMap::new();
+    options.insert("handle_token".to_string(), token.to_variant());
+    options.insert("interactive".to_string(), false.to_variant());
+    connection.call(
+        Some("org.freedesktop.portal.Desktop"),
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Screenshot",
+        "Screenshot",
+        Some(&("", options).to_variant()),
+        None,
+        DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        {
+            let connection = connection.clone();
+            move |result| {
+                let Err(e) = result else { return };
+                if let Some(id) = subscription.borrow_mut().take() {
+                    connection.signal_unsubscribe(id);
+                }
+                if let Some(callback) = callback.borrow_mut().take() {
+                    callback(Err(format!("screenshot portal unavailable: {}", e)));
+                }
+            }
+        },
+    );
+}
+
+/// Load and delete the file the portal saved
+fn load_portal_file(uri: &str) -> Result<Frame, String> {
+    let path = gio::File::for_uri(uri).path().ok_or_else(|| format!("unsupported screenshot location {}", uri))?;
+    let frame = Pixbuf::from_file(&path)
+        .map(|p| Frame::from_pixbuf_every(&p, SAMPLE_STEP))
+        .map_err(|e| format!("cannot load screenshot: {}", e));
+    let _ = std::fs::remove_file(&path);
+    frame
+}
@@ -0,0 +1,247 @@
+//! What happened recently, for the event log: timestamped entries with a
+//! level, kept in a bounded ring buffer.
+//!
+//! Entries come from two places. `follow` turns the scheduler's structured
+//! events into one line each (started, shifted, fell back, failed), and the
+//! history is itself a `SetTextSafe` sink, so everything the scheduler and the
+//! other controllers narrate lands here too. Messages sent with `log_safe`
+//! keep their level; plain narration is `Debug` unless it reports a failure
+//! (`✗ …`), which keeps the default view to what changed.
+
+use glib::DateTime;
+use std::cell::{Cell, RefCell};
+use std::collections::VecDeque;
+use std::path::Path;
+use std::rc::Rc;
+
+use crate::profiles::method_name;
+use crate::scheduler::{ListenerId, Scheduler, SchedulerEvent};
+use crate::SetTextSafe;
+
+/// Entries kept before the oldest are dropped
+pub const CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Debug,
+    Info,
+    Warning,
+    Error,
+}
+
+impl Level {
+    pub const ALL: [Level; 4] = [Level::Debug, Level::Info, Level::Warning, Level::Error];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Level::Debug => "DEBUG",
+            Level::Info => "INFO",
+            Level::Warning => "WARN",
+            Level::Error => "ERROR",
+        }
+    }
+
+    /// Level of a status message sent without one
+    pub fn of_message(text: &str) -> Self {
+        if text.starts_with('✗') {
+            Level::Error
+        } else {
+            Level::Debug
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub time: DateTime,
+    pub level: Level,
+    pub text: String,
+}
+
+impl Entry {
+    /// `12:00:01 WARN  text`, with the date for exports; further lines of
+    /// `text` are indented under the first
+    pub fn format(&self, with_date: bool) -> String {
+        let pattern = if with_date { "%Y-%m-%d %H:%M:%S" } else { "%H:%M:%S" };
+        let time = self.time.format(pattern).map(|t| t.to_string()).unwrap_or_default();
+        let prefix = format!("{} {:<5} ", time, self.level.label());
+        let indent = " ".repeat(prefix.chars().count());
+        format!("{}{}", prefix, self.text.lines().collect::<Vec<_>>().join(&format!("\n{}", indent)))
+    }
+}
+
+type Listener = Rc<dyn Fn(&Entry)>;
+
+struct Inner {
+    entries: RefCell<VecDeque<Entry>>,
+    capacity: usize,
+    listeners: RefCell<Vec<(ListenerId, Listener)>>,
+    next_listener_id: Cell<ListenerId>,
+}
+
+#[derive(Clone)]
+pub struct History {
+    inner: Rc<Inner>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::new(CAPACITY)
+    }
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            inner: Rc::new(Inner {
+                entries: RefCell::new(VecDeque::with_capacity(capacity)),
+                capacity: capacity.max(1),
+                listeners: RefCell::new(Vec::new()),
+                next_listener_id: Cell::new(1),
+            }),
+        }
+    }
+
+    pub fn log(&self, level: Level, text: &str) {
+        let entry = Entry {
+            time: DateTime::now_local().unwrap_or_else(|_| DateTime::now_utc().expect("system clock")),
+            level,
+            text: text.trim_end().to_string(),
+        };
+        {
+            let mut entries = self.inner.entries.borrow_mut();
+            if entries.len() == self.inner.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry.clone());
+        }
+        // Listeners may log or (dis)connect, so don't hold the borrow
+        let listeners: Vec<_> = self.inner.listeners.borrow().iter().map(|(_, f)| f.clone()).collect();
+        for listener in listeners {
+            listener(&entry);
+        }
+    }
+
+    pub fn info(&self, text: &str) {
+        self.log(Level::Info, text);
+    }
+
+    pub fn warning(&self, text: &str) {
+        self.log(Level::Warning, text);
+    }
+
+    pub fn error(&self, text: &str) {
+        self.log(Level::Error, text);
+    }
+
+    /// Called after each new entry
+    pub fn connect_added<F: Fn(&Entry) + 'static>(&self, f: F) -> ListenerId {
+        let id = self.inner.next_listener_id.get();
+        self.inner.next_listener_id.set(id + 1);
+        self.inner.listeners.borrow_mut().push((id, Rc::new(f)));
+        id
+    }
+
+    pub fn disconnect(&self, id: ListenerId) {
+        self.inner.listeners.borrow_mut().retain(|(i, _)| *i != id);
+    }
+
+    /// Entries at `min_level` or above, oldest first
+    pub fn entries(&self, min_level: Level) -> Vec<Entry> {
+        self.inner.entries.borrow().iter().filter(|e| e.level >= min_level).cloned().collect()
+    }
+
+    /// Newest entry at `min_level` or above
+    pub fn last(&self, min_level: Level) -> Option<Entry> {
+        self.inner.entries.borrow().iter().rev().find(|e| e.level >= min_level).cloned()
+    }
+
+    pub fn clear(&self) {
+        self.inner.entries.borrow_mut().clear();
+    }
+
+    /// One formatted entry per line, as `export` writes them
+    pub fn to_text(&self, min_level: Level) -> String {
+        self.entries(min_level).iter().map(|e| format!("{}\n", e.format(true))).collect()
+    }
+
+    pub fn export(&self, path: &Path, min_level: Level) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text(min_level))
+    }
+
+    /// Log `scheduler`'s events from now on
+    pub fn follow(&self, scheduler: &Scheduler) -> ListenerId {
+        let history = self.clone();
+        let watched = scheduler.clone();
+        // StateChanged also fires when nothing visible changed, e.g. stopping while stopped
+        let last_state = RefCell::new(describe_state(scheduler));
+        scheduler.connect_event(move |event| match event {
+            SchedulerEvent::StateChanged => {
+                let state = describe_state(&watched);
+                if *last_state.borrow() != state {
+                    history.info(&state);
+                    *last_state.borrow_mut() = state;
+                }
+            }
+            SchedulerEvent::Shifted => {
+                if let Some(session) = watched.session() {
+                    let (x, y) = session.current_offset;
+                    history.info(&format!("{} at {:+}{:+} ({})", session.display.name, x, y, method_name(session.method_idx)));
+                }
+            }
+            SchedulerEvent::DisplaysChanged => {
+                let names: Vec<String> = watched.displays().into_iter().map(|d| d.name).collect();
+                history.info(&if names.is_empty() {
+                    "Displays changed: none connected".to_string()
+                } else {
+                    format!("Displays changed: {}", names.join(", "))
+                });
+            }
+            SchedulerEvent::MethodFallback { from, to, reason } => {
+                let display = watched.session().map(|s| s.display.name).unwrap_or_default();
+                history.warning(&format!(
+                    "Switched {} from {} to {}: {}",
+                    display, method_name(*from), method_name(*to), reason
+                ));
+            }
+            SchedulerEvent::Error(message) => history.error(message),
+        })
+    }
+}
+
+fn describe_state(scheduler: &Scheduler) -> String {
+    match scheduler.session() {
+        Some(session) if scheduler.is_running() => format!("Auto-shift running on {} with {}", session.display.name, method_name(session.method_idx)),
+        Some(session) => format!("Auto-shift paused on {}", session.display.name),
+        None => "Auto-shift stopped".to_string(),
+    }
+}
+
+/// Each message becomes its own entry; appending does not grow the last one
+impl SetTextSafe for History {
+    fn set_text_safe(&self, text: &str) {
+        self.log(Level::of_message(text), text);
+    }
+
+    fn append_text_safe(&self, text: &str) {
+        self.log(Level::of_message(text), text);
+    }
+
+    fn log_safe(&self, level: Level, text: &str) {
+        self.log(level, text);
+    }
+}
+
+/// Passes messages on at `Debug`, whatever they say: for the steps of an
+/// operation whose outcome is reported on its own
+pub struct Detail<'a>(pub &'a dyn SetTextSafe);
+
+impl SetTextSafe for Detail<'_> {
+    fn set_text_safe(&self, text: &str) {
+        self.0.log_safe(Level::Debug, text);
+    }
+
+    fn append_text_safe(&self, text: &str) {
+        self.0.log_safe(Level::Debug, text);
+    }
+}
@@ -0,0 +1,43 @@
+//! How long the user has been away from keyboard and mouse.
+//!
+//! Asks GNOME's idle monitor, then the freedesktop screensaver interface
+//! (KDE and others), then `xprintidle` on X11. `PIXELSHIFT_FAKE_IDLE` set to a
+//! number of seconds, or to a file holding one, overrides all of them for
+//! testing; the file is read on every call, so a test can come back to the
+//! keyboard by rewriting it.
+
+use gio::{BusType, DBusCallFlags};
+use std::process::Command;
+use std::time::Duration;
+
+/// Time since the last input, if any source can tell
+pub fn idle_time() -> Option<Duration> {
+    if let Ok(fake) = std::env::var("PIXELSHIFT_FAKE_IDLE") {
+        let fake = std::fs::read_to_string(&fake).unwrap_or(fake);
+        return fake.trim().parse::<f64>().ok().map(|secs| Duration::from_secs_f64(secs.max(0.0)));
+    }
+    dbus_idle_time().or_else(xprintidle)
+}
+
+fn dbus_idle_time() -> Option<Duration> {
+    let connection = gio::bus_get_sync(BusType::Session, gio::Cancellable::NONE).ok()?;
+    let call = |name: &str, path: &str, interface: &str, method: &str| {
+        connection
+            .call_sync(Some(name), path, interface, method, None, None, DBusCallFlags::NO_AUTO_START, 500, gio::Cancellable::NONE)
+            .ok()
+    };
+
+    if let Some((ms,)) = call("org.gnome.Mutter.IdleMonitor", "/org/gnome/Mutter/IdleMonitor/Core", "org.gnome.Mutter.IdleMonitor", "GetIdletime")
+        .and_then(|r| r.get::<(u64,)>())
+    {
+        return Some(Duration::from_millis(ms));
+    }
+    call("org.freedesktop.ScreenSaver", "/org/freedesktop/ScreenSaver", "org.freedesktop.ScreenSaver", "GetSessionIdleTime")
+        .and_then(|r| r.get::<(u32,)>())
+        .map(|(ms,)| Duration::from_millis(ms as u64))
+}
+
+fn xprintidle() -> Option<Duration> {
+    let output = Command::new("xprintidle").output().ok().filter(|o| o.status.success())?;
+    String::from_utf8_lossy(&output.stdout).trim().parse::<u64>().ok().map(Duration::from_millis)
+}
@@ -0,0 +1,114 @@
+//! The pixel shifting engine behind pixelshift-gtk: display detection and
+//! EDID identity, the RandR shift methods, their reset and which of them a
+//! driver honours, DDC/CI, shift patterns and the auto-shift scheduler, and
+//! per-monitor profiles. The GTK window and the headless `pixelshift`
+//! command are thin front ends over this crate.
+//!
+//! Everything runs on a GLib main context: the scheduler and the other
+//! controllers arm GLib timers, so embedders need a running main loop (or to
+//! iterate the default context) for them to fire. Progress and errors are
+//! reported as text through a `SetTextSafe` status sink; `history` keeps a
+//! levelled log of them and of the scheduler's events.
+//!
+//! The `gtk` feature adds what needs GTK to draw: dimming overlays
+//! (`dimmer`), fullscreen pixel conditioning (`conditioning`) and a status
+//! sink for `gtk4::Label`.
+//!
+//! ```no_run
+//! use pixelshift_core::scheduler::{AutoShiftSession, Scheduler};
+//! use pixelshift_core::{get_connected_displays, preferred_display_index, SetTextSafe};
+//!
+//! struct Print;
+//!
+//! impl SetTextSafe for Print {
+//!     fn set_text_safe(&self, text: &str) {
+//!         println!("{}", text);
+//!     }
+//!
+//!     fn append_text_safe(&self, text: &str) {
+//!         println!("{}", text);
+//!     }
+//! }
+//!
+//! let displays = get_connected_displays();
+//! let display = displays[preferred_display_index(&displays).unwrap()].clone();
+//! let scheduler = Scheduler::new(Print);
+//! scheduler.start(AutoShiftSession::from_profile(display, &Print));
+//! glib::MainLoop::new(None, false).run();
+//! ```
+
+pub mod activity;
+pub mod autostart;
+pub mod backend;
+pub mod brightness;
+pub mod capabilities;
+#[cfg(feature = "gtk")]
+pub mod conditioning;
+pub mod dbus;
+pub mod ddc;
+#[cfg(feature = "gtk")]
+pub mod dimmer;
+pub mod display;
+pub mod dryrun;
+pub mod edid;
+pub mod framebuffer;
+pub mod history;
+pub mod idle;
+pub mod lock;
+pub mod logind;
+pub mod profiles;
+pub mod quirks;
+pub mod readback;
+pub mod schedule;
+pub mod scheduler;
+pub mod state;
+pub mod usage;
+pub mod wear;
+
+pub use backend::{apply_pixel_shift, reset_display_safe};
+pub use display::{get_connected_displays, preferred_display_index, select_display, DisplayInfo};
+
+/// Where status messages go: a label, the terminal, a log
+pub trait SetTextSafe {
+    fn set_text_safe(&self, text: &str);
+    fn append_text_safe(&self, text: &str);
+
+    /// A message that says how much it matters, for sinks that filter on
+    /// that; the others just show it
+    fn log_safe(&self, level: history::Level, text: &str) {
+        let _ = level;
+        self.set_text_safe(text);
+    }
+}
+
+/// Status sink for steps nobody needs to see
+pub(crate) struct Quiet;
+
+impl SetTextSafe for Quiet {
+    fn set_text_safe(&self, _text: &str) {}
+
+    fn append_text_safe(&self, _text: &str) {}
+}
+
+/// Updates from an idle callback, so it is safe to call while GTK is busy
+#[cfg(feature = "gtk")]
+impl SetTextSafe for gtk4::Label {
+    fn set_text_safe(&self, text: &str) {
+        let label = self.clone();
+        let message = text.to_string();
+        glib::idle_add_local(move || {
+            label.set_text(&message);
+            glib::ControlFlow::Break
+        });
+    }
+
+    fn append_text_safe(&self, text: &str) {
+        let label = self.clone();
+        let message = text.to_string();
+        glib::idle_add_local(move || {
+            let current = label.text();
+            label.set_text(&format!("{}\n{}", current, message));
+            glib::ControlFlow::Break
+        });
+    }
+}
@@ -0,0 +1,46 @@
+//! One writer per statistics file.
+//!
+//! The GUI and `pixelshift run` can be up at the same time, and both would
+//! add the same minutes to `usage.ini` and the wear maps. Whichever takes
+//! `$XDG_RUNTIME_DIR/pixelshift-gtk/NAME.lock` first does the recording. The
+//! kernel drops the lock with its process, so the other one takes over on its
+//! next sample, even after a crash.
+
+use std::cell::RefCell;
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::path::PathBuf;
+
+pub struct RecorderLock {
+    name: &'static str,
+    file: RefCell<Option<File>>,
+}
+
+impl RecorderLock {
+    pub fn new(name: &'static str) -> Self {
+        Self { name, file: RefCell::new(None) }
+    }
+
+    pub fn path(&self) -> PathBuf {
+        glib::user_runtime_dir().join("pixelshift-gtk").join(format!("{}.lock", self.name))
+    }
+
+    /// Whether this process records, taking the lock when it is free
+    pub fn acquire(&self) -> bool {
+        if self.file.borrow().is_some() {
+            return true;
+        }
+
+        let path = self.path();
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        // Without a runtime directory there is nothing to coordinate through
+        let Ok(file) = OpenOptions::new().create(true).truncate(false).write(true).open(&path) else { return true };
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } != 0 {
+            return false;
+        }
+        *self.file.borrow_mut() = Some(file);
+        true
+    }
+}
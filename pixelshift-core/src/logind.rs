@@ -0,0 +1,96 @@
+use gio::prelude::*;
+use gio::{BusType, DBusCallFlags, DBusConnection, DBusSignalFlags, SignalSubscriptionId, UnixFDList};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+const LOGIND_BUS_NAME: &str = "org.freedesktop.login1";
+const LOGIND_OBJECT_PATH: &str = "/org/freedesktop/login1";
+const LOGIND_MANAGER_INTERFACE: &str = "org.freedesktop.login1.Manager";
+
+/// Watches logind's PrepareForSleep signal and holds a delay inhibitor so
+/// displays can be restored before the machine actually suspends.
+pub struct SleepWatcher {
+    connection: DBusConnection,
+    subscription: Option<SignalSubscriptionId>,
+}
+
+impl SleepWatcher {
+    /// Connect to logind on the system bus. Setting `PIXELSHIFT_LOGIND_BUS=session`
+    /// talks to a mock logind on the session bus instead.
+    pub fn connect<F: Fn(bool) + 'static>(on_prepare_for_sleep: F) -> Result<Self, glib::Error> {
+        let bus_type = match std::env::var("PIXELSHIFT_LOGIND_BUS").as_deref() {
+            Ok("session") => BusType::Session,
+            _ => BusType::System,
+        };
+        let connection = gio::bus_get_sync(bus_type, gio::Cancellable::NONE)?;
+        Ok(Self::with_connection(connection, on_prepare_for_sleep))
+    }
+
+    /// The callback receives `true` right before suspend and `false` after wake.
+    pub fn with_connection<F: Fn(bool) + 'static>(connection: DBusConnection, on_prepare_for_sleep: F) -> Self {
+        // Dropping the fd list closes the inhibitor fd, which lets logind proceed
+        let inhibitor: Rc<RefCell<Option<UnixFDList>>> = Rc::new(RefCell::new(take_delay_inhibitor(&connection)));
+
+        let subscription = connection.signal_subscribe(
+            Some(LOGIND_BUS_NAME),
+            Some(LOGIND_MANAGER_INTERFACE),
+            Some("PrepareForSleep"),
+            Some(LOGIND_OBJECT_PATH),
+            None,
+            DBusSignalFlags::NONE,
+            move |connection, _sender, _path, _interface, _signal, parameters| {
+                let Some((going_to_sleep,)) = parameters.get::<(bool,)>() else {
+                    return;
+                };
+
+                on_prepare_for_sleep(going_to_sleep);
+
+                if going_to_sleep {
+                    inhibitor.borrow_mut().take();
+                } else {
+                    *inhibitor.borrow_mut() = take_delay_inhibitor(connection);
+                }
+            },
+        );
+
+        Self {
+            connection,
+            subscription: Some(subscription),
+        }
+    }
+}
+
+impl Drop for SleepWatcher {
+    fn drop(&mut self) {
+        if let Some(subscription) = self.subscription.take() {
+            self.connection.signal_unsubscribe(subscription);
+        }
+    }
+}
+
+/// Ask logind to delay suspend until we release the returned fd
+fn take_delay_inhibitor(connection: &DBusConnection) -> Option<UnixFDList> {
+    let parameters = (
+        "sleep",
+        "OLED Pixel Shifter",
+        "Restoring display offsets before suspend",
+        "delay",
+    )
+        .to_variant();
+
+    connection
+        .call_with_unix_fd_list_sync(
+            Some(LOGIND_BUS_NAME),
+            LOGIND_OBJECT_PATH,
+            LOGIND_MANAGER_INTERFACE,
+            "Inhibit",
+            Some(&parameters),
+            Some(glib::VariantTy::new("(h)").unwrap()),
+            DBusCallFlags::NONE,
+            -1,
+            UnixFDList::NONE,
+            gio::Cancellable::NONE,
+        )
+        .ok()
+        .and_then(|(_, fd_list)| fd_list)
+}
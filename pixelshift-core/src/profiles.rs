@@ -0,0 +1,292 @@
+//! Per-monitor settings profiles.
+//!
+//! Profiles live in `$XDG_CONFIG_HOME/pixelshift-gtk/profiles.ini` (usually
+//! `~/.config/pixelshift-gtk/profiles.ini`), a GLib key file with one group
+//! per monitor, keyed by EDID identity or connector name:
+//!
+//! ```ini
+//! [meta]
+//! version=1
+//!
+//! [profile DEL-A0B1-12345]
+//! name=DELL AW3423DW
+//! method=transform
+//! amount=2
+//! pattern=circular
+//! interval=30
+//! adaptive=false
+//! wear=false
+//! schedule=
+//! dim=
+//! dim-level=40
+//! conditioning=
+//! conditioning-minutes=10
+//! conditioning-idle=0
+//! conditioning-at=
+//! idle-dim=0
+//! idle-dim-level=30
+//! idle-dim-method=randr
+//! hardware-orbit=false
+//! ```
+//!
+//! - `method`: `transform`, `panning-smooth`, `position` or `panning`
+//! - `amount`: shift in pixels, 1-10
+//! - `pattern`: `circular` (9-point orbit) or `alternate` (toggle on/off)
+//! - `interval`: seconds between shifts, 5-300
+//! - `adaptive`: stretch or shorten `interval` by how static the screen is
+//! - `wear`: record a wear heatmap for this monitor (see the `wear` module)
+//! - `schedule`: optional time-of-day rules overriding `interval`, e.g.
+//!   `sat-sun off; mon-fri 09:00-18:00 60s; * 10m` (see the `schedule` module);
+//!   empty means `interval` all the time
+//! - `dim`: screen areas to darken with a click-through overlay: `auto` for
+//!   areas detected as static, or `x,y,WxH` rectangles in the monitor's
+//!   pixels separated by `;`, e.g. `0,1040,1920x40`; empty means off
+//! - `dim-level`: how much the overlay darkens, in percent (10-90)
+//! - `conditioning`: colors (`#rrggbb` or names) and `gradient` steps the
+//!   pixel conditioning routine cycles through, separated by `;`; empty means
+//!   `white; red; green; blue; gradient` (see the `conditioning` module)
+//! - `conditioning-minutes`: how long one conditioning run lasts (1-120)
+//! - `conditioning-idle`: run it after this many idle minutes, 0 for never
+//! - `conditioning-at`: also run it daily at these `HH:MM` times, separated by `;`
+//! - `idle-dim`: lower the brightness after this many idle minutes, 0 for never
+//! - `idle-dim-level`: brightness while idle, in percent of the normal level (10-90)
+//! - `idle-dim-method`: `randr` (X server gamma) or `ddc` (the monitor's own
+//!   brightness over DDC/CI, see `brightness`)
+//! - `hardware-orbit`: switch on the monitor's own pixel orbit over DDC/CI
+//!   while a session runs, instead of shifting in software (see `quirks`)
+//!
+//! `[meta] version` is the format version. Older files are migrated on load
+//! and written back in the current format on the next save; a file without
+//! one was written by hand and read as version 1. A file from a newer version
+//! is read as far as this one understands it but never overwritten, so the
+//! fields it does not know about are not lost.
+
+use glib::{KeyFile, KeyFileFlags};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::brightness::BrightnessMethod;
+
+pub const SCHEMA_VERSION: i32 = 1;
+
+const META_GROUP: &str = "meta";
+const PROFILE_GROUP_PREFIX: &str = "profile ";
+
+/// Upgrades from each format to the next, starting at version 1; one is
+/// added with every format change
+const MIGRATIONS: [fn(&KeyFile); SCHEMA_VERSION as usize - 1] = [];
+
+/// Method names in the same order as the method combo
+pub const METHOD_KEYS: [&str; 4] = ["transform", "panning-smooth", "position", "panning"];
+
+/// What the method combo calls them, for messages
+pub const METHOD_NAMES: [&str; 4] = ["Transform Matrix", "Smooth Panning", "Position Offset", "Basic Panning"];
+
+/// Name of `method_idx` for messages; indices come from files and D-Bus, so
+/// an unknown one gets a placeholder rather than a panic
+pub fn method_name(method_idx: u32) -> &'static str {
+    METHOD_NAMES.get(method_idx as usize).copied().unwrap_or("unknown method")
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Profile {
+    pub name: String,
+    pub method_idx: u32,
+    pub shift_amount: i32,
+    pub use_pattern: bool,
+    pub interval_secs: u32,
+    pub adaptive: bool,
+    pub wear: bool,
+    pub schedule: String,
+    pub dim: String,
+    pub dim_level: u32,
+    pub conditioning: String,
+    pub conditioning_minutes: u32,
+    pub conditioning_idle: u32,
+    pub conditioning_at: String,
+    pub idle_dim: u32,
+    pub idle_dim_level: u32,
+    pub idle_dim_method: BrightnessMethod,
+    pub hardware_orbit: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            method_idx: 0,
+            shift_amount: 2,
+            use_pattern: true,
+            interval_secs: 30,
+            adaptive: false,
+            wear: false,
+            schedule: String::new(),
+            dim: String::new(),
+            dim_level: 40,
+            conditioning: String::new(),
+            conditioning_minutes: 10,
+            conditioning_idle: 0,
+            conditioning_at: String::new(),
+            idle_dim: 0,
+            idle_dim_level: 30,
+            idle_dim_method: BrightnessMethod::Randr,
+            hardware_orbit: false,
+        }
+    }
+}
+
+pub struct ProfileStore {
+    path: PathBuf,
+    profiles: HashMap<String, Profile>,
+    /// Format of a file written by a newer version, which `save` leaves alone
+    newer_version: Option<i32>,
+}
+
+impl ProfileStore {
+    pub fn default_path() -> PathBuf {
+        glib::user_config_dir().join("pixelshift-gtk").join("profiles.ini")
+    }
+
+    /// Load the default profile file; a missing or unreadable file gives an empty store
+    pub fn load() -> Self {
+        Self::load_from(Self::default_path())
+    }
+
+    pub fn load_from(path: PathBuf) -> Self {
+        let key_file = KeyFile::new();
+        let mut newer_version = None;
+        let profiles = match key_file.load_from_file(&path, KeyFileFlags::NONE) {
+            Ok(()) => {
+                let version = key_file.integer(META_GROUP, "version").unwrap_or(1);
+                if version > SCHEMA_VERSION {
+                    newer_version = Some(version);
+                } else {
+                    migrate(&key_file, version);
+                }
+                read_profiles(&key_file)
+            }
+            Err(_) => HashMap::new(),
+        };
+
+        Self { path, profiles, newer_version }
+    }
+
+    pub fn get(&self, monitor_id: &str) -> Option<&Profile> {
+        self.profiles.get(monitor_id)
+    }
+
+    pub fn insert(&mut self, monitor_id: &str, profile: Profile) {
+        self.profiles.insert(monitor_id.to_string(), profile);
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        if let Some(version) = self.newer_version {
+            return Err(glib::Error::new(
+                glib::FileError::Failed,
+                &format!(
+                    "{} was written by a newer version (format {}, this one knows up to {}); not overwriting it",
+                    self.path.display(),
+                    version,
+                    SCHEMA_VERSION
+                ),
+            ));
+        }
+
+        let key_file = KeyFile::new();
+        key_file.set_integer(META_GROUP, "version", SCHEMA_VERSION);
+        key_file.set_comment(
+            None,
+            None,
+            " OLED Pixel Shifter per-monitor profiles. See pixelshift-core/src/profiles.rs for the format.",
+        )?;
+
+        let mut ids: Vec<&String> = self.profiles.keys().collect();
+        ids.sort();
+        for id in ids {
+            let profile = &self.profiles[id];
+            let group = format!("{}{}", PROFILE_GROUP_PREFIX, id);
+            key_file.set_string(&group, "name", &profile.name);
+            key_file.set_string(&group, "method", METHOD_KEYS.get(profile.method_idx as usize).unwrap_or(&METHOD_KEYS[0]));
+            key_file.set_integer(&group, "amount", profile.shift_amount);
+            key_file.set_string(&group, "pattern", if profile.use_pattern { "circular" } else { "alternate" });
+            key_file.set_integer(&group, "interval", profile.interval_secs as i32);
+            key_file.set_boolean(&group, "adaptive", profile.adaptive);
+            key_file.set_boolean(&group, "wear", profile.wear);
+            key_file.set_string(&group, "schedule", &profile.schedule);
+            key_file.set_string(&group, "dim", &profile.dim);
+            key_file.set_integer(&group, "dim-level", profile.dim_level as i32);
+            key_file.set_string(&group, "conditioning", &profile.conditioning);
+            key_file.set_integer(&group, "conditioning-minutes", profile.conditioning_minutes as i32);
+            key_file.set_integer(&group, "conditioning-idle", profile.conditioning_idle as i32);
+            key_file.set_string(&group, "conditioning-at", &profile.conditioning_at);
+            key_file.set_integer(&group, "idle-dim", profile.idle_dim as i32);
+            key_file.set_integer(&group, "idle-dim-level", profile.idle_dim_level as i32);
+            key_file.set_string(&group, "idle-dim-method", profile.idle_dim_method.key());
+            key_file.set_boolean(&group, "hardware-orbit", profile.hardware_orbit);
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                glib::Error::new(glib::FileError::Failed, &format!("Cannot create {}: {}", dir.display(), e))
+            })?;
+        }
+        key_file.save_to_file(&self.path)
+    }
+}
+
+/// Bring a key file in format `from` up to `SCHEMA_VERSION`, one step at a time
+fn migrate(key_file: &KeyFile, from: i32) {
+    for step in MIGRATIONS.iter().skip((from - 1).max(0) as usize) {
+        step(key_file);
+    }
+    key_file.set_integer(META_GROUP, "version", SCHEMA_VERSION);
+}
+
+fn read_profiles(key_file: &KeyFile) -> HashMap<String, Profile> {
+    let mut profiles = HashMap::new();
+    let defaults = Profile::default();
+
+    for group in key_file.groups().iter() {
+        let Some(id) = group.strip_prefix(PROFILE_GROUP_PREFIX) else {
+            continue;
+        };
+
+        let method_idx = key_file
+            .string(group, "method")
+            .ok()
+            .and_then(|m| METHOD_KEYS.iter().position(|k| *k == m.as_str()))
+            .map(|i| i as u32)
+            .unwrap_or(defaults.method_idx);
+
+        let profile = Profile {
+            name: key_file.string(group, "name").map(|s| s.to_string()).unwrap_or_default(),
+            method_idx,
+            shift_amount: key_file.integer(group, "amount").map(|a| a.clamp(1, 10)).unwrap_or(defaults.shift_amount),
+            use_pattern: key_file.string(group, "pattern").map(|p| p != "alternate").unwrap_or(defaults.use_pattern),
+            interval_secs: key_file.integer(group, "interval").map(|i| i.clamp(5, 300) as u32).unwrap_or(defaults.interval_secs),
+            adaptive: key_file.boolean(group, "adaptive").unwrap_or(defaults.adaptive),
+            wear: key_file.boolean(group, "wear").unwrap_or(defaults.wear),
+            schedule: key_file.string(group, "schedule").map(|s| s.to_string()).unwrap_or_default(),
+            dim: key_file.string(group, "dim").map(|s| s.to_string()).unwrap_or_default(),
+            dim_level: key_file.integer(group, "dim-level").map(|l| l.clamp(10, 90) as u32).unwrap_or(defaults.dim_level),
+            conditioning: key_file.string(group, "conditioning").map(|s| s.to_string()).unwrap_or_default(),
+            conditioning_minutes: key_file
+                .integer(group, "conditioning-minutes")
+                .map(|m| m.clamp(1, 120) as u32)
+                .unwrap_or(defaults.conditioning_minutes),
+            conditioning_idle: key_file.integer(group, "conditioning-idle").map(|m| m.max(0) as u32).unwrap_or(defaults.conditioning_idle),
+            conditioning_at: key_file.string(group, "conditioning-at").map(|s| s.to_string()).unwrap_or_default(),
+            idle_dim: key_file.integer(group, "idle-dim").map(|m| m.max(0) as u32).unwrap_or(defaults.idle_dim),
+            idle_dim_level: key_file.integer(group, "idle-dim-level").map(|l| l.clamp(10, 90) as u32).unwrap_or(defaults.idle_dim_level),
+            idle_dim_method: key_file
+                .string(group, "idle-dim-method")
+                .ok()
+                .and_then(|m| BrightnessMethod::from_key(&m))
+                .unwrap_or(defaults.idle_dim_method),
+            hardware_orbit: key_file.boolean(group, "hardware-orbit").unwrap_or(defaults.hardware_orbit),
+        };
+        profiles.insert(id.to_string(), profile);
+    }
+
+    profiles
+}
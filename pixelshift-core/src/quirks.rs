@@ -0,0 +1,210 @@
+//! Monitors with their own pixel orbit or panel refresh, controlled through
+//! manufacturer-specific VCP codes over DDC/CI.
+//!
+//! Those codes are not standardized, so they come from a quirk database: GLib
+//! key files read from `/usr/share/pixelshift-gtk/ddc-quirks.ini` and then
+//! `$XDG_CONFIG_HOME/pixelshift-gtk/ddc-quirks.ini`, later entries winning.
+//! Groups are keyed by EDID manufacturer and model name, or manufacturer and
+//! hex product code when the EDID has no name. No entries ship yet, since
+//! the codes differ per model and firmware; this made-up monitor only shows
+//! the format:
+//!
+//! ```ini
+//! [XYZ Example OLED]
+//! orbit=0xE8
+//! orbit-on=1
+//! orbit-off=0
+//! refresh=0xE9
+//! refresh-start=1
+//! note=Pixel Shift in the OSD
+//!
+//! [XYZ 1234]
+//! orbit=0xF4
+//! orbit-on=2
+//! orbit-off=0
+//! ```
+//!
+//! Find the real codes for a monitor with `ddcutil capabilities` or by
+//! toggling the OSD option and comparing `ddcutil getvcp` output, then try
+//! them with `pixelshift ddc set`.
+//!
+//! When a session's monitor has its orbit on (because the profile enabled it
+//! or the user did in the OSD), the scheduler stops shifting in software so
+//! the panel is not moved twice.
+
+use glib::{KeyFile, KeyFileFlags};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::ddc::{self, Ddc};
+use crate::edid::EdidInfo;
+use crate::{DisplayInfo, SetTextSafe};
+
+const FILE_NAME: &str = "ddc-quirks.ini";
+
+/// A VCP code and the values that switch a feature on and off
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcpToggle {
+    pub code: u8,
+    pub on: u16,
+    pub off: u16,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Quirk {
+    pub orbit: Option<VcpToggle>,
+    /// Code and value that start the panel's refresh cycle
+    pub refresh: Option<(u8, u16)>,
+    pub note: String,
+}
+
+pub struct QuirkDb {
+    quirks: HashMap<String, Quirk>,
+}
+
+impl QuirkDb {
+    pub fn paths() -> Vec<PathBuf> {
+        vec![
+            PathBuf::from("/usr/share/pixelshift-gtk").join(FILE_NAME),
+            glib::user_config_dir().join("pixelshift-gtk").join(FILE_NAME),
+        ]
+    }
+
+    /// Entries that cannot be read are skipped and reported to `status`
+    pub fn load(status: &dyn SetTextSafe) -> Self {
+        Self::load_from(&Self::paths(), status)
+    }
+
+    /// Read `paths` in order, later entries replacing earlier ones
+    pub fn load_from(paths: &[PathBuf], status: &dyn SetTextSafe) -> Self {
+        let mut quirks = HashMap::new();
+        for path in paths {
+            let key_file = KeyFile::new();
+            if key_file.load_from_file(path, KeyFileFlags::NONE).is_err() {
+                continue;
+            }
+            for group in key_file.groups().iter() {
+                match read_quirk(&key_file, group) {
+                    Ok(quirk) => {
+                        quirks.insert(group.to_string(), quirk);
+                    }
+                    Err(e) => status.set_text_safe(&format!("✗ Ignoring quirk [{}] in {}: {}", group, path.display(), e)),
+                }
+            }
+        }
+        Self { quirks }
+    }
+
+    pub fn lookup(&self, edid: &EdidInfo) -> Option<&Quirk> {
+        edid.model_name
+            .as_ref()
+            .and_then(|model| self.quirks.get(&format!("{} {}", edid.manufacturer, model)))
+            .or_else(|| self.quirks.get(&format!("{} {:04X}", edid.manufacturer, edid.product_code)))
+    }
+
+    pub fn for_display(&self, display: &DisplayInfo) -> Option<&Quirk> {
+        display.edid.as_ref().and_then(|edid| self.lookup(edid))
+    }
+}
+
+fn read_quirk(key_file: &KeyFile, group: &str) -> Result<Quirk, String> {
+    let number = |key: &str| -> Result<Option<u16>, String> {
+        match key_file.string(group, key) {
+            Ok(value) => ddc::parse_number(&value).map(Some).ok_or_else(|| format!("{} is not a number", key)),
+            Err(_) => Ok(None),
+        }
+    };
+    let code = |key: &str| -> Result<Option<u8>, String> {
+        number(key)?.map(|c| u8::try_from(c).map_err(|_| format!("{} is not a VCP code", key))).transpose()
+    };
+
+    let orbit = match code("orbit")? {
+        Some(code) => Some(VcpToggle {
+            code,
+            on: number("orbit-on")?.ok_or("orbit without orbit-on")?,
+            off: number("orbit-off")?.unwrap_or(0),
+        }),
+        None => None,
+    };
+    let refresh = match code("refresh")? {
+        Some(code) => Some((code, number("refresh-start")?.unwrap_or(1))),
+        None => None,
+    };
+    Ok(Quirk {
+        orbit,
+        refresh,
+        note: key_file.string(group, "note").map(|s| s.to_string()).unwrap_or_default(),
+    })
+}
+
+/// The monitor's own orbit while a session runs, and the value to put back
+pub struct HardwareOrbit {
+    display: DisplayInfo,
+    toggle: VcpToggle,
+    /// Value before we switched it on, if we did
+    restore: Option<u16>,
+    active: bool,
+}
+
+impl HardwareOrbit {
+    /// Look at `display`'s orbit and switch it on when `enable` is set. `None`
+    /// when the quirk database knows no orbit control for the monitor.
+    pub fn engage(display: &DisplayInfo, enable: bool, status: &dyn SetTextSafe) -> Option<Result<Self, String>> {
+        let toggle = QuirkDb::load(status).for_display(display)?.orbit?;
+        Some(Self::engage_with(display, toggle, enable))
+    }
+
+    fn engage_with(display: &DisplayInfo, toggle: VcpToggle, enable: bool) -> Result<Self, String> {
+        let mut ddc = Ddc::for_display(display)?;
+        let current = ddc.get_vcp(toggle.code)?.current;
+        let mut orbit = Self {
+            display: display.clone(),
+            toggle,
+            restore: None,
+            active: current != toggle.off,
+        };
+        if enable && !orbit.active {
+            ddc.set_vcp(toggle.code, toggle.on)?;
+            orbit.restore = Some(current);
+            orbit.active = true;
+        }
+        Ok(orbit)
+    }
+
+    /// Whether the monitor is moving the picture itself
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    /// Put the orbit setting back the way the session found it
+    pub fn release(self) -> Result<(), String> {
+        match self.restore {
+            Some(value) => Ddc::for_display(&self.display)?.set_vcp(self.toggle.code, value),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Switch the monitor's own orbit on or off
+pub fn set_orbit(display: &DisplayInfo, on: bool, status: &dyn SetTextSafe) -> Result<(), String> {
+    let toggle = QuirkDb::load(status)
+        .for_display(display)
+        .and_then(|q| q.orbit)
+        .ok_or_else(|| format!("no orbit control known for {} (see ddc-quirks.ini)", display.name))?;
+    Ddc::for_display(display)?.set_vcp(toggle.code, if on { toggle.on } else { toggle.off })
+}
+
+/// `Some(on)` from the monitor, `None` without a quirk for it
+pub fn orbit_state(display: &DisplayInfo, status: &dyn SetTextSafe) -> Option<Result<bool, String>> {
+    let toggle = QuirkDb::load(status).for_display(display)?.orbit?;
+    Some(Ddc::for_display(display).and_then(|mut ddc| ddc.get_vcp(toggle.code)).map(|v| v.current != toggle.off))
+}
+
+/// Start the panel's own refresh (compensation) cycle
+pub fn start_refresh(display: &DisplayInfo, status: &dyn SetTextSafe) -> Result<(), String> {
+    let (code, value) = QuirkDb::load(status)
+        .for_display(display)
+        .and_then(|q| q.refresh)
+        .ok_or_else(|| format!("no refresh control known for {} (see ddc-quirks.ini)", display.name))?;
+    Ddc::for_display(display)?.set_vcp(code, value)
+}
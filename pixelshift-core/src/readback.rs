@@ -0,0 +1,120 @@
+//! What RandR says an output is actually doing, from `xrandr --verbose`, so a
+//! shift the server accepted can be checked for the effect it should have had.
+
+use std::process::Command;
+
+use crate::display::parse_output_position;
+use crate::{dryrun, DisplayInfo};
+
+/// Transform entries are 16.16 fixed point on the wire; allow for the rounding
+const TRANSFORM_TOLERANCE: f64 = 1e-4;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputReadback {
+    /// Position of the CRTC within the X screen
+    pub position: (i32, i32),
+    /// Row-major, as `--transform` takes it
+    pub transform: [f64; 9],
+    /// Panning area as `W`, `H`, `X`, `Y`, if any is set
+    pub panning: Option<(u32, u32, i32, i32)>,
+}
+
+impl OutputReadback {
+    /// Whether shifting `display` by `offset` with `method_idx` shows here
+    pub fn shows(&self, method_idx: u32, display: &DisplayInfo, offset: (i32, i32)) -> bool {
+        let (x, y) = (display.x + offset.0, display.y + offset.1);
+        match method_idx {
+            1 => self.panning == Some((display.width + 10, display.height + 10, x, y)),
+            2 => self.position == (x, y),
+            3 => self.panning == Some((display.width, display.height, x, y)),
+            _ => {
+                let tx = offset.0 as f64 / display.width as f64;
+                let ty = offset.1 as f64 / display.height as f64;
+                (self.transform[2] - tx).abs() <= TRANSFORM_TOLERANCE && (self.transform[5] - ty).abs() <= TRANSFORM_TOLERANCE
+            }
+        }
+    }
+
+    /// The part of the state `method_idx` changes, for messages
+    pub fn describe(&self, method_idx: u32) -> String {
+        match method_idx {
+            1 | 3 => match self.panning {
+                Some((w, h, x, y)) => format!("panning {}x{}+{}+{}", w, h, x, y),
+                None => "no panning".to_string(),
+            },
+            2 => format!("position {}x{}", self.position.0, self.position.1),
+            _ => {
+                let entries: Vec<String> = self.transform.iter().map(|v| format!("{:.6}", v)).collect();
+                format!("transform {}", entries.join(","))
+            }
+        }
+    }
+}
+
+/// Read `output`'s current state from the X server
+pub fn read(output: &str) -> Result<OutputReadback, String> {
+    let result = Command::new("xrandr").args(["--query", "--verbose"]).output().map_err(|e| format!("cannot run xrandr: {}", e))?;
+    if !result.status.success() {
+        return Err(format!("xrandr --verbose failed: {}", String::from_utf8_lossy(&result.stderr).trim()));
+    }
+    parse(&String::from_utf8_lossy(&result.stdout), output).ok_or_else(|| format!("xrandr --verbose does not describe {}", output))
+}
+
+/// What reading a shift back found
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verification {
+    /// RandR shows the offset; a dry run, which changes nothing, counts as this
+    Applied,
+    /// RandR shows something else; what it reports
+    Ineffective(String),
+    /// RandR could not be asked or did not describe the output; why
+    Unknown(String),
+}
+
+/// Check that the last shift of `display` took effect
+pub fn verify(method_idx: u32, display: &DisplayInfo, offset: (i32, i32)) -> Verification {
+    if dryrun::is_enabled() {
+        return Verification::Applied;
+    }
+    match read(&display.name) {
+        Ok(readback) if readback.shows(method_idx, display, offset) => Verification::Applied,
+        Ok(readback) => Verification::Ineffective(readback.describe(method_idx)),
+        Err(e) => Verification::Unknown(e),
+    }
+}
+
+pub fn parse(verbose: &str, output: &str) -> Option<OutputReadback> {
+    let mut lines = verbose.lines().skip_while(|line| line.split_whitespace().next() != Some(output) || !line.contains(" connected"));
+    let header = lines.next()?;
+
+    let mut readback = OutputReadback {
+        position: parse_output_position(header)?,
+        transform: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        panning: None,
+    };
+
+    let mut block = lines.take_while(|line| line.starts_with(' ') || line.starts_with('\t')).map(str::trim);
+    while let Some(line) = block.next() {
+        if let Some(first_row) = line.strip_prefix("Transform:") {
+            let values: Vec<f64> = std::iter::once(first_row)
+                .chain(block.by_ref().take(2))
+                .flat_map(str::split_whitespace)
+                .filter_map(|v| v.parse().ok())
+                .collect();
+            if let Ok(transform) = values.try_into() {
+                readback.transform = transform;
+            }
+        } else if let Some(panning) = line.strip_prefix("Panning:") {
+            readback.panning = parse_geometry(panning.trim()).filter(|(w, h, _, _)| *w > 0 && *h > 0);
+        }
+    }
+    Some(readback)
+}
+
+/// `WxH+X+Y`
+fn parse_geometry(text: &str) -> Option<(u32, u32, i32, i32)> {
+    let (size, position) = text.split_once('+')?;
+    let (w, h) = size.split_once('x')?;
+    let (x, y) = position.split_once('+')?;
+    Some((w.parse().ok()?, h.parse().ok()?, x.parse().ok()?, y.parse().ok()?))
+}
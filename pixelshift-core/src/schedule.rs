@@ -0,0 +1,309 @@
+//! Time-of-day and weekday schedules for a profile's shift interval.
+//!
+//! A schedule is a list of rules separated by `;` or newlines. The first rule
+//! matching the local time wins; when none matches the profile's interval is
+//! used. Each rule is `[DAYS] [HH:MM-HH:MM] INTERVAL`:
+//!
+//! ```text
+//! sat-sun off; mon-fri 09:00-18:00 60s; * 10m
+//! ```
+//!
+//! - `DAYS`: `*`, `weekdays`, `weekends`, a day (`mon` or `monday`), a range
+//!   (`mon-fri`) or a list (`sat,sun`); all days when omitted
+//! - time ranges may wrap past midnight (`22:00-06:00`); all day when omitted
+//! - `INTERVAL`: seconds (`45`, `45s`), minutes (`10m`), hours (`1h`) or `off`
+
+use glib::DateTime;
+use std::fmt;
+
+use crate::SetTextSafe;
+
+const DAY_NAMES: [&str; 7] = ["mon", "tue", "wed", "thu", "fri", "sat", "sun"];
+const FULL_DAY_NAMES: [&str; 7] = ["monday", "tuesday", "wednesday", "thursday", "friday", "saturday", "sunday"];
+const MINUTES_PER_DAY: u32 = 24 * 60;
+const MINUTES_PER_WEEK: u32 = 7 * MINUTES_PER_DAY;
+
+/// Position within the week, Monday 00:00 being zero
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WeekTime {
+    /// 0 = Monday
+    pub weekday: u32,
+    pub minute: u32,
+}
+
+impl WeekTime {
+    pub fn from_date_time(t: &DateTime) -> Self {
+        Self {
+            weekday: (t.day_of_week() - 1).clamp(0, 6) as u32,
+            minute: (t.hour() * 60 + t.minute()) as u32,
+        }
+    }
+
+    fn from_week_minute(minute: u32) -> Self {
+        let minute = minute % MINUTES_PER_WEEK;
+        Self {
+            weekday: minute / MINUTES_PER_DAY,
+            minute: minute % MINUTES_PER_DAY,
+        }
+    }
+
+    fn week_minute(self) -> u32 {
+        self.weekday * MINUTES_PER_DAY + self.minute
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    /// Bit 0 = Monday
+    days: u8,
+    start: u32,
+    end: u32,
+    /// `None` turns shifting off
+    interval: Option<u64>,
+}
+
+impl Rule {
+    fn matches(&self, t: WeekTime) -> bool {
+        let on = |weekday: u32| self.days & (1 << weekday) != 0;
+        if self.start < self.end {
+            on(t.weekday) && (self.start..self.end).contains(&t.minute)
+        } else {
+            // Wraps past midnight: the early hours belong to the previous day's rule
+            let yesterday = (t.weekday + 6) % 7;
+            (on(t.weekday) && t.minute >= self.start) || (on(yesterday) && t.minute < self.end)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schedule {
+    rules: Vec<Rule>,
+}
+
+impl Schedule {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let rules = text
+            .split([';', '\n'])
+            .map(str::trim)
+            .filter(|r| !r.is_empty())
+            .map(parse_rule)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Interval in effect at `t`, `None` when shifting is off
+    pub fn interval_at(&self, t: WeekTime, default: u64) -> Option<u64> {
+        match self.rules.iter().find(|r| r.matches(t)) {
+            Some(rule) => rule.interval,
+            None => Some(default),
+        }
+    }
+
+    /// Minutes from `t` until the interval next changes, if it ever does
+    pub fn minutes_until_change(&self, t: WeekTime, default: u64) -> Option<u32> {
+        let current = self.interval_at(t, default);
+        let start = t.week_minute();
+
+        // Which rule matches can only change where a rule's hours start or
+        // end, or at midnight where its days do
+        let mut offsets: Vec<u32> = (0..7)
+            .flat_map(|day| {
+                let midnight = day * MINUTES_PER_DAY;
+                self.rules.iter().flat_map(move |r| [midnight + r.start, midnight + r.end]).chain([midnight])
+            })
+            .map(|minute| (minute + MINUTES_PER_WEEK - start) % MINUTES_PER_WEEK)
+            .filter(|&offset| offset != 0)
+            .collect();
+        offsets.sort_unstable();
+        offsets.dedup();
+        offsets
+            .into_iter()
+            .find(|offset| self.interval_at(WeekTime::from_week_minute(start + offset), default) != current)
+    }
+
+    /// Upcoming changes after `now`, starting with the interval in effect now
+    pub fn timeline(&self, now: &DateTime, default: u64, count: usize) -> Vec<(DateTime, Option<u64>)> {
+        let mut entries = vec![(now.clone(), self.interval_at(WeekTime::from_date_time(now), default))];
+        let mut t = now.add_seconds(-(now.seconds())).unwrap_or_else(|_| now.clone());
+
+        while entries.len() < count {
+            let Some(minutes) = self.minutes_until_change(WeekTime::from_date_time(&t), default) else { break };
+            let Ok(next) = t.add_minutes(minutes as i32) else { break };
+            entries.push((next.clone(), self.interval_at(WeekTime::from_date_time(&next), default)));
+            t = next;
+        }
+        entries
+    }
+}
+
+impl fmt::Display for Schedule {
+    /// Normalized form that parses back to the same schedule
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let rules: Vec<String> = self
+            .rules
+            .iter()
+            .map(|r| {
+                let days = if r.days == 0x7f {
+                    "*".to_string()
+                } else {
+                    (0..7).filter(|d| r.days & (1 << d) != 0).map(|d| DAY_NAMES[d]).collect::<Vec<_>>().join(",")
+                };
+                let hours = if r.start == 0 && r.end == MINUTES_PER_DAY {
+                    String::new()
+                } else {
+                    format!(" {}-{}", format_minute(r.start), format_minute(r.end))
+                };
+                let interval = match r.interval {
+                    None => "off".to_string(),
+                    Some(s) if s % 3600 == 0 => format!("{}h", s / 3600),
+                    Some(s) if s % 60 == 0 => format!("{}m", s / 60),
+                    Some(s) => format!("{}s", s),
+                };
+                format!("{}{} {}", days, hours, interval)
+            })
+            .collect();
+        write!(f, "{}", rules.join("; "))
+    }
+}
+
+pub fn format_interval(interval: Option<u64>) -> String {
+    match interval {
+        None => "off".to_string(),
+        Some(s) if s % 3600 == 0 => format!("every {}h", s / 3600),
+        Some(s) if s % 60 == 0 => format!("every {}m", s / 60),
+        Some(s) => format!("every {}s", s),
+    }
+}
+
+fn format_minute(minute: u32) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+fn parse_rule(text: &str) -> Result<Rule, String> {
+    let mut tokens: Vec<&str> = text.split_whitespace().collect();
+    let interval = parse_interval(tokens.pop().ok_or("empty rule")?)?;
+
+    let mut days = 0x7f;
+    let mut start = 0;
+    let mut end = MINUTES_PER_DAY;
+    for token in tokens {
+        if token.contains(':') {
+            let (from, to) = token.split_once('-').ok_or_else(|| format!("'{}' is not a time range like 09:00-18:00", token))?;
+            start = parse_clock(from)?;
+            end = parse_clock(to)?;
+            if start == end {
+                return Err(format!("time range '{}' is empty", token));
+            }
+        } else {
+            days = parse_days(token)?;
+        }
+    }
+
+    Ok(Rule { days, start, end, interval })
+}
+
+fn parse_interval(token: &str) -> Result<Option<u64>, String> {
+    if token.eq_ignore_ascii_case("off") {
+        return Ok(None);
+    }
+
+    let (number, unit) = match token.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => token.split_at(i),
+        None => (token, "s"),
+    };
+    let multiplier = match unit {
+        "s" => 1,
+        "m" | "min" => 60,
+        "h" => 3600,
+        _ => return Err(format!("'{}' is not an interval like 60s, 10m or off", token)),
+    };
+    let value: u64 = number.parse().map_err(|_| format!("'{}' is not an interval like 60s, 10m or off", token))?;
+    let seconds = value * multiplier;
+    if !(5..=86_400).contains(&seconds) {
+        return Err(format!("interval '{}' must be between 5s and 24h", token));
+    }
+    Ok(Some(seconds))
+}
+
+fn parse_clock(text: &str) -> Result<u32, String> {
+    let invalid = || format!("'{}' is not a time like 09:30", text);
+    let (h, m) = text.split_once(':').ok_or_else(invalid)?;
+    let h: u32 = h.parse().map_err(|_| invalid())?;
+    let m: u32 = m.parse().map_err(|_| invalid())?;
+    // 24:00 is allowed as the end of the day
+    if m >= 60 || h > 24 || (h == 24 && m != 0) {
+        return Err(invalid());
+    }
+    Ok(h * 60 + m)
+}
+
+fn parse_days(token: &str) -> Result<u8, String> {
+    let token = token.to_ascii_lowercase();
+    match token.as_str() {
+        "*" | "daily" => return Ok(0x7f),
+        "weekdays" => return Ok(0x1f),
+        "weekends" => return Ok(0x60),
+        _ => {}
+    }
+
+    let day = |name: &str| {
+        DAY_NAMES
+            .iter()
+            .position(|d| *d == name)
+            .or_else(|| FULL_DAY_NAMES.iter().position(|d| *d == name))
+            .ok_or_else(|| format!("unknown day '{}'", name))
+    };
+
+    let mut mask = 0u8;
+    for part in token.split(',') {
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let (from, to) = (day(from)?, day(to)?);
+                // Ranges may wrap, e.g. fri-mon
+                let mut d = from;
+                loop {
+                    mask |= 1 << d;
+                    if d == to {
+                        break;
+                    }
+                    d = (d + 1) % 7;
+                }
+            }
+            None => mask |= 1 << day(part)?,
+        }
+    }
+    Ok(mask)
+}
+
+/// Local wall clock for evaluating schedules. `PIXELSHIFT_FAKE_TIME` (ISO 8601,
+/// e.g. `2026-10-19T08:59:30`) makes it start at that time and run from there,
+/// so schedule changes can be exercised without waiting for them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Clock {
+    offset_secs: f64,
+}
+
+impl Clock {
+    pub fn from_env(status: &dyn SetTextSafe) -> Self {
+        let Ok(fake) = std::env::var("PIXELSHIFT_FAKE_TIME") else { return Self::default() };
+        let (Ok(fake), Ok(now)) = (DateTime::from_iso8601(&fake, Some(&glib::TimeZone::local())), DateTime::now_local()) else {
+            status.set_text_safe(&format!("✗ Ignoring invalid PIXELSHIFT_FAKE_TIME '{}'", fake));
+            return Self::default();
+        };
+        Self {
+            offset_secs: fake.difference(&now).as_seconds() as f64,
+        }
+    }
+
+    pub fn now(&self) -> DateTime {
+        let now = DateTime::now_local().expect("local time is available");
+        if self.offset_secs == 0.0 {
+            return now;
+        }
+        now.add_seconds(self.offset_secs).unwrap_or(now)
+    }
+}
@@ -0,0 +1,814 @@
+use glib::source::SourceId;
+use std::cell::{Cell, RefCell};
+use std::rc::{Rc, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::activity::ActivitySampler;
+use crate::backend::{self, ShiftError};
+use crate::capabilities::CapabilityStore;
+use crate::history::{Detail, Level};
+use crate::profiles::{method_name, ProfileStore};
+use crate::quirks::HardwareOrbit;
+use crate::schedule::{Clock, Schedule, WeekTime};
+use crate::state::{self, ShiftState};
+use crate::{get_connected_displays, preferred_display_index, reset_display_safe, DisplayInfo, SetTextSafe};
+
+#[derive(Clone)]
+struct ShiftPattern {
+    positions: Vec<(i32, i32)>,
+    current_index: usize,
+}
+
+impl ShiftPattern {
+    pub fn new(shift_amount: i32) -> Self {
+        // Create a circular pattern to minimize visible transitions
+        let positions = vec![
+            (0, 0),                    // Center
+            (shift_amount, 0),         // Right
+            (shift_amount, shift_amount), // Bottom-right
+            (0, shift_amount),         // Bottom
+            (-shift_amount, shift_amount), // Bottom-left
+            (-shift_amount, 0),        // Left
+            (-shift_amount, -shift_amount), // Top-left
+            (0, -shift_amount),        // Top
+            (shift_amount, -shift_amount), // Top-right
+        ];
+
+        Self {
+            positions,
+            current_index: 0,
+        }
+    }
+
+    /// Index of the position the next call to `next` returns
+    pub fn index(&self) -> usize {
+        self.current_index
+    }
+
+    pub fn next(&mut self) -> (i32, i32) {
+        let pos = self.positions[self.current_index];
+        self.current_index = (self.current_index + 1) % self.positions.len();
+        pos
+    }
+}
+
+/// Settings and progress of a running auto-shift, kept so the timer can be
+/// re-armed (e.g. after resume) without going back to the widgets
+#[derive(Clone)]
+pub struct AutoShiftSession {
+    pub display: DisplayInfo,
+    pub shift_amount: i32,
+    pub method_idx: u32,
+    pub use_pattern: bool,
+    pub interval_secs: u64,
+    /// Overrides `interval_secs` by time of day; empty means always `interval_secs`
+    pub schedule: Schedule,
+    /// Scale the interval by how static the display's content is
+    pub adaptive: bool,
+    /// Switch on the monitor's own orbit instead of shifting, where known
+    pub hardware_orbit: bool,
+    pub current_offset: (i32, i32),
+    toggle: bool,
+    pattern: ShiftPattern,
+}
+
+impl AutoShiftSession {
+    pub fn new(display: DisplayInfo, shift_amount: i32, method_idx: u32, use_pattern: bool, interval_secs: u64) -> Self {
+        Self {
+            display,
+            shift_amount,
+            method_idx,
+            use_pattern,
+            interval_secs,
+            schedule: Schedule::default(),
+            adaptive: false,
+            hardware_orbit: false,
+            current_offset: (0, 0),
+            toggle: false,
+            pattern: ShiftPattern::new(shift_amount),
+        }
+    }
+
+    pub fn with_schedule(mut self, schedule: Schedule) -> Self {
+        self.schedule = schedule;
+        self
+    }
+
+    pub fn with_adaptive(mut self, adaptive: bool) -> Self {
+        self.adaptive = adaptive;
+        self
+    }
+
+    pub fn with_hardware_orbit(mut self, hardware_orbit: bool) -> Self {
+        self.hardware_orbit = hardware_orbit;
+        self
+    }
+
+    /// Session for `display` using its saved profile, or the defaults
+    pub fn from_profile(display: DisplayInfo, status: &dyn SetTextSafe) -> Self {
+        let profile = ProfileStore::load().get(&display.monitor_id()).cloned().unwrap_or_default();
+        let schedule = Schedule::parse(&profile.schedule).unwrap_or_else(|e| {
+            status.set_text_safe(&format!("✗ Ignoring schedule of {}: {}", display.monitor_id(), e));
+            Schedule::default()
+        });
+        Self::new(
+            display,
+            profile.shift_amount,
+            profile.method_idx,
+            profile.use_pattern,
+            profile.interval_secs as u64,
+        )
+        .with_schedule(schedule)
+        .with_adaptive(profile.adaptive)
+        .with_hardware_orbit(profile.hardware_orbit)
+    }
+
+    /// Interval in effect at `now`, `None` while the schedule has shifting off
+    pub fn interval_at(&self, now: &glib::DateTime) -> Option<u64> {
+        self.schedule.interval_at(WeekTime::from_date_time(now), self.interval_secs)
+    }
+
+    pub fn pattern_index(&self) -> usize {
+        self.pattern.index()
+    }
+
+    fn next_offset(&mut self) -> (i32, i32) {
+        if self.use_pattern {
+            self.pattern.next()
+        } else {
+            // Simple alternating shift
+            self.toggle = !self.toggle;
+            if self.toggle {
+                (self.shift_amount, self.shift_amount)
+            } else {
+                (0, 0)
+            }
+        }
+    }
+}
+
+/// Notifications for windows, the D-Bus service and other observers
+#[derive(Debug, Clone)]
+pub enum SchedulerEvent {
+    /// Started, stopped, paused or resumed
+    StateChanged,
+    Shifted,
+    DisplaysChanged,
+    Error(String),
+    /// The session moved to another method because `from` does not work on its monitor
+    MethodFallback { from: u32, to: u32, reason: String },
+}
+
+pub type ListenerId = usize;
+type Listener = Rc<dyn Fn(&SchedulerEvent)>;
+
+struct Inner {
+    session: RefCell<Option<AutoShiftSession>>,
+    timer: RefCell<Option<SourceId>>,
+    /// Bumped whenever the timer is (re)armed or disarmed, so a firing timer
+    /// can tell whether a listener replaced or stopped it
+    generation: Cell<u64>,
+    clock: Clock,
+    /// Framebuffer sampler while an adaptive session runs
+    activity: RefCell<Option<ActivitySampler>>,
+    /// The session monitor's own orbit, when the quirk database knows how to reach it
+    hardware: RefCell<Option<HardwareOrbit>>,
+    /// Unix time the timer fires next
+    next_shift: Cell<Option<u64>>,
+    /// The session's method has failed before, so its next success should
+    /// clear that from the capability cache
+    failures_recorded: Cell<bool>,
+    /// The timer was running when the machine went to sleep; a session the
+    /// user had paused stays paused after wake
+    resume_after_wake: Cell<bool>,
+    displays: RefCell<Vec<DisplayInfo>>,
+    status: Box<dyn SetTextSafe>,
+    listeners: RefCell<Vec<(ListenerId, Listener)>>,
+    next_listener_id: Cell<ListenerId>,
+}
+
+/// Owns the auto-shift timer and the display list; shared by the GUI and
+/// the headless `run` command
+#[derive(Clone)]
+pub struct Scheduler {
+    inner: Rc<Inner>,
+}
+
+impl Scheduler {
+    pub fn new(status: impl SetTextSafe + 'static) -> Self {
+        let clock = Clock::from_env(&status);
+        Self {
+            inner: Rc::new(Inner {
+                session: RefCell::new(None),
+                timer: RefCell::new(None),
+                generation: Cell::new(0),
+                clock,
+                activity: RefCell::new(None),
+                hardware: RefCell::new(None),
+                next_shift: Cell::new(None),
+                failures_recorded: Cell::new(false),
+                resume_after_wake: Cell::new(false),
+                displays: RefCell::new(get_connected_displays()),
+                status: Box::new(status),
+                listeners: RefCell::new(Vec::new()),
+                next_listener_id: Cell::new(1),
+            }),
+        }
+    }
+
+    pub fn connect_event<F: Fn(&SchedulerEvent) + 'static>(&self, f: F) -> ListenerId {
+        let id = self.inner.next_listener_id.get();
+        self.inner.next_listener_id.set(id + 1);
+        self.inner.listeners.borrow_mut().push((id, Rc::new(f)));
+        id
+    }
+
+    pub fn disconnect(&self, id: ListenerId) {
+        self.inner.listeners.borrow_mut().retain(|(i, _)| *i != id);
+    }
+
+    /// Where the scheduler reports, for code acting on its behalf
+    pub fn status(&self) -> &dyn SetTextSafe {
+        self.inner.status.as_ref()
+    }
+
+    fn emit(&self, event: SchedulerEvent) {
+        // Listeners may call back into the scheduler or (dis)connect, so don't hold the borrow
+        let listeners: Vec<_> = self.inner.listeners.borrow().iter().map(|(_, f)| f.clone()).collect();
+        for listener in listeners {
+            listener(&event);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.inner.timer.borrow().is_some()
+    }
+
+    /// A session exists but its timer is stopped (hotplug, suspend)
+    pub fn is_paused(&self) -> bool {
+        !self.is_running() && self.inner.session.borrow().is_some()
+    }
+
+    /// Running or paused session
+    pub fn session(&self) -> Option<AutoShiftSession> {
+        self.inner.session.borrow().clone()
+    }
+
+    /// Unix time of the next scheduled shift, if running
+    pub fn next_shift_time(&self) -> Option<u64> {
+        self.inner.next_shift.get()
+    }
+
+    /// Schedule clock; honours `PIXELSHIFT_FAKE_TIME`
+    pub fn clock(&self) -> Clock {
+        self.inner.clock
+    }
+
+    /// Interval in effect now for the session, `None` if idle or scheduled off
+    pub fn current_interval(&self) -> Option<u64> {
+        let now = self.inner.clock.now();
+        let interval = self.inner.session.borrow().as_ref().and_then(|s| s.interval_at(&now));
+        interval.map(|i| self.adapt(i))
+    }
+
+    /// Share of the session's display that has been static, when sampling
+    pub fn static_fraction(&self) -> Option<f64> {
+        self.inner.activity.borrow().as_ref().and_then(|a| a.static_fraction())
+    }
+
+    fn adapt(&self, interval: u64) -> u64 {
+        match *self.inner.activity.borrow() {
+            Some(ref activity) => activity.interval(interval),
+            None => interval,
+        }
+    }
+
+    fn start_sampling(&self) {
+        let display = match *self.inner.session.borrow() {
+            Some(ref session) if session.adaptive => session.display.clone(),
+            _ => return,
+        };
+        let mut activity = self.inner.activity.borrow_mut();
+        if activity.is_none() {
+            *activity = Some(ActivitySampler::start(display));
+        }
+    }
+
+    /// Check the monitor's own orbit, switching it on if the session asks
+    fn engage_hardware(&self) {
+        let (display, enable) = match *self.inner.session.borrow() {
+            Some(ref session) => (session.display.clone(), session.hardware_orbit),
+            None => return,
+        };
+
+        match HardwareOrbit::engage(&display, enable, self.inner.status.as_ref()) {
+            Some(Ok(orbit)) => {
+                if orbit.is_active() {
+                    self.inner.status.log_safe(Level::Info, &format!("{}'s built-in pixel orbit is on; not shifting in software.", display.name));
+                }
+                *self.inner.hardware.borrow_mut() = Some(orbit);
+            }
+            Some(Err(e)) if enable => {
+                self.inner.status.log_safe(Level::Warning, &format!("Monitor pixel orbit unavailable ({}); shifting in software.", e));
+            }
+            Some(Err(_)) => {}
+            None if enable => {
+                self.inner.status.log_safe(Level::Warning, &format!("No pixel orbit control known for {}; shifting in software.", display.name));
+            }
+            None => {}
+        }
+    }
+
+    /// Move a new session off a method already known to fail on its monitor
+    fn skip_failing_method(&self) {
+        let (display, from) = match *self.inner.session.borrow() {
+            Some(ref session) => (session.display.clone(), session.method_idx),
+            None => return,
+        };
+        let store = CapabilityStore::load();
+        let Some(capabilities) = store.get(&display.monitor_id()) else {
+            self.inner.failures_recorded.set(false);
+            return;
+        };
+
+        let method = match (capabilities.reason(from), capabilities.fallback(from)) {
+            (None, _) => from,
+            (Some(reason), Some(to)) => {
+                if let Some(session) = self.inner.session.borrow_mut().as_mut() {
+                    session.method_idx = to;
+                }
+                self.report_fallback(&display, from, to, reason);
+                to
+            }
+            (Some(_), None) => {
+                self.inner.status.log_safe(Level::Warning, &format!("No shift method is known to work on {}; trying {} anyway.", display.name, method_name(from)));
+                from
+            }
+        };
+        self.inner.failures_recorded.set(capabilities.runtime.get(method as usize).is_some_and(Option::is_some));
+    }
+
+    /// The session's method just failed with `reason`: count that against it
+    /// for its monitor and shift with the next method not known to fail
+    /// instead. False if none works either, or the display is gone rather
+    /// than the method.
+    fn fall_back(&self, reason: String) -> bool {
+        let Some(session) = self.session() else { return false };
+        let display = session.display;
+        // A monitor unplugged between ticks says nothing about the method
+        if !get_connected_displays().iter().any(|d| d.name == display.name) {
+            return false;
+        }
+
+        let status = &Detail(self.inner.status.as_ref());
+        let mut store = CapabilityStore::load();
+        let (mut failed, mut why) = (session.method_idx, reason.clone());
+        let mut tried = vec![failed];
+        let working = loop {
+            store.record_failure(&display, failed, &why);
+            // One failure is not enough to stop using a method, but it is
+            // enough not to go back to it within this fallback
+            let next = store
+                .get(&display.monitor_id())
+                .and_then(|c| c.fallbacks(failed).find(|m| !tried.contains(m)));
+            let Some(next) = next else { break None };
+            tried.push(next);
+            // Undo whatever the failed attempt left before the next method takes over
+            reset_display_safe(&display, status);
+            match backend::shift(next, &display, session.current_offset, status) {
+                Ok(()) => break Some(next),
+                Err(e) => (failed, why) = (next, e.to_string()),
+            }
+        };
+        if let Some(to) = working {
+            store.record_success(&display, to);
+        }
+        self.inner.failures_recorded.set(false);
+        if let Err(e) = store.save() {
+            self.inner.status.set_text_safe(&format!("✗ Could not save which methods work: {}", e));
+        }
+
+        let Some(to) = working else { return false };
+        if let Some(session) = self.inner.session.borrow_mut().as_mut() {
+            session.method_idx = to;
+            state::save(&ShiftState::for_session(session));
+        }
+        self.report_fallback(&display, session.method_idx, to, &reason);
+        self.emit(SchedulerEvent::Shifted);
+        true
+    }
+
+    /// The session's method worked, so the failures on record for it no
+    /// longer count towards skipping it
+    fn forget_failures(&self) {
+        let Some(session) = self.session() else { return };
+        let mut store = CapabilityStore::load();
+        if store.record_success(&session.display, session.method_idx) {
+            if let Err(e) = store.save() {
+                self.inner.status.set_text_safe(&format!("✗ Could not save which methods work: {}", e));
+            }
+        }
+    }
+
+    fn report_fallback(&self, display: &DisplayInfo, from: u32, to: u32, reason: &str) {
+        self.inner.status.set_text_safe(&format!(
+            "{} does not work on {} ({}); using {} instead.",
+            method_name(from),
+            display.name,
+            reason,
+            method_name(to)
+        ));
+        self.emit(SchedulerEvent::MethodFallback { from, to, reason: reason.to_string() });
+    }
+
+    /// Whether the monitor moves the picture itself, so shifting would double up
+    pub fn hardware_orbit_active(&self) -> bool {
+        self.inner.hardware.borrow().as_ref().is_some_and(|h| h.is_active())
+    }
+
+    /// Displays as of the last enumeration
+    pub fn displays(&self) -> Vec<DisplayInfo> {
+        self.inner.displays.borrow().clone()
+    }
+
+    pub fn start(&self, session: AutoShiftSession) {
+        if self.is_running() {
+            return;
+        }
+        // A paused session still holds its display's offset and the monitor's orbit setting
+        if self.is_paused() {
+            self.stop();
+        }
+
+        let summary = if session.schedule.is_empty() {
+            format!("every {}s", session.interval_secs)
+        } else {
+            format!("on schedule \"{}\"", session.schedule)
+        };
+        self.inner.status.set_text_safe(&format!("Starting auto-shift for {} {}", session.display.name, summary));
+        *self.inner.session.borrow_mut() = Some(session);
+        self.skip_failing_method();
+        self.engage_hardware();
+        self.start_sampling();
+        self.arm();
+        self.emit(SchedulerEvent::StateChanged);
+    }
+
+    /// Stop shifting and put the display back to normal
+    pub fn stop(&self) -> bool {
+        self.disarm();
+        self.inner.activity.borrow_mut().take();
+        if let Some(hardware) = self.inner.hardware.borrow_mut().take() {
+            if let Err(e) = hardware.release() {
+                self.inner.status.set_text_safe(&format!("✗ Could not restore the monitor's pixel orbit setting: {}", e));
+            }
+        }
+        let session = self.inner.session.borrow_mut().take();
+        let reset = match session {
+            Some(session) => reset_display_safe(&session.display, self.inner.status.as_ref()),
+            None => true,
+        };
+        state::clear();
+        self.emit(SchedulerEvent::StateChanged);
+        reset
+    }
+
+    /// Stop the timer but keep the session so it can be resumed. Returns
+    /// false when nothing was running.
+    pub fn pause(&self) -> bool {
+        self.inner.activity.borrow_mut().take();
+        let paused = self.disarm();
+        if paused {
+            self.emit(SchedulerEvent::StateChanged);
+        }
+        paused
+    }
+
+    /// Reapply the current offset and re-arm the timer of a paused session
+    pub fn resume(&self) -> bool {
+        if self.is_running() {
+            return false;
+        }
+
+        let Some(offset) = self.session().map(|s| s.current_offset) else { return false };
+        self.shift_session(offset);
+
+        self.start_sampling();
+        self.arm();
+        self.emit(SchedulerEvent::StateChanged);
+        true
+    }
+
+    /// Advance the session to its next offset immediately and restart the interval
+    pub fn shift_now(&self) {
+        let running = self.disarm();
+        self.tick();
+        if running {
+            self.arm();
+        }
+    }
+
+    /// Apply a single offset now, to the session's display or the preferred one
+    pub fn shift_once(&self, x_offset: i32, y_offset: i32) -> Result<(), String> {
+        let (display, method_idx) = match self.session() {
+            Some(session) => (session.display, session.method_idx),
+            None => {
+                let session = AutoShiftSession::from_profile(self.preferred_display()?, self.inner.status.as_ref());
+                (session.display, session.method_idx)
+            }
+        };
+
+        if let Err(e) = backend::shift(method_idx, &display, (x_offset, y_offset), &Detail(self.inner.status.as_ref())) {
+            let message = failure_message(&e, &display.name);
+            self.emit(SchedulerEvent::Error(message.clone()));
+            return Err(message);
+        }
+
+        if let Some(session) = self.inner.session.borrow_mut().as_mut() {
+            session.current_offset = (x_offset, y_offset);
+        }
+        state::save(&ShiftState::new(self.is_running().then(std::process::id), &display.name, method_idx, (x_offset, y_offset)));
+        self.emit(SchedulerEvent::Shifted);
+        Ok(())
+    }
+
+    /// Move the session over to another shift method, keeping its offset.
+    /// Returns the method it used before.
+    pub fn set_method(&self, method_idx: u32) -> Result<u32, String> {
+        let Some(session) = self.session() else { return Err("no auto-shift session".to_string()) };
+        if session.method_idx == method_idx {
+            return Ok(method_idx);
+        }
+
+        // Undo what the old method did before the new one takes over
+        let (x_offset, y_offset) = session.current_offset;
+        reset_display_safe(&session.display, self.inner.status.as_ref());
+        let applied = match (x_offset, y_offset) {
+            (0, 0) => Ok(()),
+            offset => backend::shift(method_idx, &session.display, offset, &Detail(self.inner.status.as_ref())),
+        };
+
+        if let Some(session) = self.inner.session.borrow_mut().as_mut() {
+            session.method_idx = method_idx;
+            if applied.is_err() {
+                session.current_offset = (0, 0);
+            }
+            state::save(&ShiftState::for_session(session));
+        }
+        if let Err(e) = applied {
+            let message = failure_message(&e, &session.display.name);
+            self.emit(SchedulerEvent::Error(message.clone()));
+            return Err(message);
+        }
+        self.emit(SchedulerEvent::Shifted);
+        Ok(session.method_idx)
+    }
+
+    /// Put the display back to zero offset without ending the session
+    pub fn reset_display(&self) -> Result<(), String> {
+        let display = match self.session() {
+            Some(session) => session.display,
+            None => self.preferred_display()?,
+        };
+
+        if !reset_display_safe(&display, self.inner.status.as_ref()) {
+            let message = format!("Reset failed on {}", display.name);
+            self.emit(SchedulerEvent::Error(message.clone()));
+            return Err(message);
+        }
+
+        match self.inner.session.borrow_mut().as_mut() {
+            Some(session) => session.current_offset = (0, 0),
+            None => state::clear(),
+        }
+        self.emit(SchedulerEvent::Shifted);
+        Ok(())
+    }
+
+    pub fn preferred_display(&self) -> Result<DisplayInfo, String> {
+        let displays = self.inner.displays.borrow();
+        preferred_display_index(&displays)
+            .map(|i| displays[i].clone())
+            .ok_or_else(|| "No connected displays".to_string())
+    }
+
+    /// Called before the machine sleeps: stop the timer so it cannot fire a
+    /// burst of ticks on wake, and restore the display
+    pub fn suspend(&self) {
+        self.inner.resume_after_wake.set(self.pause());
+        if let Some(ref session) = *self.inner.session.borrow() {
+            reset_display_safe(&session.display, self.inner.status.as_ref());
+            self.inner.status.log_safe(Level::Info, "Suspending: display restored.");
+        }
+    }
+
+    /// Called after wake: re-detect displays and reapply the offset once the
+    /// driver has finished restoring its own RandR state
+    pub fn wake(&self) {
+        let weak = Rc::downgrade(&self.inner);
+        glib::timeout_add_local_once(Duration::from_secs(2), move || {
+            let Some(scheduler) = Self::from_weak(&weak) else { return };
+            let resume = scheduler.inner.resume_after_wake.replace(false);
+            scheduler.refresh_displays();
+
+            if !resume || !scheduler.is_paused() {
+                return;
+            }
+            if scheduler.session_display_present() {
+                scheduler.resume();
+            } else if let Some(ref session) = *scheduler.inner.session.borrow() {
+                scheduler.inner.status.log_safe(Level::Warning, &format!(
+                    "{} not found after resume; auto-shift paused.",
+                    session.display.name
+                ));
+            }
+        });
+    }
+
+    /// Re-enumerate displays and follow the session's monitor: pause when it
+    /// disappears, and report when a paused one comes back
+    pub fn refresh_displays(&self) {
+        *self.inner.displays.borrow_mut() = get_connected_displays();
+        let running = self.is_running();
+        let present = self.session_display_present();
+        let name = self.inner.session.borrow().as_ref().map(|s| s.display.name.clone());
+
+        match name {
+            Some(name) if running && !present => {
+                self.pause();
+                self.inner.status.log_safe(Level::Warning, &format!("{} was disconnected; auto-shift paused.", name));
+            }
+            Some(name) if !running && present => {
+                self.inner.status.log_safe(Level::Info, &format!("{} reconnected. Press Resume to continue shifting.", name));
+            }
+            _ => {}
+        }
+
+        self.emit(SchedulerEvent::DisplaysChanged);
+    }
+
+    /// Point the session at the current entry for its monitor; false if it is gone
+    pub fn session_display_present(&self) -> bool {
+        let displays = self.inner.displays.borrow();
+        let mut session = self.inner.session.borrow_mut();
+        let Some(session) = session.as_mut() else { return false };
+        match displays.iter().find(|d| d.is_same_monitor(&session.display)) {
+            Some(display) => {
+                session.display = display.clone();
+                if let Some(ref activity) = *self.inner.activity.borrow() {
+                    activity.set_display(display.clone());
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn from_weak(weak: &Weak<Inner>) -> Option<Self> {
+        weak.upgrade().map(|inner| Self { inner })
+    }
+
+    fn arm(&self) {
+        let (delay, shifts) = self.next_wakeup();
+        let generation = self.inner.generation.get() + 1;
+        self.inner.generation.set(generation);
+        let weak = Rc::downgrade(&self.inner);
+
+        // One-shot so every wakeup can pick the interval the schedule wants next
+        let sid = glib::timeout_add_local_once(Duration::from_secs(delay), move || {
+            let Some(scheduler) = Self::from_weak(&weak) else { return };
+            scheduler.tick();
+            if scheduler.inner.generation.get() == generation {
+                scheduler.arm();
+            }
+        });
+        *self.inner.timer.borrow_mut() = Some(sid);
+
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        self.inner.next_shift.set(shifts.then_some(now + delay));
+    }
+
+    /// Seconds until the next tick, and whether that tick will shift: the
+    /// current interval, cut short when the schedule changes before then
+    fn next_wakeup(&self) -> (u64, bool) {
+        let session = self.inner.session.borrow();
+        let Some(ref session) = *session else { return (30, true) };
+        let now = self.inner.clock.now();
+        let week_time = WeekTime::from_date_time(&now);
+
+        let until_change = session
+            .schedule
+            .minutes_until_change(week_time, session.interval_secs)
+            .map(|minutes| (minutes as u64 * 60).saturating_sub(now.seconds() as u64).max(1));
+
+        match (session.interval_at(&now).map(|i| self.adapt(i)), until_change) {
+            (Some(interval), Some(change)) if change < interval => {
+                let then = now.add_seconds(change as f64).unwrap_or(now);
+                (change, session.interval_at(&then).is_some())
+            }
+            (Some(interval), _) => (interval, true),
+            (None, Some(change)) => (change, true),
+            // Off for good: look again in an hour in case the profile changes
+            (None, None) => (3600, false),
+        }
+    }
+
+    fn disarm(&self) -> bool {
+        self.inner.next_shift.set(None);
+        self.inner.generation.set(self.inner.generation.get() + 1);
+        match self.inner.timer.borrow_mut().take() {
+            Some(id) => {
+                id.remove();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn tick(&self) {
+        let now = self.inner.clock.now();
+        if self.session().is_some_and(|s| s.interval_at(&now).is_none()) {
+            self.idle(&now);
+            return;
+        }
+
+        if self.hardware_orbit_active() {
+            return;
+        }
+
+        let sampling_error = self.inner.activity.borrow().as_ref().and_then(|a| a.take_error());
+        if let Some(e) = sampling_error {
+            self.inner.status.log_safe(Level::Warning, &format!("Adaptive interval unavailable ({}); using the fixed interval.", e));
+        }
+
+        let Some(offset) = self.inner.session.borrow_mut().as_mut().map(|s| s.next_offset()) else { return };
+        self.shift_session(offset);
+    }
+
+    /// Move the session's display to `offset` and check it took, falling back
+    /// to another method when the session's does not work
+    fn shift_session(&self, offset: (i32, i32)) {
+        let (result, display_name) = {
+            let mut session = self.inner.session.borrow_mut();
+            let Some(session) = session.as_mut() else { return };
+
+            session.current_offset = offset;
+            // The outcome is reported as Shifted or Error, or by the fallback
+            let result = backend::shift(session.method_idx, &session.display, offset, &Detail(self.inner.status.as_ref()));
+            if result.is_ok() {
+                state::save(&ShiftState::for_session(session));
+            }
+            (result, session.display.name.clone())
+        };
+
+        match result {
+            Ok(()) => {
+                if self.inner.failures_recorded.take() {
+                    self.forget_failures();
+                }
+                self.emit(SchedulerEvent::Shifted)
+            }
+            Err(e) => {
+                if !self.fall_back(e.to_string()) {
+                    self.emit(SchedulerEvent::Error(failure_message(&e, &display_name)));
+                }
+            }
+        }
+    }
+
+    /// Scheduled off: leave the panel unshifted until the schedule turns back on
+    fn idle(&self, now: &glib::DateTime) {
+        let reset = {
+            let mut session = self.inner.session.borrow_mut();
+            let Some(session) = session.as_mut() else { return };
+
+            let reset = session.current_offset != (0, 0) && reset_display_safe(&session.display, self.inner.status.as_ref());
+            if reset {
+                session.current_offset = (0, 0);
+                state::save(&ShiftState::for_session(session));
+            }
+
+            let resumes = session
+                .schedule
+                .timeline(now, session.interval_secs, 2)
+                .get(1)
+                .and_then(|(t, _)| t.format("%a %H:%M").ok())
+                .map(|t| format!(" until {}", t))
+                .unwrap_or_default();
+            self.inner.status.log_safe(Level::Info, &format!("Schedule: shifting off{}.", resumes));
+            reset
+        };
+
+        if reset {
+            self.emit(SchedulerEvent::Shifted);
+        }
+    }
+}
+
+/// What went wrong, for `SchedulerEvent::Error` and callers of the shift methods
+fn failure_message(error: &ShiftError, display_name: &str) -> String {
+    match error {
+        ShiftError::Refused(_) => format!("Shift failed on {}", display_name),
+        ShiftError::Ineffective(_) => format!("Shift on {} was accepted but ineffective", display_name),
+    }
+}
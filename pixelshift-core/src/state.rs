@@ -0,0 +1,95 @@
+//! Runtime record of the shift currently applied to a display, kept in
+//! `$XDG_RUNTIME_DIR/pixelshift-gtk/state` so `status` and `recover` can find
+//! it from another process or after a crash.
+//!
+//! A dry run leaves the record alone: nothing it logs was applied, and a real
+//! session's record must survive it.
+
+use glib::{KeyFile, KeyFileFlags};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::dryrun;
+use crate::profiles::METHOD_KEYS;
+use crate::scheduler::AutoShiftSession;
+
+const GROUP: &str = "shift";
+
+#[derive(Debug, Clone)]
+pub struct ShiftState {
+    /// Process driving the shift; `None` for one-off shifts from the CLI
+    pub pid: Option<u32>,
+    pub display: String,
+    pub method: String,
+    pub offset: (i32, i32),
+    /// Unix time of the last successful apply
+    pub updated: u64,
+}
+
+impl ShiftState {
+    pub fn new(pid: Option<u32>, display: &str, method_idx: u32, offset: (i32, i32)) -> Self {
+        Self {
+            pid,
+            display: display.to_string(),
+            method: METHOD_KEYS.get(method_idx as usize).unwrap_or(&METHOD_KEYS[0]).to_string(),
+            offset,
+            updated: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        }
+    }
+
+    pub fn for_session(session: &AutoShiftSession) -> Self {
+        Self::new(Some(std::process::id()), &session.display.name, session.method_idx, session.current_offset)
+    }
+
+    /// Whether the owning process is still alive
+    pub fn owner_running(&self) -> bool {
+        self.pid.is_some_and(|pid| PathBuf::from(format!("/proc/{}", pid)).exists())
+    }
+}
+
+pub fn state_path() -> PathBuf {
+    glib::user_runtime_dir().join("pixelshift-gtk").join("state")
+}
+
+pub fn load() -> Option<ShiftState> {
+    let key_file = KeyFile::new();
+    key_file.load_from_file(state_path(), KeyFileFlags::NONE).ok()?;
+
+    Some(ShiftState {
+        pid: key_file.integer(GROUP, "pid").ok().filter(|p| *p > 0).map(|p| p as u32),
+        display: key_file.string(GROUP, "display").ok()?.to_string(),
+        method: key_file.string(GROUP, "method").map(|m| m.to_string()).unwrap_or_default(),
+        offset: (
+            key_file.integer(GROUP, "x").unwrap_or(0),
+            key_file.integer(GROUP, "y").unwrap_or(0),
+        ),
+        updated: key_file.uint64(GROUP, "updated").unwrap_or(0),
+    })
+}
+
+pub fn save(state: &ShiftState) {
+    if dryrun::is_enabled() {
+        return;
+    }
+    let key_file = KeyFile::new();
+    key_file.set_integer(GROUP, "pid", state.pid.map(|p| p as i32).unwrap_or(0));
+    key_file.set_string(GROUP, "display", &state.display);
+    key_file.set_string(GROUP, "method", &state.method);
+    key_file.set_integer(GROUP, "x", state.offset.0);
+    key_file.set_integer(GROUP, "y", state.offset.1);
+    key_file.set_uint64(GROUP, "updated", state.updated);
+
+    let path = state_path();
+    if let Some(dir) = path.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    // Best effort: losing the record only degrades `status`/`recover`
+    let _ = key_file.save_to_file(&path);
+}
+
+pub fn clear() {
+    if dryrun::is_enabled() {
+        return;
+    }
+    let _ = std::fs::remove_file(state_path());
+}
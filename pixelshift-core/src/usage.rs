@@ -0,0 +1,269 @@
+//! Panel usage accounting per monitor identity: powered-on time, time with
+//! auto-shift active, and time at each brightness level.
+//!
+//! Sampled once a minute while the app or `run` is active, by only one of
+//! them when both are (see `lock`), and kept in
+//! `$XDG_DATA_HOME/pixelshift-gtk/usage.ini`:
+//!
+//! ```ini
+//! [monitor DEL-A0B1-12345]
+//! name=DELL AW3423DW
+//! on=123456.0
+//! shifting=120000.0
+//! brightness=0;0;0;0;0;0;0;0;3600;0;119856
+//! first-seen=1760000000
+//! last-seen=1760123456
+//! ```
+//!
+//! Times are seconds. `brightness` has one bucket per 10% step (0%, 10%, …,
+//! 100%) of the output's RandR brightness.
+
+use glib::source::SourceId;
+use glib::{ControlFlow, KeyFile, KeyFileFlags};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Command;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::brightness;
+use crate::lock::RecorderLock;
+use crate::scheduler::Scheduler;
+
+pub const SAMPLE_SECS: u64 = 60;
+pub const BRIGHTNESS_BUCKETS: usize = 11;
+const GROUP_PREFIX: &str = "monitor ";
+
+#[derive(Debug, Clone, Default)]
+pub struct UsageRecord {
+    pub name: String,
+    pub on_secs: f64,
+    pub shifting_secs: f64,
+    pub brightness_secs: [f64; BRIGHTNESS_BUCKETS],
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+impl UsageRecord {
+    /// Share of powered-on time with shifting active
+    pub fn coverage(&self) -> f64 {
+        if self.on_secs <= 0.0 {
+            0.0
+        } else {
+            (self.shifting_secs / self.on_secs).clamp(0.0, 1.0)
+        }
+    }
+
+    /// Time-weighted average brightness, 0.0-1.0
+    pub fn mean_brightness(&self) -> Option<f64> {
+        let total: f64 = self.brightness_secs.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let weighted: f64 = self.brightness_secs.iter().enumerate().map(|(i, s)| i as f64 / 10.0 * s).sum();
+        Some(weighted / total)
+    }
+}
+
+pub struct UsageStore {
+    path: PathBuf,
+    pub records: HashMap<String, UsageRecord>,
+}
+
+impl UsageStore {
+    pub fn default_path() -> PathBuf {
+        glib::user_data_dir().join("pixelshift-gtk").join("usage.ini")
+    }
+
+    pub fn load() -> Self {
+        let path = Self::default_path();
+        let key_file = KeyFile::new();
+        let mut records = HashMap::new();
+
+        if key_file.load_from_file(&path, KeyFileFlags::NONE).is_ok() {
+            for group in key_file.groups().iter() {
+                let Some(id) = group.strip_prefix(GROUP_PREFIX) else { continue };
+                let mut brightness_secs = [0.0; BRIGHTNESS_BUCKETS];
+                if let Ok(list) = key_file.double_list(group, "brightness") {
+                    for (bucket, secs) in brightness_secs.iter_mut().zip(list) {
+                        *bucket = secs;
+                    }
+                }
+                records.insert(
+                    id.to_string(),
+                    UsageRecord {
+                        name: key_file.string(group, "name").map(|s| s.to_string()).unwrap_or_default(),
+                        on_secs: key_file.double(group, "on").unwrap_or(0.0),
+                        shifting_secs: key_file.double(group, "shifting").unwrap_or(0.0),
+                        brightness_secs,
+                        first_seen: key_file.uint64(group, "first-seen").unwrap_or(0),
+                        last_seen: key_file.uint64(group, "last-seen").unwrap_or(0),
+                    },
+                );
+            }
+        }
+
+        Self { path, records }
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let key_file = KeyFile::new();
+        let mut ids: Vec<&String> = self.records.keys().collect();
+        ids.sort();
+        for id in ids {
+            let record = &self.records[id];
+            let group = format!("{}{}", GROUP_PREFIX, id);
+            key_file.set_string(&group, "name", &record.name);
+            key_file.set_double(&group, "on", record.on_secs.round());
+            key_file.set_double(&group, "shifting", record.shifting_secs.round());
+            key_file.set_string(
+                &group,
+                "brightness",
+                &record.brightness_secs.iter().map(|s| format!("{:.0}", s)).collect::<Vec<_>>().join(";"),
+            );
+            key_file.set_uint64(&group, "first-seen", record.first_seen);
+            key_file.set_uint64(&group, "last-seen", record.last_seen);
+        }
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                glib::Error::new(glib::FileError::Failed, &format!("Cannot create {}: {}", dir.display(), e))
+            })?;
+        }
+        key_file.save_to_file(&self.path)
+    }
+
+    /// Sorted by powered-on time, most used first
+    pub fn sorted(&self) -> Vec<(&String, &UsageRecord)> {
+        let mut records: Vec<_> = self.records.iter().collect();
+        records.sort_by(|a, b| b.1.on_secs.total_cmp(&a.1.on_secs));
+        records
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("monitor,name,on_hours,shifting_hours,coverage,mean_brightness");
+        for i in 0..BRIGHTNESS_BUCKETS {
+            csv.push_str(&format!(",hours_at_{}pct", i * 10));
+        }
+        csv.push_str(",first_seen,last_seen\n");
+
+        for (id, record) in self.sorted() {
+            csv.push_str(&format!(
+                "{},\"{}\",{:.2},{:.2},{:.3},{}",
+                id,
+                record.name.replace('"', "\"\""),
+                record.on_secs / 3600.0,
+                record.shifting_secs / 3600.0,
+                record.coverage(),
+                record.mean_brightness().map(|b| format!("{:.2}", b)).unwrap_or_default(),
+            ));
+            for secs in &record.brightness_secs {
+                csv.push_str(&format!(",{:.2}", secs / 3600.0));
+            }
+            csv.push_str(&format!(",{},{}\n", record.first_seen, record.last_seen));
+        }
+        csv
+    }
+}
+
+/// Hours with one decimal, e.g. "12.3 h"
+pub fn format_hours(secs: f64) -> String {
+    format!("{:.1} h", secs / 3600.0)
+}
+
+/// Whether DPMS has the monitors powered on; true when it cannot be queried
+fn monitors_powered_on() -> bool {
+    match Command::new("xset").arg("q").output() {
+        Ok(o) => {
+            let text = String::from_utf8_lossy(&o.stdout);
+            !["Monitor is Off", "Monitor is in Standby", "Monitor is in Suspend"].iter().any(|s| text.contains(s))
+        }
+        Err(_) => true,
+    }
+}
+
+struct Inner {
+    scheduler: Scheduler,
+    lock: RecorderLock,
+    last_sample: Cell<Instant>,
+    timer: RefCell<Option<SourceId>>,
+}
+
+/// Adds a sample to the store every minute for each connected monitor
+pub struct UsageTracker {
+    inner: Rc<Inner>,
+}
+
+impl UsageTracker {
+    pub fn start(scheduler: &Scheduler) -> Self {
+        let inner = Rc::new(Inner {
+            scheduler: scheduler.clone(),
+            lock: RecorderLock::new("usage"),
+            last_sample: Cell::new(Instant::now()),
+            timer: RefCell::new(None),
+        });
+        // Claimed up front so the first process to start is the one recording
+        inner.lock.acquire();
+
+        let weak = Rc::downgrade(&inner);
+        let sid = glib::timeout_add_local(Duration::from_secs(SAMPLE_SECS), move || match weak.upgrade() {
+            Some(inner) => {
+                sample(&inner);
+                ControlFlow::Continue
+            }
+            None => ControlFlow::Break,
+        });
+        *inner.timer.borrow_mut() = Some(sid);
+
+        Self { inner }
+    }
+}
+
+impl Drop for UsageTracker {
+    fn drop(&mut self) {
+        // Count the partial minute before going away
+        sample(&self.inner);
+        if let Some(id) = self.inner.timer.borrow_mut().take() {
+            id.remove();
+        }
+    }
+}
+
+fn sample(inner: &Inner) {
+    let now = Instant::now();
+    // A suspended machine does not run timers; don't count the gap
+    let elapsed = (now - inner.last_sample.replace(now)).as_secs_f64().min(2.0 * SAMPLE_SECS as f64);
+    if elapsed < 1.0 || !inner.lock.acquire() || !monitors_powered_on() {
+        return;
+    }
+
+    let unix_now = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let brightness = brightness::randr_levels();
+    let shifting = inner
+        .scheduler
+        .session()
+        .filter(|_| inner.scheduler.is_running())
+        .map(|s| s.display.monitor_id());
+
+    let mut store = UsageStore::load();
+    for display in inner.scheduler.displays() {
+        let id = display.monitor_id();
+        let record = store.records.entry(id.clone()).or_default();
+        record.name = display.edid.as_ref().map(|e| e.display_name()).unwrap_or_else(|| display.name.clone());
+        if record.first_seen == 0 {
+            record.first_seen = unix_now;
+        }
+        record.last_seen = unix_now;
+        record.on_secs += elapsed;
+        if shifting.as_deref() == Some(id.as_str()) {
+            record.shifting_secs += elapsed;
+        }
+        let level = brightness.get(&display.name).copied().unwrap_or(1.0).clamp(0.0, 1.0);
+        record.brightness_secs[(level * 10.0).round() as usize] += elapsed;
+    }
+
+    if let Err(e) = store.save() {
+        inner.scheduler.status().set_text_safe(&format!("✗ Could not save usage statistics: {}", e));
+    }
+}
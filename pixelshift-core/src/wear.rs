@@ -0,0 +1,356 @@
+//! Per-monitor wear heatmap: how much light each region of the panel has
+//! emitted while its content stayed unchanged, accumulated over days.
+//!
+//! Recording is opt-in per monitor (`wear=true` in its profile). Maps live in
+//! `$XDG_DATA_HOME/pixelshift-gtk/wear/<monitor id>.ini` as GLib key files:
+//!
+//! ```ini
+//! [wear]
+//! version=1
+//! cols=64
+//! rows=36
+//! sampled=86400.0
+//! exposure=0.5;0.5;...
+//! static=0.1;0.4;...
+//! preview=0.2;0.2;...
+//! ```
+//!
+//! `exposure` is Σ luminance × seconds per cell (row by row), `static` the same
+//! counting only samples where the cell had not changed since the previous
+//! one, and `preview` the most recent luminance grid.
+//!
+//! Only one of the app and `run` records at a time (see `lock`). Maps are
+//! written every ten samples and when recording stops, so a heatmap opened
+//! meanwhile can be up to ten minutes behind.
+
+use glib::source::SourceId;
+use glib::{ControlFlow, KeyFile, KeyFileFlags};
+use gdk_pixbuf::{Colorspace, InterpType, Pixbuf};
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::activity::STILL_THRESHOLD;
+use crate::framebuffer::{self, Grid};
+use crate::lock::RecorderLock;
+use crate::profiles::ProfileStore;
+use crate::scheduler::Scheduler;
+use crate::DisplayInfo;
+
+pub const GRID_COLS: usize = 64;
+pub const GRID_ROWS: usize = 36;
+pub const SAMPLE_SECS: u64 = 60;
+/// Samples between writes of the maps
+const SAVE_EVERY: u32 = 10;
+const GROUP: &str = "wear";
+const VERSION: i32 = 1;
+
+pub struct WearMap {
+    monitor_id: String,
+    pub cols: usize,
+    pub rows: usize,
+    /// Seconds covered by samples
+    pub sampled_secs: f64,
+    pub exposure: Vec<f64>,
+    pub static_exposure: Vec<f64>,
+    pub preview: Vec<f64>,
+    /// Unix time of the last sample
+    pub updated: u64,
+}
+
+impl WearMap {
+    pub fn new(monitor_id: &str) -> Self {
+        let cells = GRID_COLS * GRID_ROWS;
+        Self {
+            monitor_id: monitor_id.to_string(),
+            cols: GRID_COLS,
+            rows: GRID_ROWS,
+            sampled_secs: 0.0,
+            exposure: vec![0.0; cells],
+            static_exposure: vec![0.0; cells],
+            preview: vec![0.0; cells],
+            updated: 0,
+        }
+    }
+
+    pub fn path(monitor_id: &str) -> PathBuf {
+        // Monitor ids are MFG-PRODUCT-SERIAL or connector names; keep them filename-safe anyway
+        let file: String = monitor_id
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+            .collect();
+        glib::user_data_dir().join("pixelshift-gtk").join("wear").join(format!("{}.ini", file))
+    }
+
+    /// The stored map, or an empty one if there is none or it does not fit the grid
+    pub fn load(monitor_id: &str) -> Self {
+        let key_file = KeyFile::new();
+        let mut map = Self::new(monitor_id);
+        if key_file.load_from_file(Self::path(monitor_id), KeyFileFlags::NONE).is_err() {
+            return map;
+        }
+
+        let cells = map.cols * map.rows;
+        let list = |key: &str| key_file.double_list(GROUP, key).ok().filter(|l| l.len() == cells);
+        let same_grid = key_file.integer(GROUP, "cols").ok() == Some(map.cols as i32)
+            && key_file.integer(GROUP, "rows").ok() == Some(map.rows as i32);
+        if let (true, Some(exposure), Some(static_exposure)) = (same_grid, list("exposure"), list("static")) {
+            map.exposure = exposure;
+            map.static_exposure = static_exposure;
+            map.preview = list("preview").unwrap_or(map.preview);
+            map.sampled_secs = key_file.double(GROUP, "sampled").unwrap_or(0.0);
+            map.updated = key_file.uint64(GROUP, "updated").unwrap_or(0);
+        }
+        map
+    }
+
+    pub fn save(&self) -> Result<(), glib::Error> {
+        let join = |values: &[f64]| values.iter().map(|v| format!("{:.1}", v)).collect::<Vec<_>>().join(";");
+
+        let key_file = KeyFile::new();
+        key_file.set_integer(GROUP, "version", VERSION);
+        key_file.set_integer(GROUP, "cols", self.cols as i32);
+        key_file.set_integer(GROUP, "rows", self.rows as i32);
+        key_file.set_double(GROUP, "sampled", self.sampled_secs);
+        key_file.set_uint64(GROUP, "updated", self.updated);
+        key_file.set_string(GROUP, "exposure", &join(&self.exposure));
+        key_file.set_string(GROUP, "static", &join(&self.static_exposure));
+        key_file.set_string(
+            GROUP,
+            "preview",
+            &self.preview.iter().map(|v| format!("{:.3}", v)).collect::<Vec<_>>().join(";"),
+        );
+
+        let path = Self::path(&self.monitor_id);
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                glib::Error::new(glib::FileError::Failed, &format!("Cannot create {}: {}", dir.display(), e))
+            })?;
+        }
+        key_file.save_to_file(&path)
+    }
+
+    /// Add one sample; `previous` is the grid sampled before it, if any
+    pub fn record(&mut self, grid: &Grid, previous: Option<&Grid>, elapsed_secs: f64) {
+        for (i, luma) in grid.cells.iter().enumerate().take(self.exposure.len()) {
+            let light = *luma as f64 * elapsed_secs;
+            self.exposure[i] += light;
+            if previous.is_some_and(|p| p.cells.get(i).is_some_and(|old| (old - luma).abs() <= STILL_THRESHOLD)) {
+                self.static_exposure[i] += light;
+            }
+            self.preview[i] = *luma as f64;
+        }
+        self.sampled_secs += elapsed_secs;
+        self.updated = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    }
+
+    /// Static exposure as a share of the time sampled: 1.0 is a cell that
+    /// showed unchanging full white the whole time
+    pub fn risk(&self) -> Vec<f64> {
+        if self.sampled_secs <= 0.0 {
+            return vec![0.0; self.static_exposure.len()];
+        }
+        self.static_exposure.iter().map(|e| (e / self.sampled_secs).clamp(0.0, 1.0)).collect()
+    }
+
+    /// Cell with the highest risk as (col, row, risk)
+    pub fn hottest(&self) -> Option<(usize, usize, f64)> {
+        self.risk()
+            .into_iter()
+            .enumerate()
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .filter(|(_, risk)| *risk > 0.0)
+            .map(|(i, risk)| (i % self.cols, i / self.cols, risk))
+    }
+
+    /// Heatmap over a dimmed copy of the last sampled content, `width` × `height` pixels
+    pub fn render(&self, width: i32, height: i32) -> Option<Pixbuf> {
+        let risk = self.risk();
+        let max = risk.iter().cloned().fold(0.0, f64::max).max(f64::EPSILON);
+
+        let mut data = Vec::with_capacity(self.cols * self.rows * 3);
+        for (preview, risk) in self.preview.iter().zip(&risk) {
+            // Relative to the worst cell so the pattern shows even early on
+            let t = (risk / max).sqrt();
+            let base = preview * 0.5;
+            let (r, g, b) = heat_color(t);
+            let alpha = 0.15 + 0.85 * t;
+            for channel in [r, g, b] {
+                data.push(((base * (1.0 - alpha) + channel * alpha) * 255.0).round() as u8);
+            }
+        }
+
+        let cells = Pixbuf::from_mut_slice(data, Colorspace::Rgb, false, 8, self.cols as i32, self.rows as i32, self.cols as i32 * 3);
+        cells.scale_simple(width.max(1), height.max(1), InterpType::Bilinear)
+    }
+
+    /// One line per cell with its position on `display`
+    pub fn to_csv(&self, display: &DisplayInfo) -> String {
+        let risk = self.risk();
+        let mut csv = String::from("row,col,x,y,width,height,exposure_hours,static_hours,risk\n");
+        for row in 0..self.rows {
+            for col in 0..self.cols {
+                let i = row * self.cols + col;
+                let x = col as u32 * display.width / self.cols as u32;
+                let y = row as u32 * display.height / self.rows as u32;
+                let w = (col as u32 + 1) * display.width / self.cols as u32 - x;
+                let h = (row as u32 + 1) * display.height / self.rows as u32 - y;
+                csv.push_str(&format!(
+                    "{},{},{},{},{},{},{:.3},{:.3},{:.4}\n",
+                    row,
+                    col,
+                    display.x + x as i32,
+                    display.y + y as i32,
+                    w,
+                    h,
+                    self.exposure[i] / 3600.0,
+                    self.static_exposure[i] / 3600.0,
+                    risk[i],
+                ));
+            }
+        }
+        csv
+    }
+
+    pub fn export_png(&self, display: &DisplayInfo, path: &std::path::Path) -> Result<(), String> {
+        // Keep the panel's aspect ratio, 960 pixels wide
+        let height = (960.0 * display.height.max(1) as f64 / display.width.max(1) as f64).round() as i32;
+        let pixbuf = self.render(960, height).ok_or("could not render heatmap")?;
+        pixbuf.savev(path, "png", &[]).map_err(|e| e.to_string())
+    }
+}
+
+/// Black → blue → red → yellow
+fn heat_color(t: f64) -> (f64, f64, f64) {
+    let t = t.clamp(0.0, 1.0);
+    if t < 0.33 {
+        (0.0, 0.0, t / 0.33)
+    } else if t < 0.66 {
+        let u = (t - 0.33) / 0.33;
+        (u, 0.0, 1.0 - u)
+    } else {
+        (1.0, (t - 0.66) / 0.34, 0.0)
+    }
+}
+
+struct Inner {
+    scheduler: Scheduler,
+    lock: RecorderLock,
+    maps: RefCell<HashMap<String, WearMap>>,
+    previous: RefCell<HashMap<String, (Grid, Instant)>>,
+    /// Samples recorded since the maps were last written
+    unsaved: Cell<u32>,
+    /// Whether the last capture failed, so a lasting failure is reported once
+    failing: Cell<bool>,
+    timer: RefCell<Option<SourceId>>,
+}
+
+/// Samples every monitor with wear recording enabled once a minute
+pub struct WearRecorder {
+    inner: Rc<Inner>,
+}
+
+impl WearRecorder {
+    pub fn start(scheduler: &Scheduler) -> Self {
+        let inner = Rc::new(Inner {
+            scheduler: scheduler.clone(),
+            lock: RecorderLock::new("wear"),
+            maps: RefCell::new(HashMap::new()),
+            previous: RefCell::new(HashMap::new()),
+            unsaved: Cell::new(0),
+            failing: Cell::new(false),
+            timer: RefCell::new(None),
+        });
+        // Claimed up front so the first process to start is the one recording
+        inner.lock.acquire();
+
+        let weak = Rc::downgrade(&inner);
+        let sid = glib::timeout_add_local(Duration::from_secs(SAMPLE_SECS), move || match weak.upgrade() {
+            Some(inner) => {
+                sample(&inner);
+                ControlFlow::Continue
+            }
+            None => ControlFlow::Break,
+        });
+        *inner.timer.borrow_mut() = Some(sid);
+
+        Self { inner }
+    }
+}
+
+impl Drop for WearRecorder {
+    fn drop(&mut self) {
+        if let Some(id) = self.inner.timer.borrow_mut().take() {
+            id.remove();
+        }
+        save(&self.inner);
+    }
+}
+
+/// Write the maps with samples not on disk yet
+fn save(inner: &Inner) {
+    if inner.unsaved.replace(0) == 0 {
+        return;
+    }
+    for (id, map) in inner.maps.borrow().iter() {
+        if let Err(e) = map.save() {
+            inner.scheduler.status().set_text_safe(&format!("✗ Could not save wear map for {}: {}", id, e));
+        }
+    }
+}
+
+fn sample(inner: &Rc<Inner>) {
+    // Re-read profiles so toggling recording in another window takes effect
+    let profiles = ProfileStore::load();
+    let displays: Vec<DisplayInfo> = inner.scheduler.displays()
+        .into_iter()
+        .filter(|d| profiles.get(&d.monitor_id()).is_some_and(|p| p.wear))
+        .collect();
+    if displays.is_empty() || !inner.lock.acquire() {
+        inner.previous.borrow_mut().clear();
+        return;
+    }
+
+    let weak = Rc::downgrade(inner);
+    framebuffer::capture(move |result| {
+        let Some(inner) = weak.upgrade() else { return };
+        let frame = match result {
+            Ok(frame) => frame,
+            Err(e) => {
+                if !inner.failing.replace(true) {
+                    inner.scheduler.status().set_text_safe(&format!("✗ Wear sampling failed: {}", e));
+                }
+                return;
+            }
+        };
+        inner.failing.set(false);
+
+        let now = frame.captured;
+        let mut maps = inner.maps.borrow_mut();
+        let mut previous = inner.previous.borrow_mut();
+        for display in &displays {
+            let id = display.monitor_id();
+            let last = previous.get(&id);
+            if last.is_some_and(|(_, t)| *t == now) {
+                continue;
+            }
+            let grid = frame.grid(display, GRID_COLS, GRID_ROWS);
+            // Gaps (suspend, recording switched off) count as one interval, not their length
+            let elapsed = last
+                .map(|(_, t)| now.saturating_duration_since(*t).as_secs_f64().min(2.0 * SAMPLE_SECS as f64))
+                .unwrap_or(SAMPLE_SECS as f64);
+
+            let map = maps.entry(id.clone()).or_insert_with(|| WearMap::load(&id));
+            map.record(&grid, last.map(|(g, _)| g), elapsed);
+            previous.insert(id, (grid, now));
+        }
+        drop((maps, previous));
+
+        inner.unsaved.set(inner.unsaved.get() + 1);
+        if inner.unsaved.get() >= SAVE_EVERY {
+            save(&inner);
+        }
+    });
+}
@@ -0,0 +1,100 @@
+//! The methods cache: probe results and runtime failures kept apart, and
+//! runtime failures only counting once they repeat.
+
+use pixelshift_core::capabilities::{Capabilities, CapabilityStore, Support, RUNTIME_FAILURE_LIMIT, RUNTIME_FAILURE_TTL};
+use pixelshift_core::DisplayInfo;
+use std::path::Path;
+use std::sync::{Mutex, MutexGuard, Once};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// glib reads the cache directory once per process, so every test shares
+/// one file: each keeps to its own connector name and holds the lock while
+/// it loads and saves
+fn setup() -> MutexGuard<'static, ()> {
+    static ONCE: Once = Once::new();
+    static LOCK: Mutex<()> = Mutex::new(());
+    ONCE.call_once(|| {
+        let home = Path::new(env!("CARGO_TARGET_TMPDIR")).join("capabilities");
+        let _ = std::fs::remove_dir_all(&home);
+        std::env::set_var("XDG_CACHE_HOME", home.join("cache"));
+    });
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn display(name: &str) -> DisplayInfo {
+    DisplayInfo { name: name.to_string(), width: 2560, height: 1440, x: 0, y: 0, refresh_rate: 60.0, is_primary: true, edid: None, twin: false }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+}
+
+#[test]
+fn one_runtime_failure_does_not_rule_a_method_out() {
+    let _lock = setup();
+    let display = display("HDMI-1");
+    let mut store = CapabilityStore::load();
+    store.record_failure(&display, 0, "xrandr refused it");
+    store.save().unwrap();
+
+    let store = CapabilityStore::load();
+    let capabilities = store.get("HDMI-1").unwrap();
+    assert!(capabilities.supports(0));
+    assert_eq!(capabilities.fallback(3), Some(0));
+    assert_eq!(capabilities.methods[0], Support::Unknown, "runtime failures stay out of the probe results");
+    assert_eq!(capabilities.runtime[0].as_ref().map(|f| f.count), Some(1));
+}
+
+#[test]
+fn repeated_runtime_failures_rule_a_method_out_until_it_works() {
+    let _lock = setup();
+    let display = display("DP-1");
+    let mut store = CapabilityStore::load();
+    for _ in 0..RUNTIME_FAILURE_LIMIT {
+        store.record_failure(&display, 1, "accepted but ineffective");
+    }
+    store.save().unwrap();
+
+    let mut store = CapabilityStore::load();
+    let capabilities = store.get("DP-1").unwrap();
+    assert!(!capabilities.supports(1));
+    assert_eq!(capabilities.reason(1), Some("accepted but ineffective"));
+    assert_eq!(capabilities.fallback(0), Some(2));
+    assert!(capabilities.summary()[1].contains("unsupported"), "{:?}", capabilities.summary());
+
+    assert!(store.record_success(&display, 1));
+    assert!(store.get("DP-1").unwrap().supports(1));
+    assert!(!store.record_success(&display, 1), "nothing left to forget");
+}
+
+#[test]
+fn runtime_failures_expire() {
+    let _lock = setup();
+    let path = CapabilityStore::default_path();
+    std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let mut text = std::fs::read_to_string(&path).unwrap_or_default();
+    let stale = now() - RUNTIME_FAILURE_TTL - 1;
+    text.push_str(&format!("\n[monitor DP-3]\nname=DP-3\nprobed=0\nruntime-transform={};{};xrandr refused it\n", RUNTIME_FAILURE_LIMIT, stale));
+    std::fs::write(&path, text).unwrap();
+
+    let mut store = CapabilityStore::load();
+    assert!(store.get("DP-3").unwrap().supports(0), "stale failures no longer count");
+    // The next failure starts counting again instead of adding to them
+    store.record_failure(&display("DP-3"), 0, "xrandr refused it");
+    assert_eq!(store.get("DP-3").unwrap().runtime[0].as_ref().map(|f| f.count), Some(1));
+}
+
+#[test]
+fn probe_results_rule_a_method_out_at_once() {
+    let _lock = setup();
+    let mut store = CapabilityStore::load();
+    let mut capabilities = Capabilities { name: "DP-4".to_string(), probed: now(), ..Default::default() };
+    capabilities.methods = [Support::Fails("BadMatch".to_string()), Support::Works, Support::Works, Support::Works];
+    store.insert("DP-4", capabilities);
+    store.record_failure(&display("DP-4"), 2, "xrandr refused it");
+
+    let capabilities = store.get("DP-4").unwrap();
+    assert!(!capabilities.supports(0));
+    assert!(capabilities.supports(2));
+    assert_eq!(capabilities.fallbacks(3).collect::<Vec<_>>(), vec![1, 2]);
+}
@@ -0,0 +1,101 @@
+//! EDID decoding and monitor identity over a small corpus of real-shaped blobs.
+
+use pixelshift_core::display::{tell_twins_apart, DisplayInfo};
+use pixelshift_core::edid::{parse_edid, EdidInfo};
+
+fn fixture(name: &str) -> Vec<u8> {
+    let path = format!("{}/tests/fixtures/edid/{}.bin", env!("CARGO_MANIFEST_DIR"), name);
+    std::fs::read(&path).unwrap_or_else(|e| panic!("{}: {}", path, e))
+}
+
+fn parse(name: &str) -> EdidInfo {
+    parse_edid(&fixture(name)).unwrap_or_else(|| panic!("{} did not parse", name))
+}
+
+fn on(connector: &str, edid: &EdidInfo) -> DisplayInfo {
+    DisplayInfo {
+        name: connector.to_string(),
+        width: 3840,
+        height: 2160,
+        x: 0,
+        y: 0,
+        refresh_rate: 60.0,
+        is_primary: false,
+        edid: Some(edid.clone()),
+        twin: false,
+    }
+}
+
+#[test]
+fn serial_string_descriptor_wins_over_the_number() {
+    let edid = parse("serial_string");
+    assert_eq!(edid.manufacturer, "DEL");
+    assert_eq!(edid.product_code, 0xA0B1);
+    assert_eq!(edid.serial_number, 0x01020304);
+    assert_eq!(edid.serial_string.as_deref(), Some("ABC123"));
+    assert_eq!(edid.model_name.as_deref(), Some("DELL U2720Q"));
+    assert_eq!((edid.width_mm, edid.height_mm, edid.manufacture_year), (597, 336, Some(2020)));
+    assert_eq!(edid.identity(), "DEL-A0B1-ABC123");
+}
+
+#[test]
+fn numeric_serial_is_used_without_a_descriptor() {
+    let edid = parse("serial_number");
+    assert_eq!(edid.serial_string, None);
+    assert_eq!(edid.identity(), "GSM-5BBF-16843009");
+    assert_eq!(edid.display_name(), "LG TV SSCR2");
+    assert!(edid.has_serial());
+}
+
+#[test]
+fn serial_zero_names_only_the_model() {
+    let edid = parse("serial_zero");
+    assert_eq!(edid.identity(), "LGD-0617");
+    assert!(!edid.has_serial());
+    assert!(edid.is_likely_oled());
+}
+
+#[test]
+fn missing_name_falls_back_to_vendor_and_product() {
+    let edid = parse("no_name");
+    assert_eq!(edid.model_name, None);
+    assert_eq!(edid.panel_text.as_deref(), Some("ATNA40YK04-0"));
+    assert_eq!(edid.display_name(), "SDC 4141");
+    assert_eq!(edid.oled_hint(), Some("Samsung Display OLED panel part number"));
+}
+
+#[test]
+fn bad_checksum_is_rejected() {
+    assert!(parse_edid(&fixture("bad_checksum")).is_none());
+    assert!(parse_edid(&fixture("serial_string")[..100]).is_none());
+}
+
+#[test]
+fn serial_less_twins_are_told_apart_by_connector() {
+    let twin = parse("serial_zero");
+    let mut displays = [on("HDMI-1", &twin), on("DP-1", &twin), on("DP-2", &parse("serial_string"))];
+    tell_twins_apart(&mut displays);
+    let [left, right, dell] = &displays;
+    assert_eq!(left.monitor_id(), "LGD-0617@HDMI-1");
+    assert_eq!(right.monitor_id(), "LGD-0617@DP-1");
+    assert!(!left.is_same_monitor(right));
+    assert_eq!(dell.monitor_id(), "DEL-A0B1-ABC123");
+
+    // A serial follows the monitor from one connector to another
+    let dell = parse("serial_string");
+    assert_eq!(on("HDMI-1", &dell).monitor_id(), "DEL-A0B1-ABC123");
+    assert!(on("HDMI-1", &dell).is_same_monitor(&on("DP-2", &dell)));
+}
+
+#[test]
+fn a_lone_serial_less_monitor_keeps_its_id_across_connectors() {
+    let panel = parse("serial_zero");
+    let mut docked = [on("HDMI-1", &panel), on("DP-2", &parse("serial_string"))];
+    tell_twins_apart(&mut docked);
+    let mut redocked = [on("DP-1", &panel)];
+    tell_twins_apart(&mut redocked);
+
+    assert_eq!(docked[0].monitor_id(), "LGD-0617");
+    assert_eq!(redocked[0].monitor_id(), "LGD-0617");
+    assert!(docked[0].is_same_monitor(&redocked[0]));
+}
@@ -0,0 +1,113 @@
+//! Frame decoding, per-display grids and stillness over synthetic frames fed
+//! through `PIXELSHIFT_FRAME_SOURCE`.
+
+use pixelshift_core::activity::{adaptive_interval, ActivityTracker, GRID_COLS, GRID_ROWS};
+use pixelshift_core::framebuffer::{self, Frame};
+use pixelshift_core::DisplayInfo;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// 32 bpp TrueColor X Window Dump, LSB first, of `pixel(x, y)` as RGB
+fn xwd(width: u32, height: u32, pixel: impl Fn(u32, u32) -> (u8, u8, u8)) -> Vec<u8> {
+    let mut header = [0u32; 25];
+    header[0] = 100;
+    header[1] = 7;
+    header[2] = 2;
+    header[3] = 24;
+    header[4] = width;
+    header[5] = height;
+    header[11] = 32;
+    header[12] = width * 4;
+    header[13] = 4;
+    header[14] = 0xff0000;
+    header[15] = 0x00ff00;
+    header[16] = 0x0000ff;
+    header[17] = 8;
+
+    let mut data: Vec<u8> = header.iter().flat_map(|f| f.to_be_bytes()).collect();
+    for y in 0..height {
+        for x in 0..width {
+            let (r, g, b) = pixel(x, y);
+            data.extend_from_slice(&[b, g, r, 0]);
+        }
+    }
+    data
+}
+
+fn display(name: &str, x: i32, width: u32, height: u32) -> DisplayInfo {
+    DisplayInfo { name: name.to_string(), width, height, x, y: 0, refresh_rate: 60.0, is_primary: false, edid: None, twin: false }
+}
+
+#[test]
+fn xwd_channels_follow_their_masks() {
+    let frame = Frame::from_xwd(&xwd(4, 2, |x, _| [(255, 0, 0), (0, 255, 0), (0, 0, 255), (255, 255, 255)][x as usize])).unwrap();
+    assert_eq!((frame.width, frame.height, frame.step), (4, 2, 1));
+
+    let grid = frame.grid(&display("HDMI-1", 0, 4, 2), 4, 1);
+    let expected = [0.2126, 0.7152, 0.0722, 1.0];
+    for (cell, want) in grid.cells.iter().zip(expected) {
+        assert!((cell - want).abs() < 0.01, "{:?}", grid.cells);
+    }
+
+    let mut short = xwd(4, 2, |_, _| (0, 0, 0));
+    assert_eq!(Frame::from_xwd(&short[..40]).err().as_deref(), Some("truncated XWD header"));
+    short.truncate(120);
+    assert_eq!(Frame::from_xwd(&short).err().as_deref(), Some("truncated XWD image"));
+}
+
+#[test]
+fn grid_covers_only_its_display() {
+    // Dark monitor on the left, bright one with a dark bottom half on the right
+    let frame = Frame::from_xwd(&xwd(96, 36, |x, y| if x < 64 || y >= 18 { (0, 0, 0) } else { (255, 255, 255) })).unwrap();
+
+    let left = frame.grid(&display("DP-1", 0, 64, 36), 2, 2);
+    assert_eq!(left.cells, [0.0; 4]);
+    let right = frame.grid(&display("HDMI-1", 64, 32, 36), 2, 2);
+    assert_eq!(right.cells, [1.0, 1.0, 0.0, 0.0]);
+}
+
+#[test]
+fn still_areas_are_found_in_frames_from_the_source_file() {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("frames");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("frame.xwd");
+    std::env::set_var("PIXELSHIFT_FRAME_SOURCE", &path);
+
+    // A taskbar along the top stays put, with a little noise; the rest changes every frame
+    let panel = display("HDMI-1", 0, 64, 36);
+    let mut tracker = ActivityTracker::default();
+    let mut captured = Vec::new();
+    for n in 0..4u8 {
+        std::fs::write(&path, xwd(64, 36, |_, y| if y < 2 { (200, 200, 200 + n % 2) } else { (40 * n, 40 * n, 40 * n) })).unwrap();
+        let received = Rc::new(RefCell::new(None));
+        framebuffer::capture({
+            let received = received.clone();
+            move |result| *received.borrow_mut() = Some(result)
+        });
+        // Files are read on the spot, never reused
+        let frame = received.take().expect("a file capture completes at once").unwrap();
+        captured.push(frame.captured);
+        tracker.update(frame.grid(&panel, GRID_COLS, GRID_ROWS), 30.0);
+    }
+    assert!(captured.windows(2).all(|w| w[0] < w[1]));
+
+    // The two taskbar rows of pixels make up the first of the grid rows
+    let cells = tracker.static_cells();
+    let still_rows: Vec<bool> = cells.chunks(GRID_COLS).map(|row| row.iter().all(|c| *c)).collect();
+    assert_eq!(still_rows.iter().filter(|r| **r).count(), 1, "{:?}", still_rows);
+    assert!(still_rows[0]);
+    let fraction = tracker.static_fraction().unwrap();
+    assert!((fraction - 1.0 / GRID_ROWS as f64).abs() < 1e-9, "{}", fraction);
+    // Mostly moving: the interval grows
+    assert_eq!(adaptive_interval(60, fraction), 176);
+
+    std::fs::write(&path, b"not an image").unwrap();
+    let failed = Rc::new(RefCell::new(None));
+    framebuffer::capture({
+        let failed = failed.clone();
+        move |result| *failed.borrow_mut() = Some(result.err())
+    });
+    assert_eq!(failed.take().flatten().as_deref(), Some("not an XWD version 7 file"));
+    std::env::remove_var("PIXELSHIFT_FRAME_SOURCE");
+}
@@ -0,0 +1,95 @@
+//! The event log's ring buffer, level filters and status-sink levels.
+
+use pixelshift_core::history::{Detail, History, Level};
+use pixelshift_core::SetTextSafe;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+fn texts(history: &History, min_level: Level) -> Vec<String> {
+    history.entries(min_level).into_iter().map(|e| e.text).collect()
+}
+
+#[test]
+fn oldest_entries_make_way_at_capacity() {
+    let history = History::new(3);
+    for n in 1..=5 {
+        history.info(&format!("entry {}", n));
+    }
+    assert_eq!(texts(&history, Level::Debug), ["entry 3", "entry 4", "entry 5"]);
+
+    history.clear();
+    assert!(history.entries(Level::Debug).is_empty());
+    assert!(history.last(Level::Debug).is_none());
+}
+
+#[test]
+fn filters_keep_the_level_and_above() {
+    let history = History::default();
+    history.log(Level::Debug, "detail");
+    history.info("started");
+    history.warning("fell back");
+    history.error("failed");
+    history.log(Level::Debug, "more detail");
+
+    assert_eq!(texts(&history, Level::Debug).len(), 5);
+    assert_eq!(texts(&history, Level::Info), ["started", "fell back", "failed"]);
+    assert_eq!(texts(&history, Level::Warning), ["fell back", "failed"]);
+    assert_eq!(texts(&history, Level::Error), ["failed"]);
+    assert_eq!(history.last(Level::Info).map(|e| e.text).as_deref(), Some("failed"));
+    assert_eq!(history.last(Level::Debug).map(|e| e.text).as_deref(), Some("more detail"));
+}
+
+#[test]
+fn status_messages_take_their_level() {
+    let history = History::default();
+    history.set_text_safe("Applying panning: xrandr --output HDMI-1 --panning 2560x1440+2+2");
+    history.set_text_safe("✗ Could not save profile: disk full");
+    history.log_safe(Level::Warning, "HDMI-1 was disconnected; auto-shift paused.");
+    history.log_safe(Level::Info, "Schedule: shifting off.");
+
+    let levels: Vec<Level> = history.entries(Level::Debug).into_iter().map(|e| e.level).collect();
+    assert_eq!(levels, [Level::Debug, Level::Error, Level::Warning, Level::Info]);
+}
+
+#[test]
+fn detail_keeps_failures_out_of_the_default_view() {
+    let history = History::default();
+    Detail(&history).set_text_safe("✗ Panning failed: BadMatch");
+    history.error("Shift failed on HDMI-1: BadMatch");
+
+    assert_eq!(texts(&history, Level::Info), ["Shift failed on HDMI-1: BadMatch"]);
+    assert_eq!(texts(&history, Level::Debug).len(), 2);
+}
+
+#[test]
+fn listeners_see_each_entry_until_disconnected() {
+    let history = History::default();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let id = history.connect_added({
+        let seen = seen.clone();
+        move |entry| seen.borrow_mut().push((entry.level, entry.text.clone()))
+    });
+    history.warning("one");
+    history.disconnect(id);
+    history.warning("two");
+    assert_eq!(*seen.borrow(), [(Level::Warning, "one".to_string())]);
+}
+
+#[test]
+fn text_has_one_line_per_entry_with_continuations_indented() {
+    let history = History::default();
+    history.info("Methods on HDMI-1:\nTransform Matrix: works");
+    history.log(Level::Debug, "hidden");
+
+    let text = history.to_text(Level::Info);
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", text);
+    // `YYYY-MM-DD HH:MM:SS INFO  `
+    assert_eq!(&lines[0][19..], " INFO  Methods on HDMI-1:");
+    assert_eq!(lines[1], format!("{}Transform Matrix: works", " ".repeat(26)));
+
+    let path = std::env::temp_dir().join(format!("pixelshift-history-{}.log", std::process::id()));
+    history.export(&path, Level::Info).unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+    let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,83 @@
+//! The profile file: round trips, hand-written files and files from a newer
+//! version.
+
+use pixelshift_core::brightness::BrightnessMethod;
+use pixelshift_core::profiles::{method_name, Profile, ProfileStore, SCHEMA_VERSION};
+use std::path::PathBuf;
+
+fn path(test: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("profiles").join(test);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.join("profiles.ini")
+}
+
+#[test]
+fn saved_profiles_load_back_unchanged() {
+    let path = path("round_trip");
+    let profile = Profile {
+        name: "DELL AW3423DW".to_string(),
+        method_idx: 2,
+        shift_amount: 4,
+        use_pattern: false,
+        interval_secs: 90,
+        adaptive: true,
+        wear: true,
+        schedule: "sat-sun off; * 10m".to_string(),
+        dim: "0,1040,1920x40".to_string(),
+        dim_level: 60,
+        conditioning: "white; gradient".to_string(),
+        conditioning_minutes: 20,
+        conditioning_idle: 30,
+        conditioning_at: "03:00".to_string(),
+        idle_dim: 5,
+        idle_dim_level: 20,
+        idle_dim_method: BrightnessMethod::Ddc,
+        hardware_orbit: true,
+    };
+    let mut store = ProfileStore::load_from(path.clone());
+    store.insert("DEL-A0B1-12345", profile.clone());
+    store.save().unwrap();
+
+    let text = std::fs::read_to_string(&path).unwrap();
+    assert!(text.contains(&format!("[meta]\nversion={}", SCHEMA_VERSION)), "{}", text);
+    let loaded = ProfileStore::load_from(path);
+    assert_eq!(loaded.get("DEL-A0B1-12345"), Some(&profile));
+    assert_eq!(loaded.get("LGD-0617-1A2B3C"), None);
+}
+
+#[test]
+fn file_without_a_version_is_read_and_saved_in_the_current_format() {
+    let path = path("no_version");
+    std::fs::write(&path, "[profile LGD-0617-1A2B3C]\nmethod=position\namount=3\n").unwrap();
+
+    let store = ProfileStore::load_from(path.clone());
+    let profile = store.get("LGD-0617-1A2B3C").unwrap();
+    assert_eq!((profile.method_idx, profile.shift_amount), (2, 3));
+    // Whatever the file leaves out takes the default
+    assert_eq!(profile.interval_secs, Profile::default().interval_secs);
+
+    store.save().unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().contains(&format!("version={}", SCHEMA_VERSION)));
+}
+
+#[test]
+fn file_from_a_newer_version_is_read_but_not_overwritten() {
+    let path = path("newer_version");
+    let text = format!("[meta]\nversion={}\n\n[profile LGD-0617-1A2B3C]\nmethod=position\nsparkle=yes\n", SCHEMA_VERSION + 1);
+    std::fs::write(&path, &text).unwrap();
+
+    let mut store = ProfileStore::load_from(path.clone());
+    assert_eq!(store.get("LGD-0617-1A2B3C").map(|p| p.method_idx), Some(2));
+
+    store.insert("DEL-A0B1-12345", Profile::default());
+    let error = store.save().unwrap_err().to_string();
+    assert!(error.contains("newer version") && error.contains("not overwriting"), "{}", error);
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), text);
+}
+
+#[test]
+fn method_names_cover_indices_from_outside() {
+    assert_eq!(method_name(2), "Position Offset");
+    assert_eq!(method_name(7), "unknown method");
+}
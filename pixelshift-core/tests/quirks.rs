@@ -0,0 +1,84 @@
+//! Quirk database lookup and parsing.
+
+use pixelshift_core::edid::{EdidInfo, PanelTechnology};
+use pixelshift_core::quirks::{QuirkDb, VcpToggle};
+use pixelshift_core::SetTextSafe;
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+#[derive(Default)]
+struct Collect(RefCell<Vec<String>>);
+
+impl SetTextSafe for Collect {
+    fn set_text_safe(&self, text: &str) {
+        self.0.borrow_mut().push(text.to_string());
+    }
+
+    fn append_text_safe(&self, text: &str) {
+        self.set_text_safe(text);
+    }
+}
+
+fn edid(manufacturer: &str, product_code: u16, model: Option<&str>) -> EdidInfo {
+    EdidInfo {
+        manufacturer: manufacturer.to_string(),
+        product_code,
+        serial_number: 0,
+        serial_string: None,
+        model_name: model.map(str::to_string),
+        panel_text: None,
+        manufacture_year: None,
+        width_mm: 600,
+        height_mm: 340,
+        technology: PanelTechnology::Unknown,
+        min_luminance: None,
+    }
+}
+
+fn write(name: &str, text: &str) -> PathBuf {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("quirks");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    std::fs::write(&path, text).unwrap();
+    path
+}
+
+#[test]
+fn model_name_wins_over_product_code() {
+    let path = write(
+        "lookup.ini",
+        "[XYZ Example OLED]\norbit=0xE8\norbit-on=1\nnote=Pixel Shift in the OSD\n\n[XYZ 1234]\norbit=0xF4\norbit-on=2\n\n[XYZ ABCD]\nrefresh=0xE9\n",
+    );
+    let db = QuirkDb::load_from(&[path], &Collect::default());
+
+    let named = db.lookup(&edid("XYZ", 0x1234, Some("Example OLED"))).unwrap();
+    assert_eq!(named.orbit, Some(VcpToggle { code: 0xE8, on: 1, off: 0 }));
+    assert_eq!(named.note, "Pixel Shift in the OSD");
+
+    // A name without an entry falls back to the product code
+    let unnamed = db.lookup(&edid("XYZ", 0x1234, Some("Other Model"))).unwrap();
+    assert_eq!(unnamed.orbit, Some(VcpToggle { code: 0xF4, on: 2, off: 0 }));
+
+    let refresh_only = db.lookup(&edid("XYZ", 0xABCD, None)).unwrap();
+    assert_eq!((refresh_only.orbit, refresh_only.refresh), (None, Some((0xE9, 1))));
+
+    assert!(db.lookup(&edid("XYZ", 0x9999, None)).is_none());
+}
+
+#[test]
+fn later_files_win_and_bad_entries_are_reported() {
+    let system = write("system.ini", "[XYZ 1234]\norbit=0xF4\norbit-on=2\n\n[XYZ 5678]\norbit=0xF4\norbit-on=1\n");
+    let user = write("user.ini", "[XYZ 1234]\norbit=0xE0\norbit-on=3\norbit-off=1\n\n[XYZ 5678]\norbit=0x1FF\norbit-on=1\n\n[XYZ 9ABC]\norbit=0xE0\n");
+    let status = Collect::default();
+    let db = QuirkDb::load_from(&[system, user, PathBuf::from("/nonexistent/ddc-quirks.ini")], &status);
+
+    assert_eq!(db.lookup(&edid("XYZ", 0x1234, None)).and_then(|q| q.orbit), Some(VcpToggle { code: 0xE0, on: 3, off: 1 }));
+    // An unreadable override leaves the earlier entry in place
+    assert_eq!(db.lookup(&edid("XYZ", 0x5678, None)).and_then(|q| q.orbit), Some(VcpToggle { code: 0xF4, on: 1, off: 0 }));
+    assert!(db.lookup(&edid("XYZ", 0x9ABC, None)).is_none());
+
+    let messages = status.0.borrow();
+    assert_eq!(messages.len(), 2, "{:?}", messages);
+    assert!(messages[0].contains("[XYZ 5678]") && messages[0].contains("orbit is not a VCP code"), "{:?}", messages);
+    assert!(messages[1].contains("[XYZ 9ABC]") && messages[1].contains("orbit without orbit-on"), "{:?}", messages);
+}
@@ -0,0 +1,120 @@
+//! Schedule parsing, lookup and the upcoming changes.
+
+use glib::{DateTime, TimeZone};
+use pixelshift_core::schedule::{Schedule, WeekTime};
+
+const OFFICE: &str = "sat-sun off; mon-fri 09:00-18:00 60s; * 10m";
+
+fn at(weekday: u32, hour: u32, minute: u32) -> WeekTime {
+    WeekTime { weekday, minute: hour * 60 + minute }
+}
+
+/// 2026-10-19 is a Monday
+fn monday(hour: i32, minute: i32, seconds: f64) -> DateTime {
+    DateTime::new(&TimeZone::utc(), 2026, 10, 19, hour, minute, seconds).unwrap()
+}
+
+#[test]
+fn parse_normalizes_and_round_trips() {
+    let schedule = Schedule::parse("Weekends OFF\nmonday-friday 09:00-18:00 60;  daily 600s").unwrap();
+    assert_eq!(schedule.to_string(), "sat,sun off; mon,tue,wed,thu,fri 09:00-18:00 1m; * 10m");
+    assert_eq!(Schedule::parse(&schedule.to_string()).unwrap(), schedule);
+
+    assert_eq!(Schedule::parse("fri-mon 1h").unwrap().to_string(), "mon,fri,sat,sun 1h");
+    assert!(Schedule::parse("").unwrap().is_empty());
+    assert!(Schedule::parse(" ; \n").unwrap().is_empty());
+}
+
+#[test]
+fn parse_rejects_bad_rules() {
+    for (text, error) in [
+        ("monkey 10m", "unknown day 'monkey'"),
+        ("sunxyz off", "unknown day 'sunxyz'"),
+        ("mo 10m", "unknown day 'mo'"),
+        ("mon-fridays 10m", "unknown day 'fridays'"),
+        ("09:00 10m", "'09:00' is not a time range like 09:00-18:00"),
+        ("09:00-25:00 10m", "'25:00' is not a time like 09:30"),
+        ("09:00-09:00 10m", "time range '09:00-09:00' is empty"),
+        ("* 3s", "interval '3s' must be between 5s and 24h"),
+        ("* 25h", "interval '25h' must be between 5s and 24h"),
+        ("* often", "'often' is not an interval like 60s, 10m or off"),
+    ] {
+        assert_eq!(Schedule::parse(text), Err(error.to_string()), "{}", text);
+    }
+}
+
+#[test]
+fn first_matching_rule_wins() {
+    let schedule = Schedule::parse(OFFICE).unwrap();
+    assert_eq!(schedule.interval_at(at(0, 8, 59), 30), Some(600));
+    assert_eq!(schedule.interval_at(at(0, 9, 0), 30), Some(60));
+    assert_eq!(schedule.interval_at(at(4, 17, 59), 30), Some(60));
+    assert_eq!(schedule.interval_at(at(4, 18, 0), 30), Some(600));
+    assert_eq!(schedule.interval_at(at(5, 12, 0), 30), None);
+
+    // Without a catch-all the profile's interval fills the gaps
+    let partial = Schedule::parse("mon 09:00-10:00 off").unwrap();
+    assert_eq!(partial.interval_at(at(1, 9, 30), 45), Some(45));
+}
+
+#[test]
+fn ranges_past_midnight_belong_to_the_day_they_start() {
+    let schedule = Schedule::parse("fri 22:00-06:00 off").unwrap();
+    assert_eq!(schedule.interval_at(at(4, 21, 59), 30), Some(30));
+    assert_eq!(schedule.interval_at(at(4, 23, 0), 30), None);
+    assert_eq!(schedule.interval_at(at(5, 5, 59), 30), None);
+    assert_eq!(schedule.interval_at(at(5, 6, 0), 30), Some(30));
+    // Thursday night's early hours are not covered
+    assert_eq!(schedule.interval_at(at(4, 3, 0), 30), Some(30));
+}
+
+#[test]
+fn minutes_until_change_finds_the_next_boundary() {
+    let schedule = Schedule::parse(OFFICE).unwrap();
+    assert_eq!(schedule.minutes_until_change(at(0, 8, 0), 30), Some(60));
+    assert_eq!(schedule.minutes_until_change(at(0, 9, 0), 30), Some(9 * 60));
+    // Friday evening runs into the weekend at midnight
+    assert_eq!(schedule.minutes_until_change(at(4, 18, 0), 30), Some(6 * 60));
+    // The weekend lasts until Monday morning, past Monday's midnight
+    assert_eq!(schedule.minutes_until_change(at(5, 0, 0), 30), Some(2 * 24 * 60));
+
+    assert_eq!(Schedule::parse("* 10m").unwrap().minutes_until_change(at(2, 12, 0), 30), None);
+    assert_eq!(Schedule::default().minutes_until_change(at(2, 12, 0), 30), None);
+}
+
+#[test]
+fn minutes_until_change_agrees_with_a_minute_by_minute_scan() {
+    const WEEK: u32 = 7 * 24 * 60;
+    for text in [OFFICE, "fri 22:00-06:00 off; sun,wed 10:00-24:00 5m", "weekdays 00:00-12:00 1h; tue off", "sat-mon 23:30-00:30 off"] {
+        let schedule = Schedule::parse(text).unwrap();
+        let week_time = |minute: u32| WeekTime { weekday: minute % WEEK / 1440, minute: minute % 1440 };
+        for start in (0..WEEK).step_by(37) {
+            let current = schedule.interval_at(week_time(start), 30);
+            let scanned = (1..=WEEK).find(|offset| schedule.interval_at(week_time(start + offset), 30) != current);
+            assert_eq!(schedule.minutes_until_change(week_time(start), 30), scanned, "{} at minute {}", text, start);
+        }
+    }
+}
+
+#[test]
+fn timeline_lists_the_changes_ahead() {
+    let schedule = Schedule::parse(OFFICE).unwrap();
+    let timeline: Vec<(String, Option<u64>)> = schedule
+        .timeline(&monday(8, 30, 15.0), 30, 5)
+        .into_iter()
+        .map(|(t, interval)| (t.format("%a %H:%M:%S").unwrap().to_string(), interval))
+        .collect();
+    assert_eq!(
+        timeline,
+        [
+            ("Mon 08:30:15".to_string(), Some(600)),
+            ("Mon 09:00:00".to_string(), Some(60)),
+            ("Mon 18:00:00".to_string(), Some(600)),
+            ("Tue 09:00:00".to_string(), Some(60)),
+            ("Tue 18:00:00".to_string(), Some(600)),
+        ]
+    );
+
+    // Nothing ever changes: only the interval in effect now
+    assert_eq!(Schedule::parse("* 10m").unwrap().timeline(&monday(12, 0, 0.0), 30, 5).len(), 1);
+}
@@ -2,381 +2,375 @@ use glib::ControlFlow;
 use gtk4::prelude::*;
 use gtk4::{
     Application, ApplicationWindow, HeaderBar, Box as GtkBox, Orientation,
-    ComboBoxText, SpinButton, Button, Label, Switch,
+    ComboBoxText, SpinButton, Button, Label, Switch, Entry,
 };
-use std::cell::RefCell;
-use std::process::Command;
+use std::cell::{Cell, RefCell};
+use std::os::unix::process::CommandExt;
 use std::rc::Rc;
 use std::time::Duration;
 use glib::source::SourceId;
 
-// Enhanced trait for thread-safe UI updates
-trait SetTextSafe {
-    fn set_text_safe(&self, text: &str);
-    fn append_text_safe(&self, text: &str);
-}
+mod tray;
 
-impl SetTextSafe for Label {
-    fn set_text_safe(&self, text: &str) {
-        let label = self.clone();
-        let message = text.to_string();
-        glib::idle_add_local(move || {
-            label.set_text(&message);
-            ControlFlow::Break
-        });
+use pixelshift_core::{
+    autostart, brightness, capabilities, conditioning, dbus, dimmer, dryrun, history, logind, profiles, schedule, scheduler, usage, wear,
+    apply_pixel_shift, get_connected_displays, preferred_display_index, reset_display_safe, select_display, DisplayInfo,
+};
+
+use history::{History, Level};
+use scheduler::{AutoShiftSession, Scheduler, SchedulerEvent};
+
+fn display_label(display: &DisplayInfo) -> String {
+    let mut label = match &display.edid {
+        Some(edid) => format!("{} - {} ({}x{}, {:.1}Hz)", display.name, edid.display_name(), display.width, display.height, display.refresh_rate),
+        None => format!("{} ({}x{}, {:.1}Hz)", display.name, display.width, display.height, display.refresh_rate),
+    };
+    if display.is_primary {
+        label.push_str(" [PRIMARY]");
+    }
+    if display.is_likely_oled() {
+        label.push_str(" [OLED]");
     }
+    label
+}
 
-    fn append_text_safe(&self, text: &str) {
-        let label = self.clone();
-        let message = text.to_string();
-        glib::idle_add_local(move || {
-            let current = label.text();
-            label.set_text(&format!("{}\n{}", current, message));
-            ControlFlow::Break
-        });
+/// Fill the display combo, keeping the previous selection when that monitor is
+/// still present and otherwise pre-selecting the first likely OLED panel
+fn populate_display_combo(combo: &ComboBoxText, displays: &[DisplayInfo], selected: Option<&DisplayInfo>) {
+    combo.remove_all();
+    for display in displays {
+        combo.append_text(&display_label(display));
     }
+
+    let index = selected
+        .and_then(|sel| displays.iter().position(|d| d.is_same_monitor(sel)))
+        .or_else(|| preferred_display_index(displays));
+    combo.set_active(index.map(|i| i as u32));
 }
 
-#[derive(Debug, Clone)]
-struct DisplayInfo {
-    name: String,
-    width: u32,
-    height: u32,
-    refresh_rate: f64,
-    is_primary: bool,
+/// Repopulate the combo from a new display list, following the selected monitor
+fn refresh_display_list(combo: &ComboBoxText, displays: &Rc<RefCell<Vec<DisplayInfo>>>, detected: Vec<DisplayInfo>) {
+    let selected = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned());
+    // Update the list before the combo so its changed handler sees the new entries
+    *displays.borrow_mut() = detected;
+    populate_display_combo(combo, &displays.borrow(), selected.as_ref());
 }
 
-/// Enhanced display detection with better parsing
-fn get_connected_displays() -> Vec<DisplayInfo> {
-    let output = match Command::new("xrandr").arg("--query").output() {
-        Ok(o) => String::from_utf8_lossy(&o.stdout).into_owned(),
-        Err(_) => return Vec::new(),
-    };
+/// Call `commit` when Enter is pressed in `entry` or focus leaves it, so a
+/// half-typed value is not written to disk on every keystroke
+fn connect_entry_commit(entry: &Entry, commit: impl Fn() + 'static) {
+    let commit = Rc::new(commit);
+    entry.connect_activate(gtk4::glib::clone!(@strong commit => move |_| commit()));
+    let focus = gtk4::EventControllerFocus::new();
+    focus.connect_leave(move |_| commit());
+    entry.add_controller(focus);
+}
 
-    let mut displays = Vec::new();
-    
-    for line in output.lines() {
-        if line.contains(" connected") && !line.contains("disconnected") {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if let Some(name) = parts.first() {
-                let name = name.to_string();
-                let is_primary = line.contains("primary");
-                
-                // Find current resolution and refresh rate
-                if let Some((width, height, refresh_rate)) = parse_current_mode(&output, &name) {
-                    displays.push(DisplayInfo {
-                        name: name.clone(),
-                        width,
-                        height,
-                        refresh_rate,
-                        is_primary,
-                    });
-                }
-            }
-        }
+/// Grey out the methods known to fail on the shown monitor, with the reasons in the tooltip
+fn show_method_support(method_combo: &ComboBoxText, method_support: &RefCell<Option<capabilities::Capabilities>>, support: Option<capabilities::Capabilities>) {
+    let tooltip = match &support {
+        Some(support) => support.summary().join("\n"),
+        None => "Not probed on this monitor yet".to_string(),
+    };
+    method_combo.set_tooltip_text(Some(&tooltip));
+    *method_support.borrow_mut() = support;
+    // Make the rows run their cell data function again
+    if let Some(model) = method_combo.model() {
+        model.foreach(|model, path, iter| {
+            model.row_changed(path, iter);
+            false
+        });
     }
-    
-    displays
 }
 
-fn parse_current_mode(xrandr_output: &str, display_name: &str) -> Option<(u32, u32, f64)> {
-    let lines: Vec<&str> = xrandr_output.lines().collect();
-    let mut found_display = false;
-    
-    for line in lines {
-        if line.starts_with(display_name) && line.contains("connected") {
-            found_display = true;
-            
-            // Try to find resolution in the connected line first
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            for part in parts {
-                if part.contains('x') && part.contains('+') {
-                    if let Some((res_part, _)) = part.split_once('+') {
-                        if let Some((w_str, h_str)) = res_part.split_once('x') {
-                            if let (Ok(width), Ok(height)) = (w_str.parse::<u32>(), h_str.parse::<u32>()) {
-                                return Some((width, height, 60.0)); // Default refresh rate
-                            }
-                        }
-                    }
+/// Application-wide state that outlives any window: the scheduler keeps
+/// shifting while the window is closed, and a new window attaches to it
+#[derive(Clone)]
+struct AppService {
+    scheduler: Scheduler,
+    /// Event log the scheduler and controllers report into
+    status: History,
+    dimmer: dimmer::DimController,
+    conditioner: conditioning::Conditioner,
+}
+
+impl AppService {
+    fn new(app: &Application) -> Self {
+        let status = History::default();
+        let scheduler = Scheduler::new(status.clone());
+        status.follow(&scheduler);
+
+        // Keep the process alive while a session exists, even with no window
+        let hold: Rc<RefCell<Option<gio::ApplicationHoldGuard>>> = Rc::new(RefCell::new(None));
+        let app_weak = app.downgrade();
+        scheduler.connect_event({
+            let scheduler = scheduler.clone();
+            move |event| {
+                if !matches!(event, SchedulerEvent::StateChanged) {
+                    return;
                 }
-            }
-            continue;
-        }
-        
-        if found_display && line.trim().starts_with(char::is_numeric) {
-            // This is a mode line for our display
-            if line.contains('*') && line.contains('+') {
-                // Current active mode
-                let parts: Vec<&str> = line.trim().split_whitespace().collect();
-                if let Some(mode_str) = parts.first() {
-                    if let Some((w_str, h_str)) = mode_str.split_once('x') {
-                        if let (Ok(width), Ok(height)) = (w_str.parse::<u32>(), h_str.parse::<u32>()) {
-                            // Try to extract refresh rate
-                            let refresh_rate = parts.iter()
-                                .find(|p| p.contains('*'))
-                                .and_then(|p| p.trim_end_matches('*').trim_end_matches('+').parse().ok())
-                                .unwrap_or(60.0);
-                            return Some((width, height, refresh_rate));
-                        }
-                    }
+                let mut hold = hold.borrow_mut();
+                match (scheduler.session().is_some(), hold.is_some()) {
+                    (true, false) => *hold = app_weak.upgrade().map(|app| app.hold()),
+                    (false, true) => *hold = None,
+                    _ => {}
                 }
             }
-        } else if found_display && !line.starts_with(' ') && !line.starts_with('\t') {
-            // We've moved to another display
-            break;
+        });
+
+        // Hotplug: GDK tracks RandR (or Wayland output) changes for us
+        if let Some(gdk_display) = gtk4::gdk::Display::default() {
+            let pending_refresh: Rc<RefCell<Option<SourceId>>> = Rc::new(RefCell::new(None));
+            gdk_display.monitors().connect_items_changed(gtk4::glib::clone!(@strong scheduler => move |_, _, _, _| {
+                // Coalesce bursts of changes while outputs settle
+                if let Some(id) = pending_refresh.borrow_mut().take() {
+                    id.remove();
+                }
+
+                let sid = glib::timeout_add_local_once(
+                    Duration::from_secs(1),
+                    gtk4::glib::clone!(@strong pending_refresh, @strong scheduler => move || {
+                        pending_refresh.borrow_mut().take();
+                        scheduler.refresh_displays();
+                    })
+                );
+                *pending_refresh.borrow_mut() = Some(sid);
+            }));
         }
-    }
-    
-    None
-}
 
-/// Safe pixel shift using only panning (no transform matrices or framebuffer changes)
-fn apply_pixel_shift_panning(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &Label) -> bool {
-    // Simple panning - just specify the offset
-    let panning_spec = format!("{}x{}+{}+{}", 
-        display.width, display.height, x_offset, y_offset);
-    
-    status_label.set_text_safe(&format!("Applying panning: xrandr --output {} --panning {}", 
-        display.name, panning_spec));
-
-    let result = Command::new("xrandr")
-        .args(["--output", &display.name, "--panning", &panning_spec])
-        .output();
-
-    match result {
-        Ok(output) => {
-            if output.status.success() {
-                status_label.set_text_safe(&format!("✓ Panning applied: +{}+{}", x_offset, y_offset));
-                true
+        // Restore before suspend, re-detect and reapply after wake
+        let sleep_watcher = logind::SleepWatcher::connect(gtk4::glib::clone!(@strong scheduler => move |going_to_sleep| {
+            if going_to_sleep {
+                scheduler.suspend();
             } else {
-                let err = String::from_utf8_lossy(&output.stderr);
-                status_label.set_text_safe(&format!("✗ Panning failed: {}", err));
-                false
+                scheduler.wake();
             }
+        }));
+        match sleep_watcher {
+            // Keep the watcher subscribed for the lifetime of the application
+            Ok(watcher) => {
+                app.connect_shutdown(move |_| {
+                    let _ = &watcher;
+                });
+            }
+            Err(e) => status.warning(&format!("Suspend handling unavailable: {}", e)),
         }
-        Err(e) => {
-            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
-            false
-        }
-    }
-}
 
-/// Alternative method using CRTC position changes (fixed format)
-fn apply_pixel_shift_position(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &Label) -> bool {
-    // Format position correctly - xrandr expects "x+y" format, handle negatives properly
-    let pos_str = if x_offset >= 0 && y_offset >= 0 {
-        format!("{}+{}", x_offset, y_offset)
-    } else if x_offset < 0 && y_offset >= 0 {
-        format!("{:+}+{}", x_offset, y_offset)  // This handles negative x
-    } else if x_offset >= 0 && y_offset < 0 {
-        format!("{}+{:+}", x_offset, y_offset)  // This handles negative y
-    } else {
-        format!("{:+}{:+}", x_offset, y_offset)  // Both negative
-    };
-    
-    status_label.set_text_safe(&format!("Applying position shift: xrandr --output {} --pos {}", 
-        display.name, pos_str));
-
-    let result = Command::new("xrandr")
-        .args([
-            "--output", &display.name,
-            "--pos", &pos_str
-        ])
-        .output();
-
-    match result {
-        Ok(output) => {
-            if output.status.success() {
-                status_label.set_text_safe(&format!("✓ Position shift applied: {}", pos_str));
-                true
-            } else {
-                let err = String::from_utf8_lossy(&output.stderr);
-                status_label.set_text_safe(&format!("✗ Position shift failed: {}", err));
-                false
+        // Wear heatmaps for monitors that opted in; dropped on shutdown to write them out
+        let wear_recorder = RefCell::new(Some(wear::WearRecorder::start(&scheduler)));
+        app.connect_shutdown(move |_| {
+            wear_recorder.borrow_mut().take();
+        });
+
+        // Powered-on, shifting and brightness hours per monitor
+        let usage_tracker = RefCell::new(Some(usage::UsageTracker::start(&scheduler)));
+        app.connect_shutdown(move |_| {
+            usage_tracker.borrow_mut().take();
+        });
+
+        // Lower brightness while nobody is looking; restored on shutdown
+        let idle_dimmer = RefCell::new(Some(brightness::IdleDimmer::start(&scheduler, status.clone())));
+        app.connect_shutdown(move |_| {
+            idle_dimmer.borrow_mut().take();
+        });
+
+        // Control interface for other desktop tools
+        let dbus_owner = RefCell::new(Some(dbus::own_name(&scheduler)));
+        app.connect_shutdown(move |_| {
+            if let Some(owner) = dbus_owner.borrow_mut().take() {
+                gio::bus_unown_name(owner);
             }
-        }
-        Err(e) => {
-            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
-            false
-        }
-    }
-}
+        });
 
-/// New method: Use transform matrix without framebuffer changes (most stable)
-fn apply_pixel_shift_transform(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &Label) -> bool {
-    // Calculate transform values as ratios (more precise than small pixel values)
-    let tx = x_offset as f64 / display.width as f64;
-    let ty = y_offset as f64 / display.height as f64;
-    
-    // Create transform matrix: translation only
-    let transform_str = format!("1,0,{:.6},0,1,{:.6},0,0,1", tx, ty);
-    
-    status_label.set_text_safe(&format!("Applying transform shift: xrandr --output {} --transform {}", 
-        display.name, transform_str));
-
-    let result = Command::new("xrandr")
-        .args([
-            "--output", &display.name,
-            "--transform", &transform_str
-        ])
-        .output();
-
-    match result {
-        Ok(output) => {
-            if output.status.success() {
-                status_label.set_text_safe(&format!("✓ Transform applied: {}px offset", 
-                    if x_offset != 0 { x_offset } else { y_offset }));
-                true
-            } else {
-                let err = String::from_utf8_lossy(&output.stderr);
-                status_label.set_text_safe(&format!("✗ Transform failed: {}", err));
-                false
+        // Tray icon for panels that host StatusNotifierItems
+        let app_weak = app.downgrade();
+        let quit_weak = app.downgrade();
+        let tray_owner = RefCell::new(Some(tray::spawn(
+            &scheduler,
+            move || {
+                if let Some(app) = app_weak.upgrade() {
+                    app.activate();
+                }
+            },
+            gtk4::glib::clone!(@strong scheduler => move || {
+                scheduler.stop();
+                if let Some(app) = quit_weak.upgrade() {
+                    app.quit();
+                }
+            }),
+        )));
+        app.connect_shutdown(move |_| {
+            if let Some(owner) = tray_owner.borrow_mut().take() {
+                gio::bus_unown_name(owner);
             }
-        }
-        Err(e) => {
-            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
-            false
-        }
+        });
+
+        // Overlays over static areas, following the session's profile
+        let dimmer = dimmer::DimController::new(&scheduler, status.clone());
+
+        // Fullscreen conditioning runs, started on idle or schedule as well as by hand
+        let conditioner = conditioning::Conditioner::new(&scheduler, status.clone());
+        app.connect_shutdown(gtk4::glib::clone!(@strong conditioner => move |_| conditioner.stop()));
+
+        Self { scheduler, status, dimmer, conditioner }
     }
-}
 
-/// Flicker-free panning with proper reset
-fn apply_pixel_shift_panning_smooth(display: &DisplayInfo, x_offset: i32, y_offset: i32, status_label: &Label) -> bool {
-    // Use a slightly larger panning area to avoid edge issues
-    let panning_w = display.width + 10;
-    let panning_h = display.height + 10;
-    let panning_spec = format!("{}x{}+{}+{}", panning_w, panning_h, x_offset, y_offset);
-    
-    status_label.set_text_safe(&format!("Applying smooth panning: xrandr --output {} --panning {}", 
-        display.name, panning_spec));
-
-    let result = Command::new("xrandr")
-        .args(["--output", &display.name, "--panning", &panning_spec])
-        .output();
-
-    match result {
-        Ok(output) => {
-            if output.status.success() {
-                status_label.set_text_safe(&format!("✓ Smooth panning applied: +{}+{}", x_offset, y_offset));
-                true
-            } else {
-                let err = String::from_utf8_lossy(&output.stderr);
-                status_label.set_text_safe(&format!("✗ Smooth panning failed: {}", err));
-                false
-            }
+    /// `--background`: start shifting the preferred display with its saved profile
+    fn start_background(&self) {
+        if self.scheduler.session().is_some() {
+            return;
         }
-        Err(e) => {
-            status_label.set_text_safe(&format!("✗ Command failed: {}", e));
-            false
+
+        match self.scheduler.preferred_display() {
+            Ok(display) => self.scheduler.start(AutoShiftSession::from_profile(display, &self.status)),
+            Err(e) => eprintln!("{}; nothing to shift.", e),
         }
     }
 }
 
-/// Reset display to normal state (enhanced)
-fn reset_display_safe(display: &DisplayInfo, status_label: &Label) -> bool {
-    // Try multiple reset methods in order of preference
-    
-    // Method 1: Reset transform matrix to identity
-    let transform_reset = Command::new("xrandr")
-        .args(["--output", &display.name, "--transform", "1,0,0,0,1,0,0,0,1"])
-        .output();
-    
-    if let Ok(output) = transform_reset {
-        if output.status.success() {
-            status_label.set_text_safe(&format!("✓ Transform reset successful for {}", display.name));
-            return true;
-        }
+/// `pixelshift-gtk COMMAND`: conditioning needs GTK to draw and runs here,
+/// everything else is the headless `pixelshift` installed alongside
+fn run_command(args: &[String]) -> i32 {
+    if args[0] != "condition" {
+        let cli = autostart::cli_executable();
+        let error = std::process::Command::new(&cli).args(args).exec();
+        eprintln!("pixelshift-gtk: cannot run {}: {}", cli.display(), error);
+        return 2;
     }
-    
-    // Method 2: Reset panning
-    let panning_reset = Command::new("xrandr")
-        .args(["--output", &display.name, "--panning", "0x0"])
-        .output();
-    
-    if let Ok(output) = panning_reset {
-        if output.status.success() {
-            status_label.set_text_safe(&format!("✓ Panning reset successful for {}", display.name));
-            return true;
-        }
-    }
-    
-    // Method 3: Reset position
-    let pos_reset = Command::new("xrandr")
-        .args(["--output", &display.name, "--pos", "0x0"])
-        .output();
-    
-    if let Ok(output) = pos_reset {
-        if output.status.success() {
-            status_label.set_text_safe(&format!("✓ Position reset successful for {}", display.name));
-            return true;
+    match run_conditioning(&args[1..]) {
+        Ok(()) => 0,
+        Err(e) => {
+            eprintln!("pixelshift-gtk condition: {}", e);
+            2
         }
     }
-    
-    // Method 4: Full auto reset as fallback
-    let auto_reset = Command::new("xrandr")
-        .args(["--output", &display.name, "--auto"])
-        .output();
-        
-    match auto_reset {
-        Ok(output) if output.status.success() => {
-            status_label.set_text_safe(&format!("✓ Auto reset successful for {}", display.name));
-            true
+}
+
+/// `condition [--display X] [--minutes N] [--pattern STEPS]`
+fn run_conditioning(args: &[String]) -> Result<(), String> {
+    let mut options = std::collections::HashMap::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "--dry-run" {
+            dryrun::set_enabled(true);
+            continue;
         }
-        _ => {
-            status_label.set_text_safe(&format!("✗ All reset methods failed for {}", display.name));
-            false
+        let Some(name) = arg.strip_prefix("--") else {
+            return Err(format!("unexpected argument '{}'", arg));
+        };
+        // Accept both `--minutes 5` and `--minutes=5`
+        let (name, value) = match name.split_once('=') {
+            Some((name, value)) => (name, value.to_string()),
+            None => (name, iter.next().ok_or_else(|| format!("--{} needs a value", name))?.clone()),
+        };
+        if !["display", "minutes", "pattern"].contains(&name) {
+            return Err(format!("unknown option --{}", name));
         }
+        options.insert(name, value);
     }
-}
 
-#[derive(Clone)]
-struct ShiftPattern {
-    positions: Vec<(i32, i32)>,
-    current_index: usize,
-}
+    let display = select_display(&get_connected_displays(), options.get("display").map(String::as_str))?;
+    let profile = profiles::ProfileStore::load().get(&display.monitor_id()).cloned().unwrap_or_default();
+    let minutes = match options.get("minutes") {
+        Some(m) => m.parse::<u32>().map_err(|_| format!("--minutes expects an integer, got '{}'", m))?.clamp(1, 120),
+        None => profile.conditioning_minutes,
+    };
+    let steps = conditioning::parse_steps(options.get("pattern").unwrap_or(&profile.conditioning))?;
+    gtk4::init().map_err(|e| format!("cannot open the display: {}", e))?;
 
-impl ShiftPattern {
-    fn new(shift_amount: i32) -> Self {
-        // Create a circular pattern to minimize visible transitions
-        let positions = vec![
-            (0, 0),                    // Center
-            (shift_amount, 0),         // Right
-            (shift_amount, shift_amount), // Bottom-right
-            (0, shift_amount),         // Bottom
-            (-shift_amount, shift_amount), // Bottom-left
-            (-shift_amount, 0),        // Left
-            (-shift_amount, -shift_amount), // Top-left
-            (0, -shift_amount),        // Top
-            (shift_amount, -shift_amount), // Top-right
-        ];
-        
-        Self {
-            positions,
-            current_index: 0,
+    let main_loop = glib::MainLoop::new(None, false);
+    let outcome = Rc::new(Cell::new(conditioning::Outcome::Interrupted));
+    let run = conditioning::ConditioningRun::start(&display, steps, Duration::from_secs(minutes as u64 * 60), {
+        let main_loop = main_loop.clone();
+        let outcome = outcome.clone();
+        move |result| {
+            outcome.set(result);
+            main_loop.quit();
         }
+    })?;
+    println!("Conditioning {} for {} min; press any key or move the mouse to stop.", display.name, minutes);
+
+    let run = Rc::new(run);
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        let run = run.clone();
+        glib::unix_signal_add_local(signal, move || {
+            run.stop();
+            ControlFlow::Break
+        });
     }
-    
-    fn next(&mut self) -> (i32, i32) {
-        let pos = self.positions[self.current_index];
-        self.current_index = (self.current_index + 1) % self.positions.len();
-        pos
-    }
-    
-    fn reset(&mut self) {
-        self.current_index = 0;
+
+    main_loop.run();
+    match outcome.get() {
+        conditioning::Outcome::Completed => println!("Conditioning finished."),
+        conditioning::Outcome::Interrupted => println!("Conditioning stopped early."),
     }
+    Ok(())
 }
 
 fn main() {
+    dryrun::init_from_env();
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).is_some_and(|a| !a.starts_with('-')) {
+        std::process::exit(run_command(&args[1..]));
+    }
+
     let app = Application::builder()
         .application_id("com.example.AdvancedPixelShift")
         .build();
+    app.add_main_option(
+        "background",
+        glib::Char::from(b'b'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Start auto-shift without opening a window",
+        None,
+    );
+
+    let service: Rc<RefCell<Option<AppService>>> = Rc::new(RefCell::new(None));
+    let background = Rc::new(Cell::new(false));
+
+    app.add_main_option(
+        "dry-run",
+        glib::Char::from(b'n'),
+        glib::OptionFlags::NONE,
+        glib::OptionArg::None,
+        "Log the commands shifts would run instead of running them",
+        None,
+    );
+
+    app.connect_handle_local_options(gtk4::glib::clone!(@strong background => move |_, options| {
+        background.set(options.contains("background"));
+        if options.contains("dry-run") {
+            dryrun::set_enabled(true);
+        }
+        -1
+    }));
+
+    app.connect_startup(gtk4::glib::clone!(@strong service => move |app| {
+        *service.borrow_mut() = Some(AppService::new(app));
+    }));
+
+    // A second launch activates this instance, so windows attach to the running scheduler
+    app.connect_activate(move |app| {
+        let Some(service) = service.borrow().clone() else { return };
+        if background.replace(false) {
+            service.start_background();
+            return;
+        }
+        if let Some(window) = app.active_window() {
+            window.present();
+            return;
+        }
+        build_ui(app, &service);
+    });
 
-    app.connect_activate(build_ui);
     app.run();
 }
 
-fn build_ui(app: &Application) {
+fn build_ui(app: &Application, service: &AppService) {
+    let scheduler = service.scheduler.clone();
+    let dimmer = service.dimmer.clone();
+
     let window = ApplicationWindow::builder()
         .application(app)
         .title("Advanced OLED Pixel Shifter")
@@ -389,6 +383,12 @@ fn build_ui(app: &Application) {
         .show_title_buttons(true)
         .build();
     window.set_titlebar(Some(&header));
+    let wear_button = Button::with_label("Wear Heatmap");
+    header.pack_end(&wear_button);
+    let usage_button = Button::with_label("Usage");
+    header.pack_end(&usage_button);
+    let conditioning_button = Button::with_label("Conditioning");
+    header.pack_end(&conditioning_button);
 
     let vbox = GtkBox::new(Orientation::Vertical, 12);
     vbox.set_margin_top(20);
@@ -398,19 +398,7 @@ fn build_ui(app: &Application) {
 
     // Display selection
     let combo = ComboBoxText::new();
-    let displays = get_connected_displays();
-    for display in &displays {
-        let label = if display.is_primary {
-            format!("{} ({}x{}, {:.1}Hz) [PRIMARY]", display.name, display.width, display.height, display.refresh_rate)
-        } else {
-            format!("{} ({}x{}, {:.1}Hz)", display.name, display.width, display.height, display.refresh_rate)
-        };
-        combo.append_text(&label);
-    }
-    if !displays.is_empty() {
-        combo.set_active(Some(0));
-    }
-    let displays = Rc::new(RefCell::new(displays));
+    let displays = Rc::new(RefCell::new(scheduler.displays()));
     vbox.append(&Label::new(Some("Select Display:")));
     vbox.append(&combo);
 
@@ -428,8 +416,23 @@ fn build_ui(app: &Application) {
     method_combo.append_text("Position Offset");
     method_combo.append_text("Basic Panning");
     method_combo.set_active(Some(0));
+    method_combo.set_hexpand(true);
+    let probe_button = Button::with_label("Probe");
+    probe_button.set_tooltip_text(Some("Try each method on the selected display with a 1-pixel shift and grey out the ones it ignores"));
+    let method_box = GtkBox::new(Orientation::Horizontal, 6);
+    method_box.append(&method_combo);
+    method_box.append(&probe_button);
     vbox.append(&Label::new(Some("Shift Method:")));
-    vbox.append(&method_combo);
+    vbox.append(&method_box);
+
+    // Methods known to fail on the selected monitor can't be picked
+    let method_support: Rc<RefCell<Option<capabilities::Capabilities>>> = Rc::new(RefCell::new(None));
+    for cell in method_combo.cells() {
+        method_combo.set_cell_data_func(&cell, gtk4::glib::clone!(@strong method_support => move |_, cell, model, iter| {
+            let index = model.path(iter).indices().first().copied().unwrap_or(0);
+            cell.set_sensitive(method_support.borrow().as_ref().is_none_or(|s| s.supports(index as u32)));
+        }));
+    }
 
     // Pattern mode
     let pattern_switch = Switch::new();
@@ -445,138 +448,982 @@ fn build_ui(app: &Application) {
     vbox.append(&Label::new(Some("Interval (seconds):")));
     vbox.append(&interval_spin);
 
+    // Adaptive interval from screen stillness
+    let adaptive_switch = Switch::new();
+    let adaptive_box = GtkBox::new(Orientation::Horizontal, 6);
+    adaptive_box.append(&Label::new(Some("Adapt Interval to Static Content:")));
+    adaptive_box.append(&adaptive_switch);
+    adaptive_box.set_tooltip_text(Some("Samples the screen every few seconds; shifts more often while much of it stays unchanged"));
+    vbox.append(&adaptive_box);
+
+    // Monitor's own pixel orbit over DDC/CI
+    let hardware_switch = Switch::new();
+    let hardware_box = GtkBox::new(Orientation::Horizontal, 6);
+    hardware_box.append(&Label::new(Some("Use Monitor's Built-in Orbit:")));
+    hardware_box.append(&hardware_switch);
+    hardware_box.set_tooltip_text(Some("Switches on the monitor's own pixel shift over DDC/CI while auto-shift runs, if ddc-quirks.ini knows how, and stops shifting in software"));
+    vbox.append(&hardware_box);
+
+    // Optional time-of-day schedule overriding the interval
+    let schedule_entry = Entry::new();
+    schedule_entry.set_placeholder_text(Some("e.g. sat-sun off; mon-fri 09:00-18:00 60s; * 10m"));
+    schedule_entry.set_tooltip_text(Some(
+        "Rules separated by ';': [days] [HH:MM-HH:MM] interval|off. The first matching rule wins; the interval above applies otherwise.",
+    ));
+    let timeline_label = Label::new(None);
+    timeline_label.set_halign(gtk4::Align::Start);
+    timeline_label.set_wrap(true);
+    vbox.append(&Label::new(Some("Schedule (optional):")));
+    vbox.append(&schedule_entry);
+    vbox.append(&timeline_label);
+
+    // Darken static areas such as a taskbar
+    let dim_entry = Entry::new();
+    dim_entry.set_placeholder_text(Some("auto, or x,y,WxH; ... e.g. 0,1040,1920x40"));
+    dim_entry.set_tooltip_text(Some(
+        "Areas to cover with a click-through dark overlay while auto-shift runs, in the monitor's pixels. 'auto' dims areas that stay unchanged.",
+    ));
+    let dim_spin = SpinButton::with_range(10.0, 90.0, 5.0);
+    dim_spin.set_value(40.0);
+    let dim_box = GtkBox::new(Orientation::Horizontal, 6);
+    dim_entry.set_hexpand(true);
+    dim_box.append(&dim_entry);
+    dim_box.append(&Label::new(Some("Darken %:")));
+    dim_box.append(&dim_spin);
+    vbox.append(&Label::new(Some("Dim Static Areas (optional):")));
+    vbox.append(&dim_box);
+
+    // Lower brightness while idle
+    let idle_dim_spin = SpinButton::with_range(0.0, 240.0, 1.0);
+    idle_dim_spin.set_tooltip_text(Some("Minutes without input before dimming; 0 turns it off"));
+    let idle_level_spin = SpinButton::with_range(10.0, 90.0, 5.0);
+    idle_level_spin.set_value(30.0);
+    let idle_method_combo = ComboBoxText::new();
+    idle_method_combo.append(Some("randr"), "RandR (X server)");
+    idle_method_combo.append(Some("ddc"), "DDC/CI (monitor)");
+    idle_method_combo.set_active_id(Some("randr"));
+    let idle_box = GtkBox::new(Orientation::Horizontal, 6);
+    idle_box.append(&Label::new(Some("After (min):")));
+    idle_box.append(&idle_dim_spin);
+    idle_box.append(&Label::new(Some("Brightness %:")));
+    idle_box.append(&idle_level_spin);
+    idle_box.append(&idle_method_combo);
+    vbox.append(&Label::new(Some("Dim When Idle:")));
+    vbox.append(&idle_box);
+
+    // Start on login
+    let autostart_switch = Switch::new();
+    let autostart_label = Label::new(None);
+    autostart_label.set_halign(gtk4::Align::Start);
+    let autostart_box = GtkBox::new(Orientation::Horizontal, 6);
+    autostart_box.append(&Label::new(Some("Start on Login:")));
+    autostart_box.append(&autostart_switch);
+    autostart_box.append(&autostart_label);
+    vbox.append(&autostart_box);
+
+    // Log commands instead of running them
+    let dry_run_switch = Switch::new();
+    dry_run_switch.set_active(dryrun::is_enabled());
+    let dry_run_box = GtkBox::new(Orientation::Horizontal, 6);
+    dry_run_box.append(&Label::new(Some("Dry Run (log commands only):")));
+    dry_run_box.append(&dry_run_switch);
+    dry_run_box.set_tooltip_text(Some(&format!("Shifts and resets are written to {} instead of being sent to the display", dryrun::log_path().display())));
+    vbox.append(&dry_run_box);
+
     // Buttons
     let button_box = GtkBox::new(Orientation::Horizontal, 12);
     let test_button = Button::with_label("Test Shift");
     let start_button = Button::with_label("Start Auto-Shift");
     let stop_button = Button::with_label("Stop & Reset");
+    let resume_button = Button::with_label("Resume");
+    resume_button.set_visible(false);
     button_box.append(&test_button);
     button_box.append(&start_button);
     button_box.append(&stop_button);
+    button_box.append(&resume_button);
     vbox.append(&button_box);
 
-    // Status
-    let status_label = Label::new(Some("Ready. Select display and configure settings."));
-    status_label.set_halign(gtk4::Align::Start);
-    status_label.set_wrap(true);
-    status_label.set_selectable(true);
-    vbox.append(&status_label);
+    // Live session state
+    let session_label = Label::new(None);
+    session_label.set_halign(gtk4::Align::Start);
+    vbox.append(&session_label);
+
+    // Latest event, with the full log below it
+    let status = service.status.clone();
+    vbox.append(&build_event_log(&window, &status));
+
+    // Per-monitor profiles: load on selection, save whenever a setting changes
+    let profiles = Rc::new(RefCell::new(profiles::ProfileStore::load()));
+    let loading_profile = Rc::new(Cell::new(false));
+
+    combo.connect_changed(gtk4::glib::clone!(@weak shift_spin, @weak method_combo, @weak pattern_switch, @weak interval_spin, @weak adaptive_switch, @weak hardware_switch, @weak schedule_entry, @weak dim_entry, @weak dim_spin, @weak idle_dim_spin, @weak idle_level_spin, @weak idle_method_combo, @strong profiles, @strong loading_profile, @strong displays => move |combo| {
+        let Some(display) = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) else { return };
+        if let Some(profile) = profiles.borrow().get(&display.monitor_id()) {
+            loading_profile.set(true);
+            shift_spin.set_value(profile.shift_amount as f64);
+            method_combo.set_active(Some(profile.method_idx));
+            pattern_switch.set_active(profile.use_pattern);
+            interval_spin.set_value(profile.interval_secs as f64);
+            adaptive_switch.set_active(profile.adaptive);
+            hardware_switch.set_active(profile.hardware_orbit);
+            schedule_entry.set_text(&profile.schedule);
+            dim_entry.set_text(&profile.dim);
+            dim_spin.set_value(profile.dim_level as f64);
+            idle_dim_spin.set_value(profile.idle_dim as f64);
+            idle_level_spin.set_value(profile.idle_dim_level as f64);
+            idle_method_combo.set_active_id(Some(profile.idle_dim_method.key()));
+            loading_profile.set(false);
+        }
+    }));
+
+    let save_profile: Rc<dyn Fn()> = Rc::new(gtk4::glib::clone!(@weak combo, @weak shift_spin, @weak method_combo, @weak pattern_switch, @weak interval_spin, @weak adaptive_switch, @weak hardware_switch, @weak schedule_entry, @weak dim_entry, @weak dim_spin, @weak idle_dim_spin, @weak idle_level_spin, @weak idle_method_combo, @strong profiles, @strong loading_profile, @strong displays, @strong status, @strong dimmer => move || {
+        if loading_profile.get() { return; }
+        let Some(display) = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) else { return };
+
+        // Fields without a widget here (e.g. wear recording) keep their saved values
+        let mut profiles = profiles.borrow_mut();
+        let previous = profiles.get(&display.monitor_id()).cloned().unwrap_or_default();
+        // Keep the last valid schedule until the one being typed parses
+        let schedule = match schedule::Schedule::parse(&schedule_entry.text()) {
+            Ok(_) => schedule_entry.text().to_string(),
+            Err(_) => previous.schedule.clone(),
+        };
+        let dim = match dimmer::DimAreas::parse(&dim_entry.text()) {
+            Ok(_) => {
+                dim_entry.remove_css_class("error");
+                dim_entry.text().to_string()
+            }
+            Err(_) => {
+                dim_entry.add_css_class("error");
+                previous.dim.clone()
+            }
+        };
+        profiles.insert(&display.monitor_id(), profiles::Profile {
+            name: display.edid.as_ref().map(|e| e.display_name()).unwrap_or_else(|| display.name.clone()),
+            method_idx: method_combo.active().unwrap_or(0),
+            shift_amount: shift_spin.value_as_int(),
+            use_pattern: pattern_switch.is_active(),
+            interval_secs: interval_spin.value_as_int().max(5) as u32,
+            adaptive: adaptive_switch.is_active(),
+            hardware_orbit: hardware_switch.is_active(),
+            schedule,
+            dim,
+            dim_level: dim_spin.value_as_int() as u32,
+            idle_dim: idle_dim_spin.value_as_int() as u32,
+            idle_dim_level: idle_level_spin.value_as_int() as u32,
+            idle_dim_method: idle_method_combo
+                .active_id()
+                .and_then(|id| brightness::BrightnessMethod::from_key(&id))
+                .unwrap_or_default(),
+            ..previous
+        });
+        if let Err(e) = profiles.save() {
+            status.error(&format!("Could not save profile: {}", e));
+        }
+        drop(profiles);
+        dimmer.refresh();
+    }));
+    shift_spin.connect_value_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    method_combo.connect_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    pattern_switch.connect_active_notify(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    interval_spin.connect_value_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    adaptive_switch.connect_active_notify(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    hardware_switch.connect_active_notify(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    connect_entry_commit(&schedule_entry, gtk4::glib::clone!(@strong save_profile => move || save_profile()));
+    connect_entry_commit(&dim_entry, gtk4::glib::clone!(@strong save_profile => move || save_profile()));
+    window.connect_close_request(gtk4::glib::clone!(@strong save_profile => move |_| {
+        save_profile();
+        glib::Propagation::Proceed
+    }));
+    dim_spin.connect_value_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    idle_dim_spin.connect_value_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    idle_level_spin.connect_value_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+    idle_method_combo.connect_changed(gtk4::glib::clone!(@strong save_profile => move |_| save_profile()));
+
+    // Which methods work on the selected monitor, as far as the cache knows;
+    // nothing is greyed out until it has been probed
+    let show_cached = Rc::new(gtk4::glib::clone!(@weak combo, @weak method_combo, @strong method_support, @strong displays => move || {
+        let Some(display) = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) else { return };
+        let cached = capabilities::CapabilityStore::load().get(&display.monitor_id()).cloned();
+        show_method_support(&method_combo, &method_support, cached);
+    }));
+    combo.connect_changed(gtk4::glib::clone!(@strong show_cached => move |_| show_cached()));
+
+    // Probing moves the panel and waits on xrandr, so it only runs on request and off the main loop
+    probe_button.connect_clicked(gtk4::glib::clone!(@weak combo, @weak method_combo, @strong method_support, @strong displays, @strong status => move |button| {
+        let Some(display) = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) else { return };
+        button.set_sensitive(false);
+        let button = button.clone();
+        let (combo, method_combo, method_support, displays, status) = (combo.clone(), method_combo.clone(), method_support.clone(), displays.clone(), status.clone());
+        glib::spawn_future_local(async move {
+            let worker_display = display.clone();
+            let result = gio::spawn_blocking(move || capabilities::probe(&worker_display))
+                .await
+                .unwrap_or_else(|_| Err("the probe panicked".to_string()));
+            button.set_sensitive(true);
+
+            let support = match result {
+                Ok(support) => support,
+                Err(e) => {
+                    status.error(&format!("Cannot probe {}: {}", display.name, e));
+                    return;
+                }
+            };
+            let mut store = capabilities::CapabilityStore::load();
+            store.insert(&display.monitor_id(), support.clone());
+            if let Err(e) = store.save() {
+                status.error(&format!("Could not save which methods work: {}", e));
+            } else {
+                status.info(&format!("Methods on {}:\n{}", display.name, support.summary().join("\n")));
+            }
+            // The selection may have moved on while the probe ran
+            let still_shown = combo.active().and_then(|i| displays.borrow().get(i as usize).map(|d| d.is_same_monitor(&display))).unwrap_or(false);
+            if !still_shown {
+                return;
+            }
+            // Off a method the monitor ignores, onto the next one that works
+            if let Some(to) = method_combo.active().filter(|m| !support.supports(*m)).and_then(|m| support.fallback(m)) {
+                method_combo.set_active(Some(to));
+            }
+            show_method_support(&method_combo, &method_support, Some(support));
+        });
+    }));
+
+    // Upcoming interval changes for the schedule as typed
+    let clock = scheduler.clock();
+    let update_timeline = Rc::new(gtk4::glib::clone!(@weak schedule_entry, @weak interval_spin, @weak timeline_label => @default-return false, move || {
+        match schedule::Schedule::parse(&schedule_entry.text()) {
+            Ok(schedule) if schedule.is_empty() => {
+                schedule_entry.remove_css_class("error");
+                timeline_label.set_text("");
+            }
+            Ok(schedule) => {
+                schedule_entry.remove_css_class("error");
+                let now = clock.now();
+                let steps: Vec<String> = schedule
+                    .timeline(&now, interval_spin.value_as_int().max(5) as u64, 5)
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, (t, interval))| {
+                        let when = if i == 0 { "Now".to_string() } else { t.format("%a %H:%M").map(|s| s.to_string()).unwrap_or_default() };
+                        format!("{}: {}", when, schedule::format_interval(interval))
+                    })
+                    .collect();
+                timeline_label.set_text(&steps.join("  →  "));
+            }
+            Err(e) => {
+                schedule_entry.add_css_class("error");
+                timeline_label.set_text(&format!("Invalid schedule: {}", e));
+            }
+        }
+        true
+    }));
+    schedule_entry.connect_changed(gtk4::glib::clone!(@strong update_timeline => move |_| { update_timeline(); }));
+    interval_spin.connect_value_changed(gtk4::glib::clone!(@strong update_timeline => move |_| { update_timeline(); }));
+    // Runs until the window's widgets are gone
+    glib::timeout_add_seconds_local(60, move || {
+        if update_timeline() { ControlFlow::Continue } else { ControlFlow::Break }
+    });
+
+    // Attach to the running session, if any, so the window shows what is actually happening
+    let session = scheduler.session();
+    populate_display_combo(&combo, &displays.borrow(), session.as_ref().map(|s| &s.display));
+    if let Some(ref session) = session {
+        loading_profile.set(true);
+        shift_spin.set_value(session.shift_amount as f64);
+        method_combo.set_active(Some(session.method_idx));
+        pattern_switch.set_active(session.use_pattern);
+        interval_spin.set_value(session.interval_secs as f64);
+        adaptive_switch.set_active(session.adaptive);
+        hardware_switch.set_active(session.hardware_orbit);
+        schedule_entry.set_text(&session.schedule.to_string());
+        loading_profile.set(false);
+    }
+
+    dry_run_switch.connect_active_notify(gtk4::glib::clone!(@strong status => move |switch| {
+        dryrun::set_enabled(switch.is_active());
+        status.info(&if switch.is_active() {
+            format!("Dry run: commands are logged to {} instead of being run.", dryrun::log_path().display())
+        } else {
+            "Dry run off: shifts are applied to the display again.".to_string()
+        });
+    }));
+
+    // Install or remove the login service for the selected monitor
+    let sync_autostart = gtk4::glib::clone!(@weak autostart_switch, @weak autostart_label => move || {
+        let status = autostart::status();
+        autostart_switch.set_active(status.as_ref().is_some_and(|s| s.enabled));
+        autostart_label.set_text(&status.map(|s| s.summary()).unwrap_or_else(|| "Off".to_string()));
+    });
+    sync_autostart();
+
+    autostart_switch.connect_state_set(gtk4::glib::clone!(@weak combo, @strong displays, @strong status => @default-return glib::Propagation::Proceed, move |_, active| {
+        let enabled = autostart::status().is_some_and(|s| s.enabled);
+        if active == enabled {
+            return glib::Propagation::Proceed;
+        }
+
+        let result = if active {
+            match combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) {
+                Some(display) => autostart::install(autostart::AutostartKind::preferred(), &display).map(|_| ()),
+                None => Err("No display selected".to_string()),
+            }
+        } else {
+            autostart::uninstall()
+        };
+        if let Err(e) = result {
+            status.error(&format!("Autostart: {}", e));
+        }
+
+        // Show what actually ended up on disk rather than what was asked for
+        glib::idle_add_local_once(sync_autostart.clone());
+        glib::Propagation::Stop
+    }));
+
+    wear_button.connect_clicked(gtk4::glib::clone!(@weak window, @weak combo, @strong displays, @strong profiles, @strong status => move |_| {
+        if let Some(display) = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) {
+            build_wear_window(&window, display, profiles.clone(), status.clone());
+        }
+    }));
+
+    usage_button.connect_clicked(gtk4::glib::clone!(@weak window, @strong status => move |_| build_usage_window(&window, status.clone())));
 
-    // State management
-    let running_id: Rc<RefCell<Option<SourceId>>> = Rc::new(RefCell::new(None));
-    let shift_pattern: Rc<RefCell<Option<ShiftPattern>>> = Rc::new(RefCell::new(None));
+    let conditioner = service.conditioner.clone();
+    conditioning_button.connect_clicked(gtk4::glib::clone!(@weak window, @weak combo, @strong displays, @strong profiles, @strong conditioner, @strong status => move |_| {
+        if let Some(display) = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned()) {
+            build_conditioning_window(&window, display, profiles.clone(), conditioner.clone(), status.clone());
+        }
+    }));
 
     // Test shift handler
-    test_button.connect_clicked(gtk4::glib::clone!(@weak combo, @weak shift_spin, @weak method_combo, @strong status_label, @strong displays => move |_| {
+    test_button.connect_clicked(gtk4::glib::clone!(@weak window, @weak combo, @weak shift_spin, @weak method_combo, @strong status, @strong displays => move |_| {
         if let Some(active_idx) = combo.active() {
-            if let Some(display) = displays.borrow().get(active_idx as usize) {
+            let display = displays.borrow().get(active_idx as usize).cloned();
+            if let Some(display) = display {
                 let shift_amount = shift_spin.value_as_int();
                 let method_idx = method_combo.active().unwrap_or(0);
                 
-                status_label.set_text_safe("Testing pixel shift...");
+                status.info("Testing pixel shift...");
                 
-                let success = match method_idx {
-                    0 => apply_pixel_shift_transform(display, shift_amount, shift_amount, &status_label),
-                    1 => apply_pixel_shift_panning_smooth(display, shift_amount, shift_amount, &status_label),
-                    2 => apply_pixel_shift_position(display, shift_amount, shift_amount, &status_label),
-                    3 => apply_pixel_shift_panning(display, shift_amount, shift_amount, &status_label),
-                    _ => apply_pixel_shift_transform(display, shift_amount, shift_amount, &status_label),
-                };
+                let success = apply_pixel_shift(method_idx, &display, shift_amount, shift_amount, &status);
                 
                 if success {
-                    // Reset after 3 seconds
-                    glib::timeout_add_local(
-                        Duration::from_secs(3),
-                        gtk4::glib::clone!(@strong display, @strong status_label => @default-return ControlFlow::Break, move || {
-                            reset_display_safe(&display, &status_label);
-                            ControlFlow::Break
-                        })
-                    );
+                    let what = format!("{} is shifted by {:+}{:+} pixels.", display.name, shift_amount, shift_amount);
+                    confirm_or_revert(&window, &what, gtk4::glib::clone!(@strong display, @strong status => move || {
+                        reset_display_safe(&display, &status);
+                    }));
                 }
             }
         }
     }));
 
+    // Moving a running session to another method applies it right away
+    method_combo.connect_changed(gtk4::glib::clone!(@weak window, @weak combo, @strong scheduler, @strong loading_profile, @strong displays => move |method_combo| {
+        if loading_profile.get() || !scheduler.is_running() { return; }
+        let (Some(method_idx), Some(session)) = (method_combo.active(), scheduler.session()) else { return };
+        let shown = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned());
+        if !shown.is_some_and(|d| d.is_same_monitor(&session.display)) { return; }
+
+        match scheduler.set_method(method_idx) {
+            Ok(previous) if previous != method_idx => {
+                let what = format!("{} now shifts with {}.", session.display.name, method_combo.active_text().unwrap_or_default());
+                confirm_or_revert(&window, &what, gtk4::glib::clone!(@weak method_combo, @strong scheduler => move || {
+                    // Back on the old method first, so the combo change below is a no-op
+                    if scheduler.set_method(previous).is_ok() {
+                        method_combo.set_active(Some(previous));
+                    }
+                }));
+            }
+            // A failed shift is logged from its SchedulerEvent::Error
+            Ok(_) | Err(_) => {}
+        }
+    }));
+
+    // Set by Start until the session's first shift has been confirmed
+    let confirm_first_shift = Rc::new(Cell::new(false));
+
     // Start auto-shift handler
-    start_button.connect_clicked(gtk4::glib::clone!(@weak combo, @weak shift_spin, @weak method_combo, @weak pattern_switch, @weak interval_spin, @strong running_id, @strong shift_pattern, @strong status_label, @strong displays => move |btn| {
-        if running_id.borrow().is_some() { return; }
+    start_button.connect_clicked(gtk4::glib::clone!(@strong confirm_first_shift, @weak combo, @weak shift_spin, @weak method_combo, @weak pattern_switch, @weak interval_spin, @weak adaptive_switch, @weak hardware_switch, @weak schedule_entry, @strong scheduler, @strong status, @strong displays => move |_| {
+        if scheduler.is_running() { return; }
 
         if let Some(active_idx) = combo.active() {
             if let Some(display) = displays.borrow().get(active_idx as usize) {
-                let display = display.clone();
                 let shift_amount = shift_spin.value_as_int();
                 let method_idx = method_combo.active().unwrap_or(0);
                 let use_pattern = pattern_switch.is_active();
                 let interval_secs = interval_spin.value_as_int().max(5) as u64;
+                let schedule = match schedule::Schedule::parse(&schedule_entry.text()) {
+                    Ok(schedule) => schedule,
+                    Err(e) => {
+                        status.error(&format!("Invalid schedule: {}", e));
+                        return;
+                    }
+                };
                 
-                // Initialize pattern
-                if use_pattern {
-                    *shift_pattern.borrow_mut() = Some(ShiftPattern::new(shift_amount));
-                }
-                
-                status_label.set_text_safe(&format!("Starting auto-shift for {} every {}s", display.name, interval_secs));
-                
-                let sid = glib::timeout_add_local(
-                    Duration::from_secs(interval_secs),
-                    gtk4::glib::clone!(@strong display, @strong shift_pattern, @strong status_label => @default-return ControlFlow::Break, move || {
-                        let (x_offset, y_offset) = if use_pattern {
-                            if let Some(ref mut pattern) = shift_pattern.borrow_mut().as_mut() {
-                                pattern.next()
-                            } else {
-                                (shift_amount, shift_amount)
-                            }
-                        } else {
-                            // Simple alternating shift
-                            static mut TOGGLE: bool = false;
-                            unsafe {
-                                TOGGLE = !TOGGLE;
-                                if TOGGLE {
-                                    (shift_amount, shift_amount)
-                                } else {
-                                    (0, 0)
-                                }
-                            }
-                        };
-                        
-                        let _success = match method_idx {
-                            0 => apply_pixel_shift_transform(&display, x_offset, y_offset, &status_label),
-                            1 => apply_pixel_shift_panning_smooth(&display, x_offset, y_offset, &status_label),
-                            2 => apply_pixel_shift_position(&display, x_offset, y_offset, &status_label),
-                            3 => apply_pixel_shift_panning(&display, x_offset, y_offset, &status_label),
-                            _ => apply_pixel_shift_transform(&display, x_offset, y_offset, &status_label),
-                        };
-                        
-                        ControlFlow::Continue
-                    })
-                );
-                
-                *running_id.borrow_mut() = Some(sid);
-                btn.set_sensitive(false);
+                confirm_first_shift.set(true);
+                scheduler.start(AutoShiftSession::new(display.clone(), shift_amount, method_idx, use_pattern, interval_secs).with_schedule(schedule).with_adaptive(adaptive_switch.is_active()).with_hardware_orbit(hardware_switch.is_active()));
             }
         }
     }));
 
     // Stop handler
-    stop_button.connect_clicked(gtk4::glib::clone!(@weak combo, @weak start_button, @strong running_id, @strong shift_pattern, @strong status_label, @strong displays => move |_| {
-        if let Some(id) = running_id.borrow_mut().take() {
-            id.remove();
-        }
-        
-        // Reset pattern
-        if let Some(ref mut pattern) = shift_pattern.borrow_mut().as_mut() {
-            pattern.reset();
-        }
-        
-        if let Some(active_idx) = combo.active() {
+    stop_button.connect_clicked(gtk4::glib::clone!(@weak combo, @strong scheduler, @strong status, @strong displays => move |_| {
+        if scheduler.session().is_some() {
+            scheduler.stop();
+        } else if let Some(active_idx) = combo.active() {
             if let Some(display) = displays.borrow().get(active_idx as usize) {
-                reset_display_safe(display, &status_label);
+                reset_display_safe(display, &status);
             }
         }
         
-        start_button.set_sensitive(true);
-        status_label.set_text_safe("Auto-shift stopped and display reset.");
+        status.info("Auto-shift stopped and display reset.");
+    }));
+
+    // Resume a session paused because its monitor was unplugged
+    resume_button.connect_clicked(gtk4::glib::clone!(@strong scheduler => move |_| {
+        scheduler.resume();
+    }));
+
+    // Keep buttons and the display list in sync with the scheduler
+    let sync_buttons = gtk4::glib::clone!(@weak start_button, @weak resume_button, @weak session_label, @strong scheduler => move || {
+        let session = scheduler.session();
+        start_button.set_sensitive(session.is_none());
+        resume_button.set_visible(scheduler.is_paused() && scheduler.session_display_present());
+        session_label.set_text(&match session {
+            Some(s) if scheduler.hardware_orbit_active() => format!("{}'s built-in pixel orbit is moving the picture", s.display.name),
+            Some(s) if scheduler.is_running() => {
+                let stillness = scheduler.static_fraction().map(|f| format!(" ({:.0}% static)", f * 100.0)).unwrap_or_default();
+                format!("Shifting {} {}{}, offset {:+}{:+}", s.display.name, schedule::format_interval(scheduler.current_interval()), stillness, s.current_offset.0, s.current_offset.1)
+            }
+            Some(s) => format!("Paused on {}, offset {:+}{:+}", s.display.name, s.current_offset.0, s.current_offset.1),
+            None => "Not running".to_string(),
+        });
+    });
+    sync_buttons();
+
+    let listener = scheduler.connect_event(gtk4::glib::clone!(@weak window, @weak combo, @weak method_combo, @strong method_support, @strong loading_profile, @strong confirm_first_shift, @strong displays, @strong scheduler => move |event| {
+        match event {
+            SchedulerEvent::StateChanged => {
+                if !scheduler.is_running() {
+                    confirm_first_shift.set(false);
+                }
+                sync_buttons();
+            }
+            SchedulerEvent::Shifted => {
+                sync_buttons();
+                if let Some(session) = scheduler.session().filter(|_| confirm_first_shift.replace(false)) {
+                    let what = format!("Auto-shift moved {} to {:+}{:+}.", session.display.name, session.current_offset.0, session.current_offset.1);
+                    confirm_or_revert(&window, &what, gtk4::glib::clone!(@strong scheduler => move || {
+                        scheduler.stop();
+                    }));
+                }
+            }
+            SchedulerEvent::DisplaysChanged => {
+                refresh_display_list(&combo, &displays, scheduler.displays());
+                sync_buttons();
+            }
+            SchedulerEvent::MethodFallback { to, .. } => {
+                // Follow the session onto the method it fell back to; the profile keeps the one chosen
+                let shown = combo.active().and_then(|i| displays.borrow().get(i as usize).cloned());
+                if let Some(session) = scheduler.session().filter(|s| shown.as_ref().is_some_and(|d| d.is_same_monitor(&s.display))) {
+                    loading_profile.set(true);
+                    method_combo.set_active(Some(*to));
+                    loading_profile.set(false);
+                    show_method_support(&method_combo, &method_support, capabilities::CapabilityStore::load().get(&session.display.monitor_id()).cloned());
+                }
+            }
+            // The event log follows the scheduler and records it
+            SchedulerEvent::Error(_) => {}
+        }
+    }));
+    window.connect_destroy(gtk4::glib::clone!(@strong scheduler => move |_| {
+        scheduler.disconnect(listener);
     }));
 
     window.set_child(Some(&vbox));
     window.show();
-}
\ No newline at end of file
+}
+
+/// Event log filter choices, most detailed first
+const LOG_FILTERS: [(&str, Level); 4] = [
+    ("Everything", Level::Debug),
+    ("Events", Level::Info),
+    ("Warnings and errors", Level::Warning),
+    ("Errors only", Level::Error),
+];
+
+fn log_filter(combo: &ComboBoxText) -> Level {
+    LOG_FILTERS[combo.active().unwrap_or(1) as usize].1
+}
+
+/// The latest event on one line, above an expandable log of what `history`
+/// keeps, with a level filter, copy and export
+fn build_event_log(window: &ApplicationWindow, history: &History) -> GtkBox {
+    let vbox = GtkBox::new(Orientation::Vertical, 6);
+
+    let last_label = Label::new(Some("Ready. Select display and configure settings."));
+    last_label.set_halign(gtk4::Align::Start);
+    last_label.set_ellipsize(gtk4::pango::EllipsizeMode::End);
+    vbox.append(&last_label);
+
+    let controls = GtkBox::new(Orientation::Horizontal, 6);
+    let filter_combo = ComboBoxText::new();
+    for (name, _) in LOG_FILTERS {
+        filter_combo.append_text(name);
+    }
+    filter_combo.set_active(Some(1));
+    let copy_button = Button::with_label("Copy");
+    let export_button = Button::with_label("Export...");
+    let clear_button = Button::with_label("Clear");
+    controls.append(&Label::new(Some("Show:")));
+    controls.append(&filter_combo);
+    controls.append(&copy_button);
+    controls.append(&export_button);
+    controls.append(&clear_button);
+
+    let text_view = gtk4::TextView::new();
+    text_view.set_editable(false);
+    text_view.set_cursor_visible(false);
+    text_view.set_monospace(true);
+    text_view.set_wrap_mode(gtk4::WrapMode::WordChar);
+    let scrolled = gtk4::ScrolledWindow::builder().min_content_height(160).vexpand(true).child(&text_view).build();
+
+    let log_box = GtkBox::new(Orientation::Vertical, 6);
+    log_box.append(&controls);
+    log_box.append(&scrolled);
+    let expander = gtk4::Expander::new(Some("Event Log"));
+    expander.set_child(Some(&log_box));
+    vbox.append(&expander);
+
+    let refresh = Rc::new(gtk4::glib::clone!(@weak last_label, @weak text_view, @weak scrolled, @weak filter_combo, @strong history => move || {
+        if let Some(last) = history.last(Level::Info) {
+            last_label.set_text(last.format(false).lines().next().unwrap_or_default());
+            last_label.set_tooltip_text(Some(&last.text));
+            last_label.remove_css_class("error");
+            last_label.remove_css_class("warning");
+            match last.level {
+                Level::Error => last_label.add_css_class("error"),
+                Level::Warning => last_label.add_css_class("warning"),
+                _ => {}
+            }
+        }
+
+        // Keep following new entries unless the user scrolled back to read
+        let adjustment = scrolled.vadjustment();
+        let at_end = adjustment.value() + adjustment.page_size() >= adjustment.upper() - 1.0;
+        let lines: Vec<String> = history.entries(log_filter(&filter_combo)).iter().map(|e| e.format(false)).collect();
+        let buffer = text_view.buffer();
+        buffer.set_text(&lines.join("\n"));
+        if at_end {
+            let end = buffer.create_mark(None, &buffer.end_iter(), false);
+            text_view.scroll_mark_onscreen(&end);
+            buffer.delete_mark(&end);
+        }
+    }));
+    refresh();
+
+    // Entries arrive in the middle of scheduler and GTK updates; redraw once they settle
+    let pending = Rc::new(Cell::new(false));
+    let listener = history.connect_added(gtk4::glib::clone!(@strong refresh, @strong pending => move |_| {
+        if !pending.replace(true) {
+            glib::idle_add_local_once(gtk4::glib::clone!(@strong refresh, @strong pending => move || {
+                pending.set(false);
+                refresh();
+            }));
+        }
+    }));
+    window.connect_destroy(gtk4::glib::clone!(@strong history => move |_| {
+        history.disconnect(listener);
+    }));
+
+    filter_combo.connect_changed(gtk4::glib::clone!(@strong refresh => move |_| refresh()));
+
+    copy_button.connect_clicked(gtk4::glib::clone!(@weak filter_combo, @strong history => move |button| {
+        button.clipboard().set_text(&history.to_text(log_filter(&filter_combo)));
+    }));
+
+    export_button.connect_clicked(gtk4::glib::clone!(@weak window, @weak filter_combo, @strong history => move |_| {
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Export Event Log"),
+            Some(&window),
+            gtk4::FileChooserAction::Save,
+            Some("Export"),
+            Some("Cancel"),
+        );
+        chooser.set_current_name("pixelshift-events.log");
+        chooser.connect_response(gtk4::glib::clone!(@weak filter_combo, @strong history => move |chooser, response| {
+            if response != gtk4::ResponseType::Accept { return; }
+            let Some(path) = chooser.file().and_then(|f| f.path()) else { return };
+            match history.export(&path, log_filter(&filter_combo)) {
+                Ok(()) => history.info(&format!("Exported event log to {}", path.display())),
+                Err(e) => history.error(&format!("Event log export failed: {}", e)),
+            }
+        }));
+        chooser.show();
+    }));
+
+    clear_button.connect_clicked(gtk4::glib::clone!(@strong history, @strong refresh => move |_| {
+        history.clear();
+        refresh();
+    }));
+
+    vbox
+}
+
+/// Heatmap of where `display` has shown static content, with recording toggle
+/// and export; outcomes go to the event log
+fn build_wear_window(parent: &ApplicationWindow, display: DisplayInfo, profiles: Rc<RefCell<profiles::ProfileStore>>, status: History) {
+    let window = gtk4::Window::builder()
+        .transient_for(parent)
+        .title(format!("Wear Heatmap - {}", display.name))
+        .default_width(720)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+
+    let id = display.monitor_id();
+    let record_switch = Switch::new();
+    record_switch.set_active(profiles.borrow().get(&id).is_some_and(|p| p.wear));
+    let record_box = GtkBox::new(Orientation::Horizontal, 6);
+    record_box.append(&Label::new(Some("Record Wear for This Monitor:")));
+    record_box.append(&record_switch);
+    vbox.append(&record_box);
+
+    let picture = gtk4::Picture::new();
+    picture.set_size_request(640, 360);
+    vbox.append(&picture);
+
+    let summary_label = Label::new(None);
+    summary_label.set_halign(gtk4::Align::Start);
+    summary_label.set_wrap(true);
+    summary_label.set_selectable(true);
+    vbox.append(&summary_label);
+
+    let button_box = GtkBox::new(Orientation::Horizontal, 12);
+    let refresh_button = Button::with_label("Refresh");
+    let png_button = Button::with_label("Export PNG...");
+    let csv_button = Button::with_label("Export CSV...");
+    button_box.append(&refresh_button);
+    button_box.append(&png_button);
+    button_box.append(&csv_button);
+    vbox.append(&button_box);
+
+    let refresh = Rc::new(gtk4::glib::clone!(@weak picture, @weak summary_label, @strong display => move || {
+        let map = wear::WearMap::load(&display.monitor_id());
+        let height = (640.0 * display.height.max(1) as f64 / display.width.max(1) as f64).round() as i32;
+        if let Some(pixbuf) = map.render(640, height) {
+            picture.set_pixbuf(Some(&pixbuf));
+        }
+
+        let hottest = match map.hottest() {
+            Some((col, row, risk)) => format!(
+                "Hottest region around +{}+{}: {:.0}% of sampled time showing unchanged full-brightness content.",
+                display.x + (col as u32 * display.width / map.cols as u32) as i32,
+                display.y + (row as u32 * display.height / map.rows as u32) as i32,
+                risk * 100.0
+            ),
+            None => "No static content recorded yet.".to_string(),
+        };
+        summary_label.set_text(&format!("Sampled {:.1} hours. {}", map.sampled_secs / 3600.0, hottest));
+    }));
+    refresh();
+
+    record_switch.connect_active_notify(gtk4::glib::clone!(@strong profiles, @strong id, @strong status => move |switch| {
+        let mut profiles = profiles.borrow_mut();
+        let mut profile = profiles.get(&id).cloned().unwrap_or_default();
+        profile.wear = switch.is_active();
+        profiles.insert(&id, profile);
+        if let Err(e) = profiles.save() {
+            status.error(&format!("Could not save profile: {}", e));
+        }
+    }));
+
+    refresh_button.connect_clicked(gtk4::glib::clone!(@strong refresh => move |_| refresh()));
+
+    let export = gtk4::glib::clone!(@weak window, @strong status, @strong display => move |format: &'static str| {
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Export Wear Heatmap"),
+            Some(&window),
+            gtk4::FileChooserAction::Save,
+            Some("Export"),
+            Some("Cancel"),
+        );
+        chooser.set_current_name(&format!("wear-{}.{}", display.monitor_id(), format));
+        chooser.connect_response(gtk4::glib::clone!(@strong display, @strong status => move |chooser, response| {
+            if response != gtk4::ResponseType::Accept { return; }
+            let Some(path) = chooser.file().and_then(|f| f.path()) else { return };
+
+            let map = wear::WearMap::load(&display.monitor_id());
+            let result = match format {
+                "png" => map.export_png(&display, &path),
+                _ => std::fs::write(&path, map.to_csv(&display)).map_err(|e| e.to_string()),
+            };
+            match result {
+                Ok(()) => status.info(&format!("Exported wear heatmap to {}", path.display())),
+                Err(e) => status.error(&format!("Wear heatmap export failed: {}", e)),
+            }
+        }));
+        chooser.show();
+    });
+    png_button.connect_clicked(gtk4::glib::clone!(@strong export => move |_| export("png")));
+    csv_button.connect_clicked(move |_| export("csv"));
+
+    window.set_child(Some(&vbox));
+    window.present();
+}
+
+/// Per-monitor usage totals with CSV export; outcomes go to the event log
+fn build_usage_window(parent: &ApplicationWindow, status: History) {
+    let window = gtk4::Window::builder()
+        .transient_for(parent)
+        .title("Panel Usage")
+        .default_width(640)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+
+    let grid = gtk4::Grid::builder().column_spacing(18).row_spacing(6).build();
+    let store = usage::UsageStore::load();
+    let headers = ["Monitor", "Powered On", "Shifting", "Covered", "Avg. Brightness", "Brightness Hours (0-100%)"];
+    for (col, title) in headers.iter().enumerate() {
+        let label = Label::new(None);
+        label.set_markup(&format!("<b>{}</b>", title));
+        label.set_halign(gtk4::Align::Start);
+        grid.attach(&label, col as i32, 0, 1, 1);
+    }
+
+    for (row, (id, record)) in store.sorted().into_iter().enumerate() {
+        let name = if record.name.is_empty() { id.clone() } else { format!("{}\n{}", record.name, id) };
+        let distribution = record
+            .brightness_secs
+            .iter()
+            .map(|secs| format!("{:.0}", secs / 3600.0))
+            .collect::<Vec<_>>()
+            .join(" / ");
+        let cells = [
+            name,
+            usage::format_hours(record.on_secs),
+            usage::format_hours(record.shifting_secs),
+            format!("{:.0}%", record.coverage() * 100.0),
+            record.mean_brightness().map(|b| format!("{:.0}%", b * 100.0)).unwrap_or_else(|| "-".to_string()),
+            distribution,
+        ];
+        for (col, text) in cells.iter().enumerate() {
+            let label = Label::new(Some(text));
+            label.set_halign(gtk4::Align::Start);
+            label.set_selectable(true);
+            grid.attach(&label, col as i32, row as i32 + 1, 1, 1);
+        }
+    }
+    if store.records.is_empty() {
+        grid.attach(&Label::new(Some("No usage recorded yet.")), 0, 1, headers.len() as i32, 1);
+    }
+    vbox.append(&grid);
+
+    let hint_label = Label::new(Some("Sampled once a minute while the app or `pixelshift run` is active."));
+    hint_label.set_halign(gtk4::Align::Start);
+    hint_label.set_wrap(true);
+    vbox.append(&hint_label);
+
+    let export_button = Button::with_label("Export CSV...");
+    export_button.set_halign(gtk4::Align::Start);
+    vbox.append(&export_button);
+
+    export_button.connect_clicked(gtk4::glib::clone!(@weak window, @strong status => move |_| {
+        let chooser = gtk4::FileChooserNative::new(
+            Some("Export Usage"),
+            Some(&window),
+            gtk4::FileChooserAction::Save,
+            Some("Export"),
+            Some("Cancel"),
+        );
+        chooser.set_current_name("pixelshift-usage.csv");
+        chooser.connect_response(gtk4::glib::clone!(@strong status => move |chooser, response| {
+            if response != gtk4::ResponseType::Accept { return; }
+            let Some(path) = chooser.file().and_then(|f| f.path()) else { return };
+            match std::fs::write(&path, usage::UsageStore::load().to_csv()) {
+                Ok(()) => status.info(&format!("Exported usage to {}", path.display())),
+                Err(e) => status.error(&format!("Usage export failed: {}", e)),
+            }
+        }));
+        chooser.show();
+    }));
+
+    window.set_child(Some(&vbox));
+    window.present();
+}
+
+/// Conditioning settings for one monitor, with a button to run it now;
+/// outcomes go to the event log
+fn build_conditioning_window(parent: &ApplicationWindow, display: DisplayInfo, profiles: Rc<RefCell<profiles::ProfileStore>>, conditioner: conditioning::Conditioner, status: History) {
+    let window = gtk4::Window::builder()
+        .transient_for(parent)
+        .title(format!("Pixel Conditioning - {}", display.name))
+        .default_width(480)
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+
+    let id = display.monitor_id();
+    let profile = profiles.borrow().get(&id).cloned().unwrap_or_default();
+
+    let steps_entry = Entry::new();
+    steps_entry.set_placeholder_text(Some(conditioning::DEFAULT_STEPS));
+    steps_entry.set_tooltip_text(Some("Colors (#rrggbb or names) and 'gradient', separated by ';'. Each is shown for 30 seconds in turn."));
+    steps_entry.set_text(&profile.conditioning);
+    vbox.append(&Label::new(Some("Colors and Patterns:")));
+    vbox.append(&steps_entry);
+
+    let minutes_spin = SpinButton::with_range(1.0, 120.0, 1.0);
+    minutes_spin.set_value(profile.conditioning_minutes as f64);
+    vbox.append(&Label::new(Some("Duration (minutes):")));
+    vbox.append(&minutes_spin);
+
+    let idle_spin = SpinButton::with_range(0.0, 240.0, 5.0);
+    idle_spin.set_value(profile.conditioning_idle as f64);
+    vbox.append(&Label::new(Some("Run After Idle (minutes, 0 = never):")));
+    vbox.append(&idle_spin);
+
+    let times_entry = Entry::new();
+    times_entry.set_placeholder_text(Some("e.g. 03:00; 13:30"));
+    times_entry.set_text(&profile.conditioning_at);
+    vbox.append(&Label::new(Some("Run Daily At (optional):")));
+    vbox.append(&times_entry);
+
+    let run_button = Button::with_label("Run Now");
+    run_button.set_halign(gtk4::Align::Start);
+    vbox.append(&run_button);
+
+    let hint_label = Label::new(Some("Any key, click or mouse movement ends a run early."));
+    hint_label.set_halign(gtk4::Align::Start);
+    hint_label.set_wrap(true);
+    vbox.append(&hint_label);
+
+    // Keep the last valid value of a field until the one being typed parses
+    let save = Rc::new(gtk4::glib::clone!(@weak steps_entry, @weak minutes_spin, @weak idle_spin, @weak times_entry, @strong status, @strong profiles, @strong id => move || {
+        let mut profiles = profiles.borrow_mut();
+        let mut profile = profiles.get(&id).cloned().unwrap_or_default();
+        for (entry, valid) in [
+            (&steps_entry, conditioning::parse_steps(&steps_entry.text()).is_ok()),
+            (&times_entry, conditioning::parse_times(&times_entry.text()).is_ok()),
+        ] {
+            if valid { entry.remove_css_class("error") } else { entry.add_css_class("error") }
+        }
+        if conditioning::parse_steps(&steps_entry.text()).is_ok() {
+            profile.conditioning = steps_entry.text().to_string();
+        }
+        if conditioning::parse_times(&times_entry.text()).is_ok() {
+            profile.conditioning_at = times_entry.text().to_string();
+        }
+        profile.conditioning_minutes = minutes_spin.value_as_int() as u32;
+        profile.conditioning_idle = idle_spin.value_as_int() as u32;
+        profiles.insert(&id, profile);
+        if let Err(e) = profiles.save() {
+            status.error(&format!("Could not save profile: {}", e));
+        }
+    }));
+    connect_entry_commit(&steps_entry, gtk4::glib::clone!(@strong save => move || save()));
+    connect_entry_commit(&times_entry, gtk4::glib::clone!(@strong save => move || save()));
+    minutes_spin.connect_value_changed(gtk4::glib::clone!(@strong save => move |_| save()));
+    idle_spin.connect_value_changed(gtk4::glib::clone!(@strong save => move |_| save()));
+    // Closing the window does not take the focus out of a field first
+    window.connect_close_request(gtk4::glib::clone!(@strong save => move |_| {
+        save();
+        glib::Propagation::Proceed
+    }));
+
+    run_button.connect_clicked(gtk4::glib::clone!(@strong status, @strong display, @strong conditioner => move |_| {
+        if let Err(e) = conditioner.run_now(&display) {
+            status.error(&format!("Cannot run pixel conditioning on {}: {}", display.name, e));
+        }
+    }));
+
+    window.set_child(Some(&vbox));
+    window.present();
+}
+
+/// How long a manual change stays without being confirmed
+const CONFIRM_REVERT_SECS: u32 = 15;
+
+/// Ask whether to keep a change that was just applied. `revert` runs when the
+/// user says no, closes the dialog or does not answer in time, so a change
+/// that leaves the desktop unusable undoes itself.
+fn confirm_or_revert(parent: &ApplicationWindow, what: &str, revert: impl FnOnce() + 'static) {
+    let window = gtk4::Window::builder()
+        .transient_for(parent)
+        .modal(true)
+        .resizable(false)
+        .title("Keep This Configuration?")
+        .build();
+
+    let vbox = GtkBox::new(Orientation::Vertical, 12);
+    vbox.set_margin_top(20);
+    vbox.set_margin_bottom(20);
+    vbox.set_margin_start(20);
+    vbox.set_margin_end(20);
+
+    let message = Label::new(Some(&format!("{}\nKeep this configuration?", what)));
+    message.set_halign(gtk4::Align::Start);
+    message.set_wrap(true);
+    vbox.append(&message);
+    let countdown = Label::new(Some(&format!("Reverting in {} seconds.", CONFIRM_REVERT_SECS)));
+    countdown.set_halign(gtk4::Align::Start);
+    vbox.append(&countdown);
+
+    let button_box = GtkBox::new(Orientation::Horizontal, 6);
+    button_box.set_halign(gtk4::Align::End);
+    let revert_button = Button::with_label("Revert");
+    let keep_button = Button::with_label("Keep Changes");
+    keep_button.add_css_class("suggested-action");
+    button_box.append(&revert_button);
+    button_box.append(&keep_button);
+    vbox.append(&button_box);
+
+    let revert = Rc::new(RefCell::new(Some(revert)));
+    let remaining = Rc::new(Cell::new(CONFIRM_REVERT_SECS));
+    let timer: Rc<RefCell<Option<SourceId>>> = Rc::new(RefCell::new(None));
+
+    let sid = glib::timeout_add_seconds_local(1, gtk4::glib::clone!(@weak window, @weak countdown, @strong remaining, @strong timer => @default-return ControlFlow::Break, move || {
+        remaining.set(remaining.get().saturating_sub(1));
+        if remaining.get() > 0 {
+            countdown.set_text(&format!("Reverting in {} seconds.", remaining.get()));
+            return ControlFlow::Continue;
+        }
+        // Returning Break removes the source, so don't remove it again on close
+        timer.borrow_mut().take();
+        window.close();
+        ControlFlow::Break
+    }));
+    *timer.borrow_mut() = Some(sid);
+
+    // Anything but Keep Changes ends up here, including the main window closing
+    window.connect_destroy(gtk4::glib::clone!(@strong revert, @strong timer => move |_| {
+        if let Some(id) = timer.borrow_mut().take() {
+            id.remove();
+        }
+        if let Some(revert) = revert.borrow_mut().take() {
+            revert();
+        }
+    }));
+    revert_button.connect_clicked(gtk4::glib::clone!(@weak window => move |_| window.close()));
+    keep_button.connect_clicked(gtk4::glib::clone!(@weak window, @strong revert => move |_| {
+        revert.borrow_mut().take();
+        window.close();
+    }));
+
+    window.set_child(Some(&vbox));
+    window.set_default_widget(Some(&keep_button));
+    window.present();
+}
@@ -0,0 +1,389 @@
+//! StatusNotifierItem tray icon with a com.canonical.dbusmenu menu.
+//!
+//! KDE and wlroots bars (waybar, sfwbar, ...) host these natively; GNOME
+//! needs the AppIndicator extension. Without a StatusNotifierWatcher on the
+//! bus nothing is shown and the app works as before.
+
+use gio::prelude::*;
+use gio::{BusNameOwnerFlags, BusNameWatcherFlags, BusType, DBusCallFlags, DBusConnection, DBusNodeInfo, OwnerId};
+use glib::{Variant, VariantTy};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use pixelshift_core::scheduler::{AutoShiftSession, Scheduler, SchedulerEvent};
+
+const ITEM_PATH: &str = "/StatusNotifierItem";
+const ITEM_INTERFACE: &str = "org.kde.StatusNotifierItem";
+const MENU_PATH: &str = "/MenuBar";
+const MENU_INTERFACE: &str = "com.canonical.dbusmenu";
+const WATCHER_NAME: &str = "org.kde.StatusNotifierWatcher";
+
+const INTROSPECTION_XML: &str = r#"
+<node>
+  <interface name="org.kde.StatusNotifierItem">
+    <method name="Activate"><arg type="i" direction="in"/><arg type="i" direction="in"/></method>
+    <method name="SecondaryActivate"><arg type="i" direction="in"/><arg type="i" direction="in"/></method>
+    <method name="ContextMenu"><arg type="i" direction="in"/><arg type="i" direction="in"/></method>
+    <method name="Scroll"><arg type="i" direction="in"/><arg type="s" direction="in"/></method>
+    <property name="Category" type="s" access="read"/>
+    <property name="Id" type="s" access="read"/>
+    <property name="Title" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <property name="IconName" type="s" access="read"/>
+    <property name="OverlayIconName" type="s" access="read"/>
+    <property name="ToolTip" type="(sa(iiay)ss)" access="read"/>
+    <property name="ItemIsMenu" type="b" access="read"/>
+    <property name="Menu" type="o" access="read"/>
+    <signal name="NewIcon"/>
+    <signal name="NewOverlayIcon"/>
+    <signal name="NewToolTip"/>
+    <signal name="NewStatus"><arg type="s"/></signal>
+  </interface>
+  <interface name="com.canonical.dbusmenu">
+    <method name="GetLayout">
+      <arg type="i" direction="in"/><arg type="i" direction="in"/><arg type="as" direction="in"/>
+      <arg type="u" direction="out"/><arg type="(ia{sv}av)" direction="out"/>
+    </method>
+    <method name="GetGroupProperties">
+      <arg type="ai" direction="in"/><arg type="as" direction="in"/>
+      <arg type="a(ia{sv})" direction="out"/>
+    </method>
+    <method name="GetProperty">
+      <arg type="i" direction="in"/><arg type="s" direction="in"/>
+      <arg type="v" direction="out"/>
+    </method>
+    <method name="Event">
+      <arg type="i" direction="in"/><arg type="s" direction="in"/><arg type="v" direction="in"/><arg type="u" direction="in"/>
+    </method>
+    <method name="EventGroup">
+      <arg type="a(isvu)" direction="in"/>
+      <arg type="ai" direction="out"/>
+    </method>
+    <method name="AboutToShow">
+      <arg type="i" direction="in"/>
+      <arg type="b" direction="out"/>
+    </method>
+    <method name="AboutToShowGroup">
+      <arg type="ai" direction="in"/>
+      <arg type="ai" direction="out"/><arg type="ai" direction="out"/>
+    </method>
+    <property name="Version" type="u" access="read"/>
+    <property name="TextDirection" type="s" access="read"/>
+    <property name="Status" type="s" access="read"/>
+    <property name="IconThemePath" type="as" access="read"/>
+    <signal name="LayoutUpdated"><arg type="u"/><arg type="i"/></signal>
+    <signal name="ItemsPropertiesUpdated"><arg type="a(ia{sv})"/><arg type="a(ias)"/></signal>
+  </interface>
+</node>
+"#;
+
+#[derive(Clone, Copy, PartialEq)]
+enum MenuAction {
+    Start,
+    Pause,
+    ShiftNow,
+    Reset,
+    OpenSettings,
+    Quit,
+}
+
+const MENU_ITEMS: [(i32, Option<MenuAction>); 7] = [
+    (1, Some(MenuAction::Start)),
+    (2, Some(MenuAction::Pause)),
+    (3, Some(MenuAction::ShiftNow)),
+    (4, Some(MenuAction::Reset)),
+    (5, None),
+    (6, Some(MenuAction::OpenSettings)),
+    (7, Some(MenuAction::Quit)),
+];
+
+#[derive(Clone)]
+struct Tray {
+    scheduler: Scheduler,
+    open_settings: Rc<dyn Fn()>,
+    quit: Rc<dyn Fn()>,
+    revision: Rc<Cell<u32>>,
+}
+
+/// Publish the tray item; `open_settings` and `quit` come from the application
+pub fn spawn(scheduler: &Scheduler, open_settings: impl Fn() + 'static, quit: impl Fn() + 'static) -> OwnerId {
+    let tray = Tray {
+        scheduler: scheduler.clone(),
+        open_settings: Rc::new(open_settings),
+        quit: Rc::new(quit),
+        revision: Rc::new(Cell::new(1)),
+    };
+    let name = format!("org.kde.StatusNotifierItem-{}-1", std::process::id());
+    // Registration needs both our name and a watcher, whichever comes last
+    let acquired = Rc::new(Cell::new(false));
+    let watched = Rc::new(Cell::new(false));
+
+    let registered_name = name.clone();
+    let (on_acquired, on_lost, watcher_seen) = (acquired.clone(), acquired.clone(), watched.clone());
+    let owner = gio::bus_own_name(
+        BusType::Session,
+        &name,
+        BusNameOwnerFlags::NONE,
+        move |connection, _| {
+            if let Err(e) = tray.export(&connection) {
+                eprintln!("Could not export tray item: {}", e);
+            }
+        },
+        move |connection, _| {
+            on_acquired.set(true);
+            if watcher_seen.get() {
+                register_with_watcher(&connection, &registered_name);
+            }
+        },
+        move |_, _| on_lost.set(false),
+    );
+
+    let gone = watched.clone();
+    // Re-register whenever a watcher shows up, e.g. after the panel restarts;
+    // watched for the lifetime of the application
+    gio::bus_watch_name(
+        BusType::Session,
+        WATCHER_NAME,
+        BusNameWatcherFlags::NONE,
+        move |connection, _, _| {
+            watched.set(true);
+            if acquired.get() {
+                register_with_watcher(&connection, &name);
+            }
+        },
+        move |_, _| gone.set(false),
+    );
+    owner
+}
+
+fn register_with_watcher(connection: &DBusConnection, name: &str) {
+    connection.call(
+        Some(WATCHER_NAME),
+        "/StatusNotifierWatcher",
+        WATCHER_NAME,
+        "RegisterStatusNotifierItem",
+        Some(&(name,).to_variant()),
+        None,
+        DBusCallFlags::NONE,
+        -1,
+        gio::Cancellable::NONE,
+        |result| {
+            if let Err(e) = result {
+                eprintln!("Tray registration failed: {}", e);
+            }
+        },
+    );
+}
+
+impl Tray {
+    fn export(&self, connection: &DBusConnection) -> Result<(), glib::Error> {
+        let node = DBusNodeInfo::for_xml(INTROSPECTION_XML)?;
+        let item_interface = node.lookup_interface(ITEM_INTERFACE).expect("interface is in the XML");
+        let menu_interface = node.lookup_interface(MENU_INTERFACE).expect("interface is in the XML");
+
+        let tray = self.clone();
+        let tray_props = self.clone();
+        connection
+            .register_object(ITEM_PATH, &item_interface)
+            .method_call(move |_, _, _, _, method, _, invocation| {
+                if method == "Activate" {
+                    (tray.open_settings)();
+                }
+                invocation.return_value(None);
+            })
+            .property(move |_, _, _, _, property| tray_props.item_property(property))
+            .build()?;
+
+        let tray = self.clone();
+        connection
+            .register_object(MENU_PATH, &menu_interface)
+            .method_call(move |_, _, _, _, method, parameters, invocation| {
+                let reply = tray.menu_call(method, &parameters);
+                invocation.return_value(reply.as_ref());
+            })
+            .property(|_, _, _, _, property| match property {
+                "Version" => 3u32.to_variant(),
+                "TextDirection" => "ltr".to_variant(),
+                "Status" => "normal".to_variant(),
+                _ => Vec::<String>::new().to_variant(),
+            })
+            .build()?;
+
+        // Refresh icon, menu and tooltip when the session changes; the tooltip
+        // names the time of the next shift, so it only changes with an event
+        let weak_connection = connection.downgrade();
+        let tray = self.clone();
+        self.scheduler.connect_event(move |event| {
+            let Some(connection) = weak_connection.upgrade() else { return };
+            if matches!(event, SchedulerEvent::StateChanged | SchedulerEvent::DisplaysChanged) {
+                tray.revision.set(tray.revision.get() + 1);
+                let _ = connection.emit_signal(None, MENU_PATH, MENU_INTERFACE, "LayoutUpdated", Some(&(tray.revision.get(), 0i32).to_variant()));
+                let _ = connection.emit_signal(None, ITEM_PATH, ITEM_INTERFACE, "NewOverlayIcon", None);
+            }
+            let _ = connection.emit_signal(None, ITEM_PATH, ITEM_INTERFACE, "NewToolTip", None);
+        });
+
+        Ok(())
+    }
+
+    fn item_property(&self, property: &str) -> Variant {
+        match property {
+            "Category" => "ApplicationStatus".to_variant(),
+            "Id" => "pixelshift-gtk".to_variant(),
+            "Title" => "OLED Pixel Shifter".to_variant(),
+            "Status" => "Active".to_variant(),
+            "IconName" => "video-display".to_variant(),
+            "OverlayIconName" => {
+                if self.scheduler.is_running() {
+                    "media-playback-start"
+                } else if self.scheduler.is_paused() {
+                    "media-playback-pause"
+                } else {
+                    ""
+                }
+            }
+            .to_variant(),
+            "ToolTip" => {
+                let pixmaps: Vec<(i32, i32, Vec<u8>)> = Vec::new();
+                ("video-display", pixmaps, "OLED Pixel Shifter", self.tooltip()).to_variant()
+            }
+            "ItemIsMenu" => false.to_variant(),
+            "Menu" => glib::variant::ObjectPath::try_from(MENU_PATH.to_string()).expect("valid object path").to_variant(),
+            _ => "".to_variant(),
+        }
+    }
+
+    fn tooltip(&self) -> String {
+        let Some(session) = self.scheduler.session() else {
+            return "Not running".to_string();
+        };
+        let (x, y) = session.current_offset;
+
+        if !self.scheduler.is_running() {
+            return format!("Paused on {}, offset {:+}{:+}", session.display.name, x, y);
+        }
+
+        let next = self
+            .scheduler
+            .next_shift_time()
+            .and_then(|t| glib::DateTime::from_unix_local(t as i64).ok())
+            .and_then(|t| t.format("%H:%M:%S").ok())
+            .map(|t| format!("\nNext shift at {}", t))
+            .unwrap_or_default();
+        format!("Shifting {}, offset {:+}{:+}{}", session.display.name, x, y, next)
+    }
+
+    fn menu_call(&self, method: &str, parameters: &Variant) -> Option<Variant> {
+        match method {
+            "GetLayout" => Some((self.revision.get(), self.layout()).to_variant()),
+            "GetGroupProperties" => {
+                let (ids, _) = parameters.get::<(Vec<i32>, Vec<String>)>().unwrap_or_default();
+                let items: Vec<(i32, HashMap<String, Variant>)> = MENU_ITEMS
+                    .iter()
+                    .filter(|(id, _)| ids.is_empty() || ids.contains(id))
+                    .map(|(id, action)| (*id, self.item_properties(*action)))
+                    .collect();
+                Some((items,).to_variant())
+            }
+            "GetProperty" => {
+                let (id, name) = parameters.get::<(i32, String)>().unwrap_or_default();
+                let action = MENU_ITEMS.iter().find(|(i, _)| *i == id).and_then(|(_, a)| *a);
+                let value = self.item_properties(action).remove(&name).unwrap_or_else(|| "".to_variant());
+                Some((value,).to_variant())
+            }
+            "Event" => {
+                if let Some((id, event_id, _, _)) = parameters.get::<(i32, String, Variant, u32)>() {
+                    if event_id == "clicked" {
+                        self.activate(id);
+                    }
+                }
+                None
+            }
+            "EventGroup" => {
+                let events = parameters.get::<(Vec<(i32, String, Variant, u32)>,)>().map(|(e,)| e).unwrap_or_default();
+                for (id, event_id, _, _) in events {
+                    if event_id == "clicked" {
+                        self.activate(id);
+                    }
+                }
+                Some((Vec::<i32>::new(),).to_variant())
+            }
+            "AboutToShow" => Some((false,).to_variant()),
+            "AboutToShowGroup" => Some((Vec::<i32>::new(), Vec::<i32>::new()).to_variant()),
+            _ => None,
+        }
+    }
+
+    /// Root item with the actions as children, in dbusmenu's (ia{sv}av) layout
+    fn layout(&self) -> Variant {
+        let children: Vec<Variant> = MENU_ITEMS
+            .iter()
+            .map(|(id, action)| {
+                let item = Variant::tuple_from_iter([
+                    id.to_variant(),
+                    self.item_properties(*action).to_variant(),
+                    Variant::array_from_iter_with_type(VariantTy::VARIANT, Vec::<Variant>::new()),
+                ]);
+                Variant::from_variant(&item)
+            })
+            .collect();
+
+        let mut root = HashMap::new();
+        root.insert("children-display".to_string(), "submenu".to_variant());
+        Variant::tuple_from_iter([
+            0i32.to_variant(),
+            root.to_variant(),
+            Variant::array_from_iter_with_type(VariantTy::VARIANT, children),
+        ])
+    }
+
+    fn item_properties(&self, action: Option<MenuAction>) -> HashMap<String, Variant> {
+        let mut props = HashMap::new();
+        let Some(action) = action else {
+            props.insert("type".to_string(), "separator".to_variant());
+            return props;
+        };
+
+        let has_session = self.scheduler.session().is_some();
+        let running = self.scheduler.is_running();
+        let (label, enabled) = match action {
+            MenuAction::Start if self.scheduler.is_paused() => ("Resume", true),
+            MenuAction::Start => ("Start", !has_session),
+            MenuAction::Pause => ("Pause", running),
+            // Shifting while paused would move the display behind the pause
+            MenuAction::ShiftNow => ("Shift Now", running),
+            MenuAction::Reset => ("Stop & Reset", true),
+            MenuAction::OpenSettings => ("Open Settings", true),
+            MenuAction::Quit => ("Quit", true),
+        };
+        props.insert("label".to_string(), label.to_variant());
+        props.insert("enabled".to_string(), enabled.to_variant());
+        props
+    }
+
+    fn activate(&self, id: i32) {
+        let Some(action) = MENU_ITEMS.iter().find(|(i, _)| *i == id).and_then(|(_, a)| *a) else { return };
+
+        match action {
+            MenuAction::Start if self.scheduler.is_paused() => {
+                self.scheduler.resume();
+            }
+            MenuAction::Start => {
+                if let Ok(display) = self.scheduler.preferred_display() {
+                    self.scheduler.start(AutoShiftSession::from_profile(display, self.scheduler.status()));
+                }
+            }
+            MenuAction::Pause => {
+                self.scheduler.pause();
+            }
+            MenuAction::ShiftNow if self.scheduler.is_running() => self.scheduler.shift_now(),
+            MenuAction::ShiftNow => {}
+            MenuAction::Reset => {
+                self.scheduler.stop();
+            }
+            MenuAction::OpenSettings => (self.open_settings)(),
+            MenuAction::Quit => (self.quit)(),
+        }
+    }
+}